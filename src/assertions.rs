@@ -0,0 +1,348 @@
+use crate::content::Body;
+use crate::structs::{Assertion, AssertionResult};
+use crate::utils;
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+use std::time::Duration;
+
+pub fn describe(assertion: &Assertion) -> String {
+    match assertion {
+        Assertion::Status { equals } => format!("status equals {}", equals),
+        Assertion::Header {
+            name,
+            equals,
+            contains,
+            matches,
+        } => match (equals, contains, matches) {
+            (Some(expected), _, _) => format!("header {} equals '{}'", name, expected),
+            (_, Some(expected), _) => format!("header {} contains '{}'", name, expected),
+            (_, _, Some(pattern)) => format!("header {} matches /{}/", name, pattern),
+            _ => format!("header {} is present", name),
+        },
+        Assertion::JsonPath {
+            path,
+            equals,
+            contains,
+            matches,
+            exists,
+        } => match (equals, contains, matches, exists) {
+            (Some(expected), _, _, _) => format!("{} equals {}", path, expected),
+            (_, Some(expected), _, _) => format!("{} contains {}", path, expected),
+            (_, _, Some(pattern), _) => format!("{} matches /{}/", path, pattern),
+            (_, _, _, Some(false)) => format!("{} does not exist", path),
+            _ => format!("{} exists", path),
+        },
+        Assertion::ResponseTime { less_than_ms } => {
+            format!("response time is under {} ms", less_than_ms)
+        }
+        Assertion::JsonSchema { .. } => "body matches JSON schema".to_string(),
+    }
+}
+
+fn value_as_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn check_matches(pattern: &str, actual: &str) -> Result<(), String> {
+    let regex = Regex::new(pattern).map_err(|err| format!("invalid pattern: {}", err))?;
+    if regex.is_match(actual) {
+        Ok(())
+    } else {
+        Err(format!("'{}' does not match /{}/", actual, pattern))
+    }
+}
+
+fn check_header(
+    headers: &HeaderMap,
+    name: &str,
+    equals: &Option<String>,
+    contains: &Option<String>,
+    matches: &Option<String>,
+) -> Result<(), String> {
+    let Some(value) = headers.get(name) else {
+        return Err(format!("header {} is missing", name));
+    };
+    let actual = value.to_str().unwrap_or("");
+    if let Some(expected) = equals {
+        if actual != expected {
+            return Err(format!("expected '{}' but got '{}'", expected, actual));
+        }
+    }
+    if let Some(expected) = contains {
+        if !actual.contains(expected.as_str()) {
+            return Err(format!("'{}' does not contain '{}'", actual, expected));
+        }
+    }
+    if let Some(pattern) = matches {
+        check_matches(pattern, actual)?;
+    }
+    Ok(())
+}
+
+fn json_contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::String(actual), Value::String(expected)) => actual.contains(expected.as_str()),
+        (Value::Array(items), _) => items.contains(expected),
+        (Value::Object(actual), Value::Object(expected)) => expected
+            .iter()
+            .all(|(key, value)| actual.get(key) == Some(value)),
+        _ => false,
+    }
+}
+
+fn check_json_path(
+    body: &Body,
+    path: &str,
+    equals: &Option<Value>,
+    contains: &Option<Value>,
+    matches: &Option<String>,
+    exists: &Option<bool>,
+) -> Result<(), String> {
+    let Body::Json(json) = body else {
+        return Err("response body is not JSON".to_string());
+    };
+    let value = utils::get_json_value(json, path);
+    if let Some(false) = exists {
+        return match value {
+            Some(value) => Err(format!("found {}", value)),
+            None => Ok(()),
+        };
+    }
+    let Some(actual) = value else {
+        return Err(format!("{} was not found", path));
+    };
+    if let Some(expected) = equals {
+        if actual != expected {
+            return Err(format!("expected {} but got {}", expected, actual));
+        }
+    }
+    if let Some(expected) = contains {
+        if !json_contains(actual, expected) {
+            return Err(format!("{} does not contain {}", actual, expected));
+        }
+    }
+    if let Some(pattern) = matches {
+        check_matches(pattern, &value_as_text(actual))?;
+    }
+    Ok(())
+}
+
+fn check_json_schema(body: &Body, schema: &Value) -> Result<(), String> {
+    let Body::Json(json) = body else {
+        return Err("response body is not JSON".to_string());
+    };
+    let compiled = jsonschema::JSONSchema::compile(schema)
+        .map_err(|err| format!("invalid schema: {}", err))?;
+    let result = compiled.validate(json);
+    if let Err(errors) = result {
+        let messages = errors
+            .map(|err| match err.instance_path.to_string().as_str() {
+                "" => err.to_string(),
+                location => format!("{} at '{}'", err, location),
+            })
+            .collect::<Vec<String>>();
+        return Err(messages.join("; "));
+    }
+    Ok(())
+}
+
+fn check(
+    assertion: &Assertion,
+    status_code: u16,
+    headers: &HeaderMap,
+    body: &Body,
+    duration: Duration,
+) -> Result<(), String> {
+    match assertion {
+        Assertion::Status { equals } => {
+            if status_code == *equals {
+                Ok(())
+            } else {
+                Err(format!("got {}", status_code))
+            }
+        }
+        Assertion::Header {
+            name,
+            equals,
+            contains,
+            matches,
+        } => check_header(headers, name, equals, contains, matches),
+        Assertion::JsonPath {
+            path,
+            equals,
+            contains,
+            matches,
+            exists,
+        } => check_json_path(body, path, equals, contains, matches, exists),
+        Assertion::ResponseTime { less_than_ms } => {
+            if duration.as_millis() < *less_than_ms as u128 {
+                Ok(())
+            } else {
+                Err(format!("took {} ms", duration.as_millis()))
+            }
+        }
+        Assertion::JsonSchema { schema } => check_json_schema(body, schema),
+    }
+}
+
+pub fn evaluate(
+    assertions: &[Assertion],
+    status_code: u16,
+    headers: &HeaderMap,
+    body: &Body,
+    duration: Duration,
+) -> Vec<AssertionResult> {
+    assertions
+        .iter()
+        .map(|assertion| {
+            let result = check(assertion, status_code, headers, body, duration);
+            AssertionResult {
+                assertion: describe(assertion),
+                passed: result.is_ok(),
+                message: result.err(),
+            }
+        })
+        .collect()
+}
+
+/// Prints the report to stderr so the JSON response on stdout can still be piped.
+pub fn print_report(results: &[AssertionResult]) {
+    let failed = results.iter().filter(|result| !result.passed).count();
+    eprintln!("\x1b[94mAssertions:\x1b[0m");
+    for result in results {
+        match &result.message {
+            None => eprintln!("  \x1b[32m✓\x1b[0m {}", result.assertion),
+            Some(message) => eprintln!("  \x1b[31m✗\x1b[0m {} ({})", result.assertion, message),
+        }
+    }
+    eprintln!("{} passed, {} failed", results.len() - failed, failed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn evaluate_json(assertions: Value, body: &Body) -> Vec<(String, Option<String>)> {
+        let assertions: Vec<Assertion> = serde_json::from_value(assertions).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "content-type",
+            "application/json; charset=utf-8".parse().unwrap(),
+        );
+        evaluate(&assertions, 201, &headers, body, Duration::from_millis(120))
+            .into_iter()
+            .map(|result| {
+                assert_eq!(result.passed, result.message.is_none());
+                (result.assertion, result.message)
+            })
+            .collect()
+    }
+
+    fn user() -> Body {
+        Body::Json(json!({
+            "id": 7,
+            "name": "Ada Lovelace",
+            "roles": ["admin", "dev"],
+            "address": { "city": "London", "zip": "N1" }
+        }))
+    }
+
+    #[test]
+    fn passes_matching_assertions() {
+        let results = evaluate_json(
+            json!([
+                { "type": "status", "equals": 201 },
+                { "type": "header", "name": "Content-Type", "contains": "json" },
+                { "type": "header", "name": "content-type", "matches": "^application/" },
+                { "type": "json_path", "path": "$.id", "equals": 7 },
+                { "type": "json_path", "path": "name", "contains": "Ada" },
+                { "type": "json_path", "path": "roles", "contains": "dev" },
+                { "type": "json_path", "path": "roles[1]", "matches": "^d" },
+                { "type": "json_path", "path": "address", "contains": { "city": "London" } },
+                { "type": "json_path", "path": "deletedAt", "exists": false },
+                { "type": "response_time", "less_than_ms": 500 },
+                {
+                    "type": "json_schema",
+                    "schema": { "type": "object", "required": ["id", "name"] }
+                }
+            ]),
+            &user(),
+        );
+        let failed: Vec<_> = results
+            .iter()
+            .filter(|(_, message)| message.is_some())
+            .collect();
+        assert!(failed.is_empty(), "{:?}", failed);
+    }
+
+    #[test]
+    fn reports_why_assertions_failed() {
+        let results = evaluate_json(
+            json!([
+                { "type": "status", "equals": 200 },
+                { "type": "header", "name": "ETag" },
+                { "type": "header", "name": "Content-Type", "equals": "text/plain" },
+                { "type": "json_path", "path": "$.id", "equals": "7" },
+                { "type": "json_path", "path": "roles", "contains": "root" },
+                { "type": "json_path", "path": "address.country" },
+                { "type": "json_path", "path": "id", "exists": false },
+                { "type": "json_path", "path": "name", "matches": "(" },
+                { "type": "response_time", "less_than_ms": 100 },
+                {
+                    "type": "json_schema",
+                    "schema": { "properties": { "id": { "type": "string" } } }
+                }
+            ]),
+            &user(),
+        );
+        let messages: Vec<(&str, &str)> = results
+            .iter()
+            .map(|(assertion, message)| (assertion.as_str(), message.as_deref().unwrap_or("")))
+            .collect();
+        assert_eq!(messages[0], ("status equals 200", "got 201"));
+        assert_eq!(
+            messages[1],
+            ("header ETag is present", "header ETag is missing")
+        );
+        assert_eq!(
+            messages[2].1,
+            "expected 'text/plain' but got 'application/json; charset=utf-8'"
+        );
+        assert_eq!(
+            messages[3],
+            ("$.id equals \"7\"", "expected \"7\" but got 7")
+        );
+        assert_eq!(
+            messages[4].1,
+            "[\"admin\",\"dev\"] does not contain \"root\""
+        );
+        assert_eq!(messages[5].1, "address.country was not found");
+        assert_eq!(messages[6], ("id does not exist", "found 7"));
+        assert!(
+            messages[7].1.starts_with("invalid pattern:"),
+            "{}",
+            messages[7].1
+        );
+        assert_eq!(messages[8].1, "took 120 ms");
+        assert!(messages[9].1.contains("at '/id'"), "{}", messages[9].1);
+    }
+
+    #[test]
+    fn fails_json_assertions_on_other_bodies() {
+        let results = evaluate_json(
+            json!([
+                { "type": "json_path", "path": "id", "exists": true },
+                { "type": "json_schema", "schema": {} }
+            ]),
+            &Body::Text("id=7".to_string()),
+        );
+        for (_, message) in results {
+            assert_eq!(message.as_deref(), Some("response body is not JSON"));
+        }
+    }
+}
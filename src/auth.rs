@@ -0,0 +1,417 @@
+//! The `auth` of requests: the collection default, adding the credentials to a request and
+//! answering digest challenges.
+
+use crate::structs::{ApiKeyLocation, Auth, GlobalVariable, HttpRequest, OAuth2, OAuth2Grant};
+use crate::{oauth2, utils};
+use base64::Engine;
+use md5::Md5;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The auth of requests that don't set their own, from `~/.xhtp/auth.json`.
+pub fn read_default() -> Option<Auth> {
+    let path = utils::get_auth_file_path();
+    let content = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(auth) => Some(auth),
+        Err(err) => {
+            eprintln!("Failed to parse the default auth {}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Saves the default auth, or removes it.
+pub fn save_default(auth: Option<&Auth>) -> Result<(), String> {
+    let path = utils::get_auth_file_path();
+    match auth {
+        Some(auth) => fs::write(&path, serde_json::to_string_pretty(auth).unwrap()),
+        None if fs::metadata(&path).is_ok() => fs::remove_file(&path),
+        None => Ok(()),
+    }
+    .map_err(|err| format!("Failed to write {}: {}", path, err))
+}
+
+/// The request with the default auth if it doesn't have its own.
+pub fn with_default(request: &HttpRequest) -> HttpRequest {
+    let mut request = request.clone();
+    if request.auth.is_none() {
+        request.auth = read_default();
+    }
+    request
+}
+
+/// Maps the values of the auth. Secrets come with the name of their field, other values with `None`.
+pub fn map_values(auth: &Auth, mut map: impl FnMut(&str, Option<&str>) -> String) -> Auth {
+    match auth {
+        Auth::None => Auth::None,
+        Auth::Basic { username, password } => Auth::Basic {
+            username: map(username, None),
+            password: map(password, Some("password")),
+        },
+        Auth::Digest { username, password } => Auth::Digest {
+            username: map(username, None),
+            password: map(password, Some("password")),
+        },
+        Auth::Bearer { token } => Auth::Bearer {
+            token: map(token, Some("token")),
+        },
+        Auth::ApiKey {
+            name,
+            value,
+            location,
+        } => Auth::ApiKey {
+            name: map(name, None),
+            value: map(value, Some("api_key")),
+            location: *location,
+        },
+        Auth::OAuth2(config) => Auth::OAuth2(OAuth2 {
+            token_url: map(&config.token_url, None),
+            client_id: map(&config.client_id, None),
+            client_secret: map(&config.client_secret, Some("client_secret")),
+            scope: map(&config.scope, None),
+            username: map(&config.username, None),
+            password: map(&config.password, Some("password")),
+            refresh_token: map(&config.refresh_token, Some("refresh_token")),
+            auth_url: map(&config.auth_url, None),
+            redirect_uri: map(&config.redirect_uri, None),
+            device_url: map(&config.device_url, None),
+            ..config.clone()
+        }),
+        Auth::AwsSigv4 {
+            access_key_id,
+            secret_access_key,
+            session_token,
+            region,
+            service,
+        } => Auth::AwsSigv4 {
+            access_key_id: map(access_key_id, None),
+            secret_access_key: map(secret_access_key, Some("secret_access_key")),
+            session_token: map(session_token, Some("session_token")),
+            region: map(region, None),
+            service: map(service, None),
+        },
+        Auth::Hmac {
+            secret,
+            template,
+            header,
+            header_value,
+            timestamp_header,
+            timestamp_format,
+            encoding,
+        } => Auth::Hmac {
+            secret: map(secret, Some("secret")),
+            template: map(template, None),
+            header: map(header, None),
+            header_value: map(header_value, None),
+            timestamp_header: map(timestamp_header, None),
+            timestamp_format: *timestamp_format,
+            encoding: *encoding,
+        },
+    }
+}
+
+pub fn resolve(auth: &Auth, variables: &[GlobalVariable]) -> Auth {
+    map_values(auth, |value, _| utils::replace_variables(value, variables))
+}
+
+/// Masks the password, token or key, leaving user and key names readable.
+pub fn mask(auth: &Auth, mask_value: fn(&str) -> String) -> Auth {
+    map_values(auth, |value, secret| match secret {
+        Some(_) => mask_value(value),
+        None => value.to_string(),
+    })
+}
+
+pub fn describe(auth: &Auth) -> String {
+    match auth {
+        Auth::None => "none".to_string(),
+        Auth::Basic { username, .. } => format!("basic as {}", username),
+        Auth::Digest { username, .. } => format!("digest as {}", username),
+        Auth::Bearer { .. } => "bearer token".to_string(),
+        Auth::ApiKey { name, location, .. } => match location {
+            ApiKeyLocation::Header => format!("api key in the {} header", name),
+            ApiKeyLocation::Query => format!("api key in the {} query parameter", name),
+        },
+        Auth::OAuth2(config) => {
+            let grant = match config.grant {
+                OAuth2Grant::ClientCredentials => "client credentials",
+                OAuth2Grant::Password => "password",
+                OAuth2Grant::RefreshToken => "refresh token",
+                OAuth2Grant::AuthorizationCode => "authorization code",
+                OAuth2Grant::DeviceCode => "device code",
+            };
+            format!("OAuth 2.0 {} grant from {}", grant, config.token_url)
+        }
+        Auth::AwsSigv4 { service, .. } => format!("AWS SigV4 for {}", service),
+        Auth::Hmac { header, .. } => match header.as_str() {
+            "" => "HMAC-SHA256 signature".to_string(),
+            header => format!("HMAC-SHA256 signature in the {} header", header),
+        },
+    }
+}
+
+pub fn basic_header(username: &str, password: &str) -> String {
+    let credentials = format!("{}:{}", username, password);
+    format!(
+        "Authorization: Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(credentials)
+    )
+}
+
+/// Moves bearer tokens and api keys into the headers and url, for exports that write them
+/// as such. Basic and digest auth are left for the exports to write in their own way.
+/// OAuth 2.0 becomes a bearer token with an `{{access_token}}` placeholder.
+pub fn move_to_headers(request: &HttpRequest) -> HttpRequest {
+    let mut request = request.clone();
+    match request.auth.take() {
+        Some(Auth::Bearer { token }) => {
+            request
+                .headers
+                .push(format!("Authorization: Bearer {}", token));
+        }
+        Some(Auth::ApiKey {
+            name,
+            value,
+            location: ApiKeyLocation::Header,
+        }) => request.headers.push(format!("{}: {}", name, value)),
+        Some(Auth::ApiKey {
+            name,
+            value,
+            location: ApiKeyLocation::Query,
+        }) => {
+            request
+                .url
+                .push(if request.url.contains('?') { '&' } else { '?' });
+            request.url.push_str(&format!("{}={}", name, value));
+        }
+        Some(Auth::OAuth2(_)) => request
+            .headers
+            .push("Authorization: Bearer {{access_token}}".to_string()),
+        Some(Auth::None) | None => {}
+        auth => request.auth = auth,
+    }
+    request
+}
+
+/// Swaps the OAuth 2.0 auth of a resolved request for its cached access token, if any, so
+/// that an export runs as it is.
+pub fn use_cached_token(request: &mut HttpRequest) {
+    if let Some(Auth::OAuth2(config)) = &request.auth {
+        if let Some(token) = oauth2::cached_access_token(config) {
+            request.auth = Some(Auth::Bearer { token });
+        }
+    }
+}
+
+/// Adds the credentials of a resolved request. Digest auth is only sent once challenged, and
+/// OAuth 2.0 tokens and signatures are added by `client::send`.
+pub fn apply(builder: reqwest::RequestBuilder, auth: Option<&Auth>) -> reqwest::RequestBuilder {
+    match auth {
+        Some(Auth::Basic { username, password }) => builder.basic_auth(username, Some(password)),
+        Some(Auth::Bearer { token }) => builder.bearer_auth(token),
+        Some(Auth::ApiKey {
+            name,
+            value,
+            location: ApiKeyLocation::Header,
+        }) => builder.header(name.as_str(), value.as_str()),
+        Some(Auth::ApiKey {
+            name,
+            value,
+            location: ApiKeyLocation::Query,
+        }) => builder.query(&[(name, value)]),
+        _ => builder,
+    }
+}
+
+fn hash(algorithm: &str, text: &str) -> String {
+    if algorithm.starts_with("SHA-256") {
+        format!("{:x}", Sha256::digest(text.as_bytes()))
+    } else {
+        format!("{:x}", Md5::digest(text.as_bytes()))
+    }
+}
+
+fn get_cnonce() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    hash("MD5", &format!("{}:{}", nanos, std::process::id()))[..16].to_string()
+}
+
+/// Answers a `WWW-Authenticate: Digest` challenge (RFC 7616) with MD5 or SHA-256, with
+/// `qop=auth` or without a qop.
+pub fn get_digest_authorization(
+    challenges: &[&str],
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+) -> Result<String, String> {
+    answer_digest_challenge(challenges, username, password, method, uri, &get_cnonce())
+}
+
+fn answer_digest_challenge(
+    challenges: &[&str],
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    cnonce: &str,
+) -> Result<String, String> {
+    let challenge = challenges
+        .iter()
+        .find_map(|challenge| {
+            let (scheme, parameters) = challenge.trim().split_once(' ')?;
+            scheme.eq_ignore_ascii_case("digest").then_some(parameters)
+        })
+        .ok_or("The server did not send a digest challenge")?;
+    let parameter_pattern = Regex::new(r#"(\w+)\s*=\s*(?:"([^"]*)"|([^\s,]+))"#).unwrap();
+    let parameter = |name: &str| {
+        parameter_pattern
+            .captures_iter(challenge)
+            .find_map(|captures| {
+                captures[1].eq_ignore_ascii_case(name).then(|| {
+                    captures
+                        .get(2)
+                        .or_else(|| captures.get(3))
+                        .map(|value| value.as_str().to_string())
+                        .unwrap_or_default()
+                })
+            })
+    };
+    let realm = parameter("realm").unwrap_or_default();
+    let nonce = parameter("nonce").ok_or("The digest challenge has no nonce")?;
+    let algorithm = parameter("algorithm").unwrap_or_else(|| "MD5".to_string());
+    let algorithm = algorithm.to_uppercase();
+    if !["MD5", "MD5-SESS", "SHA-256", "SHA-256-SESS"].contains(&algorithm.as_str()) {
+        return Err(format!(
+            "The digest algorithm {} is not supported",
+            algorithm
+        ));
+    }
+    let qop = match parameter("qop") {
+        Some(qop) if qop.split(',').any(|qop| qop.trim() == "auth") => Some("auth"),
+        Some(qop) => return Err(format!("The digest qop {} is not supported", qop)),
+        None => None,
+    };
+    let nc = "00000001";
+
+    let mut ha1 = hash(&algorithm, &format!("{}:{}:{}", username, realm, password));
+    if algorithm.ends_with("-SESS") {
+        ha1 = hash(&algorithm, &format!("{}:{}:{}", ha1, nonce, cnonce));
+    }
+    let ha2 = hash(&algorithm, &format!("{}:{}", method, uri));
+    let response = match qop {
+        Some(qop) => hash(
+            &algorithm,
+            &format!("{}:{}:{}:{}:{}:{}", ha1, nonce, nc, cnonce, qop, ha2),
+        ),
+        None => hash(&algorithm, &format!("{}:{}:{}", ha1, nonce, ha2)),
+    };
+
+    let mut authorization = format!(
+        "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+        username, realm, nonce, uri, algorithm, response
+    );
+    if let Some(qop) = qop {
+        authorization.push_str(&format!(", qop={}, nc={}, cnonce=\"{}\"", qop, nc, cnonce));
+    }
+    if let Some(opaque) = parameter("opaque") {
+        authorization.push_str(&format!(", opaque=\"{}\"", opaque));
+    }
+    Ok(authorization)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example of RFC 7616 section 3.9.1.
+    const CHALLENGE: &str = "realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+        nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+        opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+    const CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    fn answer(algorithm: &str) -> String {
+        let challenge = format!("Digest {}, algorithm={}", CHALLENGE, algorithm);
+        answer_digest_challenge(
+            &["Basic realm=\"other\"", &challenge],
+            "Mufasa",
+            "Circle of Life",
+            "GET",
+            "/dir/index.html",
+            CNONCE,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn answers_the_rfc_7616_example_with_sha_256() {
+        assert_eq!(
+            answer("SHA-256"),
+            "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", \
+             nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", uri=\"/dir/index.html\", \
+             algorithm=SHA-256, \
+             response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\", \
+             qop=auth, nc=00000001, cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", \
+             opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""
+        );
+    }
+
+    #[test]
+    fn answers_the_rfc_7616_example_with_md5() {
+        assert!(answer("MD5").contains("response=\"8ca523f5e9506fed4657c9700eebdbec\""));
+    }
+
+    #[test]
+    fn hashes_the_session_key_for_sess_algorithms() {
+        let ha1 = hash("MD5", "Mufasa:http-auth@example.org:Circle of Life");
+        let ha1 = hash(
+            "MD5",
+            &format!(
+                "{}:7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v:{}",
+                ha1, CNONCE
+            ),
+        );
+        let ha2 = hash("MD5", "GET:/dir/index.html");
+        let response = hash(
+            "MD5",
+            &format!(
+                "{}:7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v:00000001:{}:auth:{}",
+                ha1, CNONCE, ha2
+            ),
+        );
+        assert!(answer("MD5-sess").contains(&format!("response=\"{}\"", response)));
+    }
+
+    #[test]
+    fn answers_without_qop_when_the_challenge_has_none() {
+        let authorization = answer_digest_challenge(
+            &["Digest realm=\"testrealm@host.com\", nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\""],
+            "Mufasa",
+            "Circle Of Life",
+            "GET",
+            "/dir/index.html",
+            CNONCE,
+        )
+        .unwrap();
+        // MD5(HA1:nonce:HA2), as in RFC 2069.
+        assert!(authorization.contains("response=\"670fd8c2df070c60b045671b8b24ff02\""));
+        assert!(!authorization.contains("cnonce"));
+    }
+
+    #[test]
+    fn rejects_unsupported_challenges() {
+        let answer = |challenge: &str| {
+            answer_digest_challenge(&[challenge], "user", "password", "GET", "/", CNONCE)
+        };
+        assert!(answer("Basic realm=\"x\"").is_err());
+        assert!(answer("Digest realm=\"x\"").is_err());
+        assert!(answer("Digest nonce=\"n\", algorithm=SHA-512-256").is_err());
+        assert!(answer("Digest nonce=\"n\", qop=\"auth-int\"").is_err());
+    }
+}
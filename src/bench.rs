@@ -0,0 +1,211 @@
+use crate::client::{self, SendError};
+use crate::structs::HttpRequest;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// Number of requests sent when neither a count nor a duration is given.
+const DEFAULT_REQUEST_COUNT: u64 = 100;
+
+pub struct BenchOptions {
+    pub requests: Option<u64>,
+    pub duration: Option<u64>,
+    pub concurrency: usize,
+    pub rate: Option<u64>,
+}
+
+enum Sample {
+    Response(u16, Duration),
+    Error(String),
+}
+
+pub struct BenchResult {
+    pub elapsed: Duration,
+    pub latencies_ms: Vec<f64>,
+    pub status_codes: BTreeMap<u16, u64>,
+    pub errors: BTreeMap<String, u64>,
+}
+
+fn get_error_kind(err: &reqwest::Error) -> String {
+    if err.is_timeout() {
+        "timeout".to_string()
+    } else if err.is_connect() {
+        "connection error".to_string()
+    } else if err.is_redirect() {
+        "too many redirects".to_string()
+    } else if err.is_body() || err.is_decode() {
+        "body error".to_string()
+    } else {
+        "request error".to_string()
+    }
+}
+
+async fn send(client: &reqwest::Client, request: &HttpRequest) -> Sample {
+    let started = Instant::now();
+    let response = match client::send(client, request).await {
+        Ok(response) => response,
+        Err(SendError::Http(err)) => return Sample::Error(get_error_kind(&err)),
+        Err(SendError::Request(message)) => return Sample::Error(message),
+    };
+    let status_code = response.status().as_u16();
+    match response.bytes().await {
+        Ok(_) => Sample::Response(status_code, started.elapsed()),
+        Err(err) => Sample::Error(get_error_kind(&err)),
+    }
+}
+
+/// Sends an already resolved request repeatedly, `options.concurrency` at a time,
+/// until the request count or duration is reached, optionally capped at `options.rate` per second.
+pub async fn run(
+    client: &reqwest::Client,
+    request: &HttpRequest,
+    options: &BenchOptions,
+) -> BenchResult {
+    let limit = match (options.requests, options.duration) {
+        (None, None) => Some(DEFAULT_REQUEST_COUNT),
+        (requests, _) => requests,
+    };
+    let started = Instant::now();
+    let deadline = options
+        .duration
+        .map(|duration| started + Duration::from_secs(duration));
+    let issued = Arc::new(AtomicU64::new(0));
+    // Ticks missed while every worker waits on a slow response are not made up in a burst.
+    let ticker: Option<Arc<Mutex<Interval>>> = options.rate.map(|rate| {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / rate as f64));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Arc::new(Mutex::new(interval))
+    });
+
+    let mut workers = JoinSet::new();
+    for _ in 0..options.concurrency.max(1) {
+        let client = client.clone();
+        let request = request.clone();
+        let issued = issued.clone();
+        let ticker = ticker.clone();
+        workers.spawn(async move {
+            let mut samples = Vec::new();
+            loop {
+                if let Some(ticker) = &ticker {
+                    ticker.lock().await.tick().await;
+                }
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    break;
+                }
+                if limit.is_some_and(|limit| issued.fetch_add(1, Ordering::SeqCst) >= limit) {
+                    break;
+                }
+                samples.push(send(&client, &request).await);
+            }
+            samples
+        });
+    }
+
+    let mut result = BenchResult {
+        elapsed: Duration::ZERO,
+        latencies_ms: Vec::new(),
+        status_codes: BTreeMap::new(),
+        errors: BTreeMap::new(),
+    };
+    while let Some(worker) = workers.join_next().await {
+        for sample in worker.expect("A bench worker panicked") {
+            match sample {
+                Sample::Response(status_code, latency) => {
+                    *result.status_codes.entry(status_code).or_insert(0) += 1;
+                    result.latencies_ms.push(latency.as_secs_f64() * 1000.0);
+                }
+                Sample::Error(kind) => *result.errors.entry(kind).or_insert(0) += 1,
+            }
+        }
+    }
+    result.elapsed = started.elapsed();
+    result
+        .latencies_ms
+        .sort_by(|a, b| a.partial_cmp(b).unwrap());
+    result
+}
+
+impl BenchResult {
+    fn total(&self) -> u64 {
+        self.latencies_ms.len() as u64 + self.errors.values().sum::<u64>()
+    }
+
+    fn throughput(&self) -> f64 {
+        self.total() as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Nearest-rank percentile of the successful response latencies.
+    fn percentile(&self, percent: f64) -> f64 {
+        if self.latencies_ms.is_empty() {
+            return 0.0;
+        }
+        let rank = (percent / 100.0 * self.latencies_ms.len() as f64).ceil() as usize;
+        self.latencies_ms[rank.clamp(1, self.latencies_ms.len()) - 1]
+    }
+
+    fn mean(&self) -> f64 {
+        if self.latencies_ms.is_empty() {
+            return 0.0;
+        }
+        self.latencies_ms.iter().sum::<f64>() / self.latencies_ms.len() as f64
+    }
+
+    pub fn print_table(&self) {
+        let errors: u64 = self.errors.values().sum();
+        println!(
+            "Requests:     {} ({} responses, {} errors) in {:.2} s",
+            self.total(),
+            self.latencies_ms.len(),
+            errors,
+            self.elapsed.as_secs_f64()
+        );
+        println!("Throughput:   {:.1} req/s", self.throughput());
+        println!(
+            "Latency:      min {:.1} ms, mean {:.1} ms, max {:.1} ms",
+            self.latencies_ms.first().copied().unwrap_or(0.0),
+            self.mean(),
+            self.latencies_ms.last().copied().unwrap_or(0.0)
+        );
+        println!(
+            "              p50 {:.1} ms, p90 {:.1} ms, p99 {:.1} ms",
+            self.percentile(50.0),
+            self.percentile(90.0),
+            self.percentile(99.0)
+        );
+        println!("Status codes:");
+        for (status_code, count) in &self.status_codes {
+            println!("  {}  {}", status_code, count);
+        }
+        if !self.errors.is_empty() {
+            println!("Errors:");
+            for (kind, count) in &self.errors {
+                println!("  {}  {}", kind, count);
+            }
+        }
+    }
+
+    pub fn print_json(&self) {
+        let report = json!({
+            "requests": self.total(),
+            "responses": self.latencies_ms.len(),
+            "errors": self.errors,
+            "duration_s": self.elapsed.as_secs_f64(),
+            "throughput_rps": self.throughput(),
+            "latency_ms": {
+                "min": self.latencies_ms.first().copied().unwrap_or(0.0),
+                "mean": self.mean(),
+                "max": self.latencies_ms.last().copied().unwrap_or(0.0),
+                "p50": self.percentile(50.0),
+                "p90": self.percentile(90.0),
+                "p99": self.percentile(99.0),
+            },
+            "status_codes": self.status_codes,
+        });
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    }
+}
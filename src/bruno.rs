@@ -0,0 +1,739 @@
+//! Imports Bruno collections: the `.bru` files of a collection folder and its environments.
+
+use crate::import::{self, Imported};
+use crate::structs::{
+    ApiKeyLocation, Assertion, Auth, ClientAuthentication, ExtractVariable, GlobalVariable,
+    HttpRequest, OAuth2,
+};
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+const METHODS: [&str; 9] = [
+    "get", "post", "put", "patch", "delete", "head", "options", "trace", "connect",
+];
+
+/// A `name { ... }` or `name [ ... ]` block of a `.bru` file, with its lines unindented.
+#[derive(Clone)]
+struct Block {
+    name: String,
+    lines: Vec<String>,
+}
+
+impl Block {
+    fn text(&self) -> String {
+        self.lines.join("\n").trim().to_string()
+    }
+
+    /// The `key: value` pairs of the block, leaving out those disabled with `~`.
+    fn pairs(&self) -> Vec<(String, String)> {
+        self.lines
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('~'))
+            .filter_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                Some((key.trim().to_string(), value.trim().to_string()))
+            })
+            .collect()
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        self.pairs()
+            .into_iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Splits a `.bru` file into its blocks. Blocks end with a `}` or `]` at the start of a
+/// line, their contents are indented by two spaces.
+fn parse_blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+    for line in text.lines() {
+        let line = line.trim_end();
+        match current.as_mut() {
+            Some(_) if line == "}" || line == "]" => blocks.extend(current.take()),
+            Some(block) => {
+                let line = line.strip_prefix("  ").unwrap_or(line);
+                block.lines.push(line.to_string());
+            }
+            None => {
+                let name = line.strip_suffix('{').or_else(|| line.strip_suffix('['));
+                if let Some(name) = name.map(str::trim).filter(|name| !name.is_empty()) {
+                    current = Some(Block {
+                        name: name.to_string(),
+                        lines: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+    blocks
+}
+
+fn find<'a>(blocks: &'a [Block], name: &str) -> Option<&'a Block> {
+    blocks.iter().find(|block| block.name == name)
+}
+
+fn has_scripts(blocks: &[Block]) -> bool {
+    blocks.iter().any(|block| {
+        (block.name.starts_with("script:") || block.name == "tests") && !block.text().is_empty()
+    })
+}
+
+/// Headers and auth that folders and the collection pass to their requests.
+#[derive(Clone, Default)]
+struct Defaults {
+    headers: Vec<String>,
+    auth: Option<(String, Vec<Block>)>,
+}
+
+impl Defaults {
+    fn inherit(&self, blocks: Vec<Block>) -> Defaults {
+        let mut defaults = self.clone();
+        for (name, value) in find(&blocks, "headers")
+            .map(Block::pairs)
+            .unwrap_or_default()
+        {
+            defaults.headers.push(format!("{}: {}", name, value));
+        }
+        let mode = find(&blocks, "auth")
+            .and_then(|auth| auth.get("mode"))
+            .unwrap_or_else(|| "inherit".to_string());
+        if mode != "inherit" {
+            defaults.auth = Some((mode, blocks));
+        }
+        defaults
+    }
+}
+
+fn convert_auth(
+    mode: &str,
+    blocks: &[Block],
+    context: &str,
+    imported: &mut Imported,
+) -> Option<Auth> {
+    let parameter = |key: &str| {
+        find(blocks, &format!("auth:{}", mode))
+            .and_then(|block| block.get(key))
+            .unwrap_or_default()
+    };
+    match mode {
+        "none" => None,
+        "bearer" => Some(Auth::Bearer {
+            token: parameter("token"),
+        }),
+        "basic" => Some(Auth::Basic {
+            username: parameter("username"),
+            password: parameter("password"),
+        }),
+        "digest" => Some(Auth::Digest {
+            username: parameter("username"),
+            password: parameter("password"),
+        }),
+        "apikey" => Some(Auth::ApiKey {
+            name: parameter("key"),
+            value: parameter("value"),
+            location: match parameter("placement").as_str() {
+                "queryparams" => ApiKeyLocation::Query,
+                _ => ApiKeyLocation::Header,
+            },
+        }),
+        "oauth2" => {
+            let grant = import::get_oauth2_grant(&parameter("grant_type"));
+            let token_url = parameter("access_token_url");
+            match (grant, token_url.is_empty()) {
+                (Some(grant), false) => Some(Auth::OAuth2(OAuth2 {
+                    grant,
+                    token_url,
+                    client_id: parameter("client_id"),
+                    client_secret: parameter("client_secret"),
+                    scope: parameter("scope"),
+                    username: parameter("username"),
+                    password: parameter("password"),
+                    auth_url: parameter("authorization_url"),
+                    redirect_uri: parameter("callback_url"),
+                    client_authentication: match parameter("credentials_placement").as_str() {
+                        "basic_auth_header" => ClientAuthentication::Basic,
+                        _ => ClientAuthentication::Body,
+                    },
+                    ..Default::default()
+                })),
+                _ => Some(Auth::Bearer {
+                    token: "{{access_token}}".to_string(),
+                }),
+            }
+        }
+        "awsv4" => Some(Auth::AwsSigv4 {
+            access_key_id: parameter("accessKeyId"),
+            secret_access_key: parameter("secretAccessKey"),
+            session_token: parameter("sessionToken"),
+            region: parameter("region"),
+            service: parameter("service"),
+        }),
+        mode => {
+            imported.warn(context, &format!("{} auth is not supported", mode));
+            None
+        }
+    }
+}
+
+fn get_fields(block: Option<&Block>) -> Value {
+    let fields: Map<String, Value> = block
+        .map(Block::pairs)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| {
+            // Files are written as `@file(path)`.
+            let value = match value.strip_prefix("@file(") {
+                Some(path) => format!("@{}", path.trim_end_matches(')')),
+                None => value,
+            };
+            (name, Value::String(value))
+        })
+        .collect();
+    Value::Object(fields)
+}
+
+fn has_content_type(headers: &[String]) -> bool {
+    headers.iter().any(|header| {
+        header
+            .split_once(':')
+            .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+    })
+}
+
+/// Maps the body mode to a body type: `json` and `graphql` become `json` bodies, the forms
+/// `form` and `multipart`, and `text` and `xml` a `text` body.
+fn convert_body(
+    mode: &str,
+    blocks: &[Block],
+    headers: &mut Vec<String>,
+    context: &str,
+    imported: &mut Imported,
+) -> (Option<String>, Option<Value>) {
+    let text = |name: &str| find(blocks, name).map(Block::text).unwrap_or_default();
+    match mode {
+        "none" => (None, None),
+        "json" => {
+            let body = text("body:json");
+            match serde_json::from_str::<Value>(&body) {
+                Ok(json) => (Some("json".to_string()), Some(json)),
+                Err(_) => {
+                    if !has_content_type(headers) {
+                        headers.push("Content-Type: application/json".to_string());
+                    }
+                    (Some("text".to_string()), Some(Value::String(body)))
+                }
+            }
+        }
+        "text" | "xml" => {
+            if mode == "xml" && !has_content_type(headers) {
+                headers.push("Content-Type: application/xml".to_string());
+            }
+            let body = text(&format!("body:{}", mode));
+            (Some("text".to_string()), Some(Value::String(body)))
+        }
+        "formUrlEncoded" => (
+            Some("form".to_string()),
+            Some(get_fields(find(blocks, "body:form-urlencoded"))),
+        ),
+        "multipartForm" => (
+            Some("multipart".to_string()),
+            Some(get_fields(find(blocks, "body:multipart-form"))),
+        ),
+        "graphql" => {
+            let mut query = json!({ "query": text("body:graphql") });
+            if let Ok(variables) = serde_json::from_str::<Value>(&text("body:graphql:vars")) {
+                query["variables"] = variables;
+            }
+            (Some("json".to_string()), Some(query))
+        }
+        mode => {
+            imported.warn(context, &format!("{} bodies are not supported", mode));
+            (None, None)
+        }
+    }
+}
+
+/// `token: res.body.token` post-response variables become extracted variables.
+fn convert_post_response_vars(
+    blocks: &[Block],
+    context: &str,
+    imported: &mut Imported,
+) -> Option<Vec<ExtractVariable>> {
+    let block = find(blocks, "vars:post-response")?;
+    let mut extract_variables = Vec::new();
+    for (name, expression) in block.pairs() {
+        match expression.strip_prefix("res.body.") {
+            Some(path) => extract_variables.push(ExtractVariable {
+                key_path: path.to_string(),
+                variable_name: name,
+            }),
+            None => imported.warn(
+                context,
+                &format!("the post-response variable {}: {}", name, expression),
+            ),
+        }
+    }
+    (!extract_variables.is_empty()).then_some(extract_variables)
+}
+
+fn parse_assertion_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
+}
+
+/// Converts `res.status: eq 200`, `res.body.id: eq 1` and `res.body.id: isDefined`, and
+/// reports the other assertions.
+fn convert_assertions(
+    blocks: &[Block],
+    context: &str,
+    imported: &mut Imported,
+) -> Option<Vec<Assertion>> {
+    let block = find(blocks, "assert")?;
+    let mut assertions = Vec::new();
+    for (target, rule) in block.pairs() {
+        let (operator, value) = rule.split_once(' ').unwrap_or((rule.as_str(), ""));
+        let path = target.strip_prefix("res.body.");
+        let assertion = match (target.as_str(), operator, path) {
+            ("res.status", "eq", _) => value
+                .trim()
+                .parse()
+                .ok()
+                .map(|equals| Assertion::Status { equals }),
+            (_, "eq", Some(path)) => Some(Assertion::JsonPath {
+                path: path.to_string(),
+                equals: Some(parse_assertion_value(value.trim())),
+                contains: None,
+                matches: None,
+                exists: None,
+            }),
+            (_, "isDefined", Some(path)) => Some(Assertion::JsonPath {
+                path: path.to_string(),
+                equals: None,
+                contains: None,
+                matches: None,
+                exists: Some(true),
+            }),
+            _ => None,
+        };
+        match assertion {
+            Some(assertion) => assertions.push(assertion),
+            None => imported.warn(context, &format!("the assertion {}: {}", target, rule)),
+        }
+    }
+    (!assertions.is_empty()).then_some(assertions)
+}
+
+/// Path parameters such as `:id` become `{{id}}`, and their values are saved as variables.
+fn convert_path_params(url: &str, blocks: &[Block], imported: &mut Imported) -> String {
+    let mut url = url.to_string();
+    for (key, value) in find(blocks, "params:path")
+        .map(Block::pairs)
+        .unwrap_or_default()
+    {
+        let path_param = Regex::new(&format!(r"/:{}(/|\?|#|$)", regex::escape(&key))).unwrap();
+        url = path_param
+            .replace_all(&url, format!("/{{{{{}}}}}$1", key))
+            .to_string();
+        if !value.is_empty() {
+            imported.variables.push(GlobalVariable { key, value });
+        }
+    }
+    url
+}
+
+fn get_variables(blocks: &[Block], name: &str) -> Vec<GlobalVariable> {
+    find(blocks, name)
+        .map(Block::pairs)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, value)| GlobalVariable { key, value })
+        .collect()
+}
+
+fn convert_request(
+    blocks: Vec<Block>,
+    file_name: &str,
+    folder: &Option<String>,
+    defaults: &Defaults,
+    imported: &mut Imported,
+) {
+    let meta = find(&blocks, "meta");
+    let name = meta
+        .and_then(|meta| meta.get("name"))
+        .unwrap_or_else(|| file_name.to_string());
+    let context = match folder {
+        Some(folder) => format!("{}/{}", folder, name),
+        None => name.clone(),
+    };
+    let Some(method_block) = blocks
+        .iter()
+        .find(|block| METHODS.contains(&block.name.as_str()))
+    else {
+        imported.warn(&context, "only HTTP requests are supported");
+        return;
+    };
+    let url = method_block.get("url").unwrap_or_default();
+    let url = convert_path_params(&url, &blocks, imported);
+    let mut headers: Vec<String> = find(&blocks, "headers")
+        .map(Block::pairs)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect();
+    for header in &defaults.headers {
+        let name = header
+            .split_once(':')
+            .map(|(name, _)| name)
+            .unwrap_or(header);
+        let exists = headers.iter().any(|existing| {
+            existing
+                .split_once(':')
+                .is_some_and(|(existing, _)| existing.eq_ignore_ascii_case(name))
+        });
+        if !exists {
+            headers.push(header.clone());
+        }
+    }
+    let auth_mode = method_block
+        .get("auth")
+        .unwrap_or_else(|| "none".to_string());
+    let auth = match (auth_mode.as_str(), &defaults.auth) {
+        ("inherit", Some((mode, auth_blocks))) => {
+            convert_auth(mode, auth_blocks, &context, imported)
+        }
+        ("inherit", None) => None,
+        (mode, _) => convert_auth(mode, &blocks, &context, imported),
+    };
+    let body_mode = method_block
+        .get("body")
+        .unwrap_or_else(|| "none".to_string());
+    let (body_type, body) = convert_body(&body_mode, &blocks, &mut headers, &context, imported);
+    imported
+        .variables
+        .extend(get_variables(&blocks, "vars:pre-request"));
+    let extract_variables = convert_post_response_vars(&blocks, &context, imported);
+    let assertions = convert_assertions(&blocks, &context, imported);
+    if has_scripts(&blocks) {
+        imported.warn(&context, "scripts and tests");
+    }
+    imported.requests.push(HttpRequest {
+        name: Some(name),
+        folder: folder.clone(),
+        method: method_block.name.to_uppercase(),
+        url,
+        headers,
+        body_type,
+        body,
+        extract_variables,
+        assertions,
+        auth,
+        ..Default::default()
+    });
+}
+
+fn read_blocks(path: &Path) -> Result<Vec<Block>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    Ok(parse_blocks(&text))
+}
+
+/// Requests are ordered by their `seq`, and come before the subfolders. Folders are nested
+/// into `parent/child` folder names.
+fn convert_folder(
+    dir: &Path,
+    folder: Option<String>,
+    defaults: &Defaults,
+    imported: &mut Imported,
+) -> Result<(), String> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .map_err(|err| format!("Failed to read {}: {}", dir.display(), err))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    let mut requests = Vec::new();
+    let mut subfolders = Vec::new();
+    for path in entries {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if path.is_dir() {
+            let is_skipped = file_name.starts_with('.')
+                || file_name == "node_modules"
+                || (folder.is_none() && file_name == "environments");
+            if !is_skipped {
+                subfolders.push((path, file_name));
+            }
+        } else if let Some(stem) = file_name.strip_suffix(".bru") {
+            if stem != "folder" && stem != "collection" {
+                let blocks = read_blocks(&path)?;
+                let seq = find(&blocks, "meta")
+                    .and_then(|meta| meta.get("seq"))
+                    .and_then(|seq| seq.parse::<u64>().ok())
+                    .unwrap_or(u64::MAX);
+                requests.push((seq, stem.to_string(), blocks));
+            }
+        }
+    }
+    requests.sort_by_key(|(seq, _, _)| *seq);
+    for (_, file_name, blocks) in requests {
+        convert_request(blocks, &file_name, &folder, defaults, imported);
+    }
+    for (path, dir_name) in subfolders {
+        let folder_file = path.join("folder.bru");
+        let blocks = match folder_file.exists() {
+            true => read_blocks(&folder_file)?,
+            false => Vec::new(),
+        };
+        let name = find(&blocks, "meta")
+            .and_then(|meta| meta.get("name"))
+            .unwrap_or(dir_name);
+        let subfolder = match &folder {
+            Some(parent) => format!("{}/{}", parent, name),
+            None => name,
+        };
+        if has_scripts(&blocks) {
+            imported.warn(&subfolder, "folder scripts and tests");
+        }
+        let defaults = defaults.inherit(blocks);
+        convert_folder(&path, Some(subfolder), &defaults, imported)?;
+    }
+    Ok(())
+}
+
+/// `environments/<name>.bru` files become xhtp environments. Secret values are not part of
+/// the collection, so they are reported.
+fn convert_environments(dir: &Path, imported: &mut Imported) -> Result<(), String> {
+    let Ok(entries) = fs::read_dir(dir.join("environments")) else {
+        return Ok(());
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".bru"))
+        else {
+            continue;
+        };
+        let blocks = read_blocks(&path)?;
+        let secrets: Vec<String> = find(&blocks, "vars:secret")
+            .map(|block| {
+                block
+                    .lines
+                    .iter()
+                    .map(|line| line.trim().trim_end_matches(',').to_string())
+                    .filter(|line| !line.is_empty() && !line.starts_with('~'))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !secrets.is_empty() {
+            imported.warn(
+                &format!("environment {}", name),
+                &format!("the values of the secrets {}", secrets.join(", ")),
+            );
+        }
+        imported
+            .environments
+            .push((name.to_string(), get_variables(&blocks, "vars")));
+    }
+    Ok(())
+}
+
+/// Reads a collection folder, the one with `bruno.json`.
+pub fn read(path: &str) -> Result<Imported, String> {
+    let dir = Path::new(path);
+    if !dir.join("bruno.json").exists() {
+        return Err(format!(
+            "{} is not a Bruno collection, it has no bruno.json",
+            path
+        ));
+    }
+    let mut imported = Imported::default();
+    let collection_file = dir.join("collection.bru");
+    let blocks = match collection_file.exists() {
+        true => read_blocks(&collection_file)?,
+        false => Vec::new(),
+    };
+    imported
+        .variables
+        .extend(get_variables(&blocks, "vars:pre-request"));
+    if has_scripts(&blocks) {
+        imported.warn("collection", "collection scripts and tests");
+    }
+    let defaults = Defaults::default().inherit(blocks);
+    convert_folder(dir, None, &defaults, &mut imported)?;
+    convert_environments(dir, &mut imported)?;
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILES: [(&str, &str); 7] = [
+        ("bruno.json", r#"{ "version": "1", "name": "Shop", "type": "collection" }"#),
+        (
+            "collection.bru",
+            "headers {\n  Accept: application/json\n}\n\nauth {\n  mode: bearer\n}\n\nauth:bearer {\n  token: {{token}}\n}\n\nvars:pre-request {\n  baseUrl: https://shop.test\n}\n",
+        ),
+        (
+            "upload.bru",
+            "meta {\n  name: Upload\n  seq: 1\n}\n\npost {\n  url: https://shop.test/upload\n  body: multipartForm\n  auth: ntlm\n}\n\nbody:multipart-form {\n  title: cat\n  ~draft: true\n  file: @file(/tmp/cat.png)\n}\n",
+        ),
+        ("socket.bru", "meta {\n  name: Socket\n  type: ws\n  seq: 2\n}\n"),
+        (
+            "users/folder.bru",
+            "meta {\n  name: Users\n}\n\nauth {\n  mode: basic\n}\n\nauth:basic {\n  username: ada\n  password: {{password}}\n}\n\nscript:pre-request {\n  bru.setVar(\"x\", 1);\n}\n",
+        ),
+        (
+            "users/get-user.bru",
+            "meta {\n  name: Get user\n  type: http\n  seq: 2\n}\n\nget {\n  url: {{baseUrl}}/users/:id\n  body: none\n  auth: inherit\n}\n\nparams:path {\n  id: 7\n}\n\nheaders {\n  accept: application/xml\n  ~X-Off: 1\n}\n\nvars:post-response {\n  userName: res.body.name\n  etag: res.headers.etag\n}\n\nassert {\n  res.status: eq 200\n  res.body.id: eq 7\n  res.body.name: isDefined\n  res.body.tags: contains \"a\"\n}\n",
+        ),
+        (
+            "users/create-user.bru",
+            "meta {\n  name: Create user\n  seq: 1\n}\n\npost {\n  url: {{baseUrl}}/users\n  body: json\n  auth: none\n}\n\nbody:json {\n  {\n    \"name\": \"ada\"\n  }\n}\n",
+        ),
+    ];
+
+    /// Writes the collection to a directory of its own for each test and reads it back.
+    fn read_collection(test: &str) -> Imported {
+        let dir = std::env::temp_dir().join(format!("xhtp-bruno-{}-{}", std::process::id(), test));
+        for (name, text) in FILES {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        let environments = dir.join("environments");
+        fs::create_dir_all(&environments).unwrap();
+        fs::write(
+            environments.join("staging.bru"),
+            "vars {\n  baseUrl: https://staging.shop.test\n}\nvars:secret [\n  token,\n  password\n]\n",
+        )
+        .unwrap();
+        let imported = read(dir.to_str().unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        imported.unwrap()
+    }
+
+    #[test]
+    fn converts_collections() {
+        let imported = read_collection("requests");
+        let names: Vec<(Option<&str>, Option<&str>)> = imported
+            .requests
+            .iter()
+            .map(|request| (request.folder.as_deref(), request.name.as_deref()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (None, Some("Upload")),
+                (Some("Users"), Some("Create user")),
+                (Some("Users"), Some("Get user"))
+            ]
+        );
+
+        let upload = &imported.requests[0];
+        assert_eq!(upload.auth, None);
+        assert_eq!(upload.headers, vec!["Accept: application/json"]);
+        assert_eq!(upload.body_type.as_deref(), Some("multipart"));
+        assert_eq!(
+            upload.body,
+            Some(json!({ "title": "cat", "file": "@/tmp/cat.png" }))
+        );
+
+        let create_user = &imported.requests[1];
+        assert_eq!(create_user.method, "POST");
+        assert_eq!(create_user.auth, None);
+        assert_eq!(create_user.body_type.as_deref(), Some("json"));
+        assert_eq!(create_user.body, Some(json!({ "name": "ada" })));
+
+        let get_user = &imported.requests[2];
+        assert_eq!(get_user.method, "GET");
+        assert_eq!(get_user.url, "{{baseUrl}}/users/{{id}}");
+        assert_eq!(get_user.headers, vec!["accept: application/xml"]);
+        assert_eq!(
+            get_user.auth,
+            Some(Auth::Basic {
+                username: "ada".to_string(),
+                password: "{{password}}".to_string()
+            })
+        );
+        assert_eq!(
+            get_user.extract_variables,
+            Some(vec![ExtractVariable {
+                key_path: "name".to_string(),
+                variable_name: "userName".to_string()
+            }])
+        );
+        assert_eq!(
+            get_user.assertions,
+            Some(vec![
+                Assertion::Status { equals: 200 },
+                Assertion::JsonPath {
+                    path: "id".to_string(),
+                    equals: Some(json!(7)),
+                    contains: None,
+                    matches: None,
+                    exists: None
+                },
+                Assertion::JsonPath {
+                    path: "name".to_string(),
+                    equals: None,
+                    contains: None,
+                    matches: None,
+                    exists: Some(true)
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn converts_variables_and_environments() {
+        let imported = read_collection("variables");
+        let variables: Vec<(&str, &str)> = imported
+            .variables
+            .iter()
+            .map(|variable| (variable.key.as_str(), variable.value.as_str()))
+            .collect();
+        assert_eq!(
+            variables,
+            vec![("baseUrl", "https://shop.test"), ("id", "7")]
+        );
+        assert_eq!(imported.environments.len(), 1);
+        assert_eq!(imported.environments[0].0, "staging");
+        assert_eq!(
+            imported.environments[0].1,
+            vec![GlobalVariable {
+                key: "baseUrl".to_string(),
+                value: "https://staging.shop.test".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn warns_about_unsupported_constructs() {
+        assert_eq!(
+            read_collection("warnings").warnings,
+            vec![
+                "Upload: ntlm auth is not supported",
+                "Socket: only HTTP requests are supported",
+                "Users: folder scripts and tests",
+                "Users/Get user: the post-response variable etag: res.headers.etag",
+                "Users/Get user: the assertion res.body.tags: contains \"a\"",
+                "environment staging: the values of the secrets token, password",
+            ]
+        );
+        assert!(read("/nonexistent").is_err());
+    }
+}
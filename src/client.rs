@@ -0,0 +1,458 @@
+use crate::snapshots::SnapshotKey;
+use crate::structs::{
+    Auth, GlobalVariable, HistoryEntry, HttpRequest, HttpResponse, OAuth2, RequestOutcome,
+};
+use crate::{assertions, auth, content, history, oauth2, signing, snapshots, utils};
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+static TIMEOUT: OnceLock<Option<u64>> = OnceLock::new();
+static INSECURE_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+pub fn configure(timeout: Option<u64>) {
+    let _ = TIMEOUT.set(timeout);
+}
+
+/// The shared client, or for requests that skip certificate verification a second one
+/// with the same timeout, built on first use.
+fn get_client<'a>(client: &'a reqwest::Client, resolved: &HttpRequest) -> &'a reqwest::Client {
+    if resolved.insecure != Some(true) {
+        return client;
+    }
+    INSECURE_CLIENT.get_or_init(|| utils::build_client(TIMEOUT.get().copied().flatten(), true))
+}
+
+/// Why a request got no response.
+#[derive(Debug)]
+pub enum SendError {
+    /// Sending the request or reading its response failed.
+    Http(reqwest::Error),
+    /// The request couldn't be prepared, e.g. because of an invalid method.
+    Request(String),
+}
+
+impl From<reqwest::Error> for SendError {
+    fn from(err: reqwest::Error) -> Self {
+        SendError::Http(err)
+    }
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SendError::Http(err) => write!(f, "{}", err),
+            SendError::Request(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+fn print_http_response_as_json(http_response: &HttpResponse) {
+    let json_http_response = serde_json::to_string(&http_response).unwrap();
+    println!("{}", json_http_response);
+}
+
+/// A response that has been read in full and decoded.
+pub struct ReceivedResponse {
+    pub status_code: u16,
+    pub headers: HeaderMap,
+    pub body: content::Body,
+    pub size: usize,
+    pub duration: Duration,
+}
+
+pub async fn read_response(
+    res: reqwest::Response,
+    started: Instant,
+) -> Result<ReceivedResponse, reqwest::Error> {
+    let status_code = res.status().as_u16();
+    let headers = res.headers().clone();
+    let bytes = res.bytes().await?;
+    let duration = started.elapsed();
+    let body = content::decode_body(&headers, &bytes);
+    Ok(ReceivedResponse {
+        status_code,
+        headers,
+        body,
+        size: bytes.len(),
+        duration,
+    })
+}
+
+pub fn handle_response(
+    req: &HttpRequest,
+    snapshot_key: SnapshotKey,
+    received: ReceivedResponse,
+) -> RequestOutcome {
+    let snapshot_result = snapshots::check(req, snapshot_key, &received);
+    let ReceivedResponse {
+        status_code,
+        headers,
+        body,
+        duration,
+        ..
+    } = received;
+
+    let mut assertion_results = match &req.assertions {
+        Some(assertions) => {
+            assertions::evaluate(assertions, status_code, &headers, &body, duration)
+        }
+        None => Vec::new(),
+    };
+    assertion_results.extend(snapshot_result);
+    let mut extracted_variables = Vec::new();
+
+    if status_code == 204 {
+        println!("204 No Content");
+    } else {
+        let mut http_response = HttpResponse {
+            method: req.method.clone(),
+            url: req.url.clone(),
+            status_code,
+            json_data: None,
+            text_data: None,
+            binary_data: None,
+        };
+        match body {
+            content::Body::Json(json) => {
+                if let Some(extract_variables) = &req.extract_variables {
+                    for variable in extract_variables {
+                        if let Some(value) = utils::get_json_value(&json, &variable.key_path) {
+                            let value = utils::json_value_to_variable(value);
+                            utils::save_to_global_variables(
+                                variable.variable_name.clone(),
+                                value.clone(),
+                            );
+                            extracted_variables.push(GlobalVariable {
+                                key: variable.variable_name.clone(),
+                                value,
+                            });
+                        } else {
+                            println!(
+                                "The key '{}' was not found in the JSON.",
+                                &variable.key_path
+                            );
+                        }
+                    }
+                }
+                http_response.json_data = Some(json);
+            }
+            content::Body::Text(text) => http_response.text_data = Some(text),
+            content::Body::Binary(binary) => http_response.binary_data = Some(binary.summary()),
+        }
+        print_http_response_as_json(&http_response);
+    }
+
+    if !assertion_results.is_empty() {
+        assertions::print_report(&assertion_results);
+    }
+
+    RequestOutcome {
+        status_code,
+        duration_ms: duration.as_millis(),
+        assertion_results,
+        extracted_variables,
+    }
+}
+
+/// Fails on the first header that isn't a valid name and value, as imported requests can
+/// carry headers that were never checked.
+fn get_headers_from_vec(headers: &[String]) -> Result<reqwest::header::HeaderMap, SendError> {
+    let mut header_map = reqwest::header::HeaderMap::new();
+    for header in headers {
+        let (header_name, header_value) = header.split_once(':').unwrap_or((header, ""));
+        let header_value = header_value.trim().replace('"', "");
+        let invalid = || SendError::Request(format!("Invalid header '{}'", header));
+        header_map.insert(
+            reqwest::header::HeaderName::from_bytes(header_name.trim().as_bytes())
+                .map_err(|_| invalid())?,
+            reqwest::header::HeaderValue::from_str(header_value.as_str()).map_err(|_| invalid())?,
+        );
+    }
+    if !header_map.contains_key(reqwest::header::ACCEPT_ENCODING) {
+        header_map.insert(
+            reqwest::header::ACCEPT_ENCODING,
+            reqwest::header::HeaderValue::from_static(content::ACCEPT_ENCODING),
+        );
+    }
+    Ok(header_map)
+}
+
+/// Builds the form from the fields of the body object. A string value starting with `@`
+/// is sent as the contents of the file at that path.
+fn get_multipart_form(body: &Value) -> reqwest::multipart::Form {
+    let mut form = reqwest::multipart::Form::new();
+    let Value::Object(fields) = body else {
+        return form;
+    };
+    for (name, value) in fields {
+        form = match value {
+            Value::String(text) => match text.strip_prefix('@') {
+                Some(path) => match std::fs::read(path) {
+                    Ok(bytes) => {
+                        let file_name = std::path::Path::new(path)
+                            .file_name()
+                            .map(|file_name| file_name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        form.part(
+                            name.clone(),
+                            reqwest::multipart::Part::bytes(bytes).file_name(file_name),
+                        )
+                    }
+                    Err(err) => {
+                        eprintln!("Failed to read {} for the field {}: {}", path, name, err);
+                        form
+                    }
+                },
+                None => form.text(name.clone(), text.clone()),
+            },
+            value => form.text(name.clone(), value.to_string()),
+        };
+    }
+    form
+}
+
+/// Builds a request that has already had its variables resolved.
+pub fn build_request(
+    client: &reqwest::Client,
+    resolved: &HttpRequest,
+) -> Result<reqwest::RequestBuilder, SendError> {
+    let full_url = utils::get_url_with_https(&resolved.url);
+    let method = reqwest::Method::from_bytes(resolved.method.to_uppercase().as_bytes())
+        .map_err(|_| SendError::Request(format!("Invalid HTTP method {}", resolved.method)))?;
+
+    let request_builder = client
+        .request(method, &full_url)
+        .headers(get_headers_from_vec(&resolved.headers)?);
+    let request_builder = auth::apply(request_builder, resolved.auth.as_ref());
+    Ok(match (resolved.body_type.as_deref(), &resolved.body) {
+        (_, None) => request_builder,
+        (Some("form"), Some(body)) => request_builder.form(body),
+        (Some("multipart"), Some(body)) => request_builder.multipart(get_multipart_form(body)),
+        (Some("text"), Some(Value::String(text))) => request_builder.body(text.clone()),
+        (Some("text"), Some(body)) => request_builder.body(body.to_string()),
+        (_, Some(body)) => request_builder.json(body),
+    })
+}
+
+/// Sends a resolved request. With digest auth, the challenge of the first response is
+/// answered with a second request. OAuth 2.0 requests get their access token first, and
+/// are sent again with a new one when a cached token is rejected. Signed requests are signed
+/// once built.
+pub async fn send(
+    client: &reqwest::Client,
+    resolved: &HttpRequest,
+) -> Result<reqwest::Response, SendError> {
+    let client = get_client(client, resolved);
+    match &resolved.auth {
+        Some(Auth::Digest { username, password }) => {
+            let response = build_request(client, resolved)?.send().await?;
+            match response.status() {
+                reqwest::StatusCode::UNAUTHORIZED => {
+                    send_with_digest(client, resolved, response, username, password).await
+                }
+                _ => Ok(response),
+            }
+        }
+        Some(Auth::OAuth2(config)) => {
+            let cached = oauth2::cached_access_token(config);
+            let response = send_with_oauth2(client, resolved, config).await?;
+            if let Some(token) =
+                cached.filter(|_| response.status() == reqwest::StatusCode::UNAUTHORIZED)
+            {
+                oauth2::expire_access_token(config, &token);
+                return send_with_oauth2(client, resolved, config).await;
+            }
+            Ok(response)
+        }
+        Some(auth @ (Auth::AwsSigv4 { .. } | Auth::Hmac { .. })) => {
+            let mut request = build_request(client, resolved)?.build()?;
+            signing::sign(&mut request, auth).map_err(|err| {
+                SendError::Request(format!("Failed to sign the request: {}", err))
+            })?;
+            Ok(client.execute(request).await?)
+        }
+        _ => Ok(build_request(client, resolved)?.send().await?),
+    }
+}
+
+/// Sends the request with the access token. It isn't sent when no token can be had, which
+/// the server would only answer with a 401.
+async fn send_with_oauth2(
+    client: &reqwest::Client,
+    resolved: &HttpRequest,
+    config: &OAuth2,
+) -> Result<reqwest::Response, SendError> {
+    let builder = build_request(client, resolved)?;
+    let token = oauth2::get_access_token(client, config)
+        .await
+        .map_err(|err| {
+            SendError::Request(format!("Failed to get an OAuth 2.0 access token: {}", err))
+        })?;
+    Ok(builder.bearer_auth(token).send().await?)
+}
+
+async fn send_with_digest(
+    client: &reqwest::Client,
+    resolved: &HttpRequest,
+    response: reqwest::Response,
+    username: &str,
+    password: &str,
+) -> Result<reqwest::Response, SendError> {
+    let url = response.url();
+    let uri = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let challenges: Vec<&str> = response
+        .headers()
+        .get_all(reqwest::header::WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    let authorization = auth::get_digest_authorization(
+        &challenges,
+        username,
+        password,
+        &resolved.method.to_uppercase(),
+        &uri,
+    );
+    match authorization {
+        Ok(authorization) => Ok(build_request(client, resolved)?
+            .header(reqwest::header::AUTHORIZATION, authorization)
+            .send()
+            .await?),
+        Err(err) => {
+            eprintln!("{}", err);
+            Ok(response)
+        }
+    }
+}
+
+/// Resolves a saved request using `variables`, which take precedence over the global variables.
+/// Requests without their own auth get the default one.
+pub fn resolve_with_global_variables(
+    request: &HttpRequest,
+    variables: &[GlobalVariable],
+) -> HttpRequest {
+    let mut variables = variables.to_vec();
+    variables.extend(utils::get_global_variables());
+    utils::resolve_request(&auth::with_default(request), &variables)
+}
+
+/// Sends a saved request and records it in the history without printing the response.
+/// Also returns the masked history entry, which is built even with `--no-history`.
+pub async fn send_request(
+    client: &reqwest::Client,
+    request: &HttpRequest,
+    variables: &[GlobalVariable],
+) -> (Result<ReceivedResponse, SendError>, HistoryEntry) {
+    let resolved = resolve_with_global_variables(request, variables);
+    let started = Instant::now();
+    let result = match send(client, &resolved).await {
+        Ok(response) => read_response(response, started)
+            .await
+            .map_err(SendError::from),
+        Err(err) => Err(err),
+    };
+    let entry = history::record(Some(request), &resolved, result.as_ref());
+    (result, entry)
+}
+
+/// Sends a saved request. `variables` take precedence over the global variables.
+pub async fn make_request(
+    client: &reqwest::Client,
+    request: &HttpRequest,
+    variables: &[GlobalVariable],
+    snapshot_key: SnapshotKey,
+) -> Result<RequestOutcome, SendError> {
+    let received = send_request(client, request, variables).await.0?;
+    Ok(handle_response(request, snapshot_key, received))
+}
+
+pub async fn make_ad_hoc_request(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<RequestOutcome, SendError> {
+    let request = HttpRequest {
+        method: "GET".to_string(),
+        url: utils::get_url_with_https(url),
+        ..Default::default()
+    };
+    let started = Instant::now();
+    let result = match client
+        .get(&request.url)
+        .header(reqwest::header::ACCEPT_ENCODING, content::ACCEPT_ENCODING)
+        .send()
+        .await
+    {
+        Ok(response) => read_response(response, started).await,
+        Err(err) => Err(err),
+    }
+    .map_err(SendError::from);
+    history::record(None, &request, result.as_ref());
+    let received = result?;
+
+    println!(
+        "{}",
+        reqwest::StatusCode::from_u16(received.status_code).unwrap()
+    );
+    match &received.body {
+        content::Body::Json(json) => println!("{:#}", json),
+        content::Body::Text(text) => println!("{}", text),
+        content::Body::Binary(binary) => println!("{}", binary.hexdump()),
+    }
+
+    Ok(RequestOutcome {
+        status_code: received.status_code,
+        duration_ms: received.duration.as_millis(),
+        assertion_results: Vec::new(),
+        extracted_variables: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_body(request: &HttpRequest) -> Vec<u8> {
+        let request = build_request(&reqwest::Client::new(), request)
+            .unwrap()
+            .build()
+            .unwrap();
+        request.body().unwrap().as_bytes().unwrap().to_vec()
+    }
+
+    #[test]
+    fn sends_text_bodies_as_they_are() {
+        let request = HttpRequest {
+            method: "POST".to_string(),
+            url: "http://localhost/notes".to_string(),
+            body_type: Some("text".to_string()),
+            body: Some(Value::String("say \"hi\"".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(get_body(&request), b"say \"hi\"");
+
+        let request = HttpRequest {
+            body: Some(serde_json::json!({"a": 1})),
+            ..request
+        };
+        assert_eq!(get_body(&request), br#"{"a":1}"#);
+    }
+
+    #[test]
+    fn names_invalid_headers() {
+        let headers = get_headers_from_vec(&["Accept: text/plain".to_string()]).unwrap();
+        assert_eq!(headers["accept"], "text/plain");
+        for header in ["Bad Header: 1", "X-Line: a\nb", "{{token}}"] {
+            match get_headers_from_vec(&[header.to_string()]) {
+                Err(SendError::Request(message)) => {
+                    assert_eq!(message, format!("Invalid header '{}'", header))
+                }
+                _ => panic!("{} was accepted", header),
+            }
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use encoding_rs::{Encoding, UTF_8};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use reqwest::header::{HeaderMap, CONTENT_ENCODING, CONTENT_TYPE};
+use serde_json::Value;
+use std::io::Read;
+
+/// Encodings advertised in `Accept-Encoding` when a request doesn't set its own.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+/// Number of leading bytes shown when hexdumping a binary body.
+const HEXDUMP_LIMIT: usize = 256;
+
+pub enum Body {
+    Json(Value),
+    Text(String),
+    Binary(BinaryBody),
+}
+
+pub struct BinaryBody {
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl BinaryBody {
+    pub fn summary(&self) -> String {
+        let content_type = if self.content_type.is_empty() {
+            "unknown content type"
+        } else {
+            &self.content_type
+        };
+        format!("{} bytes of {}", self.bytes.len(), content_type)
+    }
+
+    pub fn hexdump(&self) -> String {
+        let mut lines = vec![format!("<binary body: {}>", self.summary())];
+        for (index, chunk) in self.bytes.chunks(16).take(HEXDUMP_LIMIT / 16).enumerate() {
+            let hex = chunk
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<String>>()
+                .join(" ");
+            let ascii: String = chunk
+                .iter()
+                .map(|byte| {
+                    if byte.is_ascii_graphic() || *byte == b' ' {
+                        *byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            lines.push(format!("{:08x}  {:<47}  |{}|", index * 16, hex, ascii));
+        }
+        if self.bytes.len() > HEXDUMP_LIMIT {
+            lines.push(format!(
+                "... {} more bytes",
+                self.bytes.len() - HEXDUMP_LIMIT
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Splits a content type header into its lowercased mime type and charset parameter.
+pub fn parse_content_type(content_type: &str) -> (String, Option<String>) {
+    let mut parts = content_type.split(';');
+    let mime_type = parts.next().unwrap_or("").trim().to_lowercase();
+    let charset = parts.find_map(|part| {
+        let (key, value) = part.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case("charset") {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    });
+    (mime_type, charset)
+}
+
+pub fn is_json_mime_type(mime_type: &str) -> bool {
+    mime_type == "application/json" || mime_type == "text/json" || mime_type.ends_with("+json")
+}
+
+fn is_text_mime_type(mime_type: &str) -> bool {
+    mime_type.starts_with("text/")
+        || mime_type.ends_with("+xml")
+        || matches!(
+            mime_type,
+            "application/xml"
+                | "application/javascript"
+                | "application/ecmascript"
+                | "application/x-www-form-urlencoded"
+                | "application/graphql"
+                | "application/yaml"
+                | "application/x-yaml"
+                | "application/x-ndjson"
+        )
+}
+
+/// Undoes every `Content-Encoding` applied to the body, last applied first.
+pub fn decompress(content_encoding: &str, bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut body = bytes.to_vec();
+    for encoding in content_encoding.split(',').rev() {
+        let encoding = encoding.trim().to_lowercase();
+        let mut decoded = Vec::new();
+        let result = match encoding.as_str() {
+            "" | "identity" => continue,
+            "gzip" | "x-gzip" => GzDecoder::new(body.as_slice()).read_to_end(&mut decoded),
+            "deflate" => {
+                // Servers disagree on whether deflate means zlib-wrapped or raw.
+                let zlib = ZlibDecoder::new(body.as_slice()).read_to_end(&mut decoded);
+                if zlib.is_err() {
+                    decoded.clear();
+                    DeflateDecoder::new(body.as_slice()).read_to_end(&mut decoded)
+                } else {
+                    zlib
+                }
+            }
+            "br" => brotli::Decompressor::new(body.as_slice(), 4096).read_to_end(&mut decoded),
+            "zstd" => zstd::stream::read::Decoder::new(body.as_slice())
+                .and_then(|mut decoder| decoder.read_to_end(&mut decoded)),
+            _ => return Err(format!("Unsupported content encoding '{}'", encoding)),
+        };
+        if let Err(err) = result {
+            return Err(format!("Failed to decode {} body: {}", encoding, err));
+        }
+        body = decoded;
+    }
+    Ok(body)
+}
+
+/// Decodes text using the charset label, falling back to UTF-8 for unknown labels.
+pub fn decode_text(bytes: &[u8], charset: Option<&str>) -> String {
+    let encoding = charset
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+pub fn decode_body(headers: &HeaderMap, bytes: &[u8]) -> Body {
+    let mut body = bytes.to_vec();
+    if let Some(content_encoding) = headers
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+    {
+        match decompress(content_encoding, bytes) {
+            Ok(decompressed) => body = decompressed,
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    let (mime_type, charset) = parse_content_type(content_type);
+
+    if is_json_mime_type(&mime_type) {
+        let text = decode_text(&body, charset.as_deref());
+        return match serde_json::from_str(&text) {
+            Ok(json) => Body::Json(json),
+            Err(_) => Body::Text(text),
+        };
+    }
+
+    if is_text_mime_type(&mime_type) {
+        return Body::Text(decode_text(&body, charset.as_deref()));
+    }
+
+    if mime_type.is_empty() {
+        if let Ok(text) = std::str::from_utf8(&body) {
+            return Body::Text(text.to_string());
+        }
+    }
+
+    Body::Binary(BinaryBody {
+        content_type: mime_type,
+        bytes: body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use serde_json::json;
+    use std::io::Write;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(bytes: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+            writer.write_all(bytes).unwrap();
+        }
+        compressed
+    }
+
+    fn decode_json(encoding: &str, bytes: &[u8]) -> Value {
+        let headers = headers(&[
+            ("content-encoding", encoding),
+            ("content-type", "application/problem+json"),
+        ]);
+        match decode_body(&headers, bytes) {
+            Body::Json(json) => json,
+            _ => panic!("{} body was not decoded as JSON", encoding),
+        }
+    }
+
+    #[test]
+    fn decompresses_every_advertised_encoding() {
+        let json = br#"{"title":"Not found"}"#;
+        let expected = json!({ "title": "Not found" });
+        assert_eq!(decode_json("gzip", &gzip(json)), expected);
+        assert_eq!(decode_json("br", &brotli(json)), expected);
+        assert_eq!(
+            decode_json("zstd", &zstd::encode_all(&json[..], 0).unwrap()),
+            expected
+        );
+
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(json).unwrap();
+        assert_eq!(decode_json("deflate", &zlib.finish().unwrap()), expected);
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(json).unwrap();
+        assert_eq!(decode_json("deflate", &raw.finish().unwrap()), expected);
+
+        // Encodings are undone last applied first.
+        assert_eq!(decode_json("gzip, br", &brotli(&gzip(json))), expected);
+    }
+
+    #[test]
+    fn reports_unsupported_and_corrupt_encodings() {
+        assert_eq!(
+            decompress("compress", b"x"),
+            Err("Unsupported content encoding 'compress'".to_string())
+        );
+        assert!(decompress("gzip", b"not gzip")
+            .unwrap_err()
+            .starts_with("Failed to decode gzip body:"));
+        assert_eq!(decompress("identity", b"x"), Ok(b"x".to_vec()));
+    }
+
+    #[test]
+    fn decodes_text_with_its_charset() {
+        let latin1 = headers(&[("content-type", "text/plain; charset=\"ISO-8859-1\"")]);
+        match decode_body(&latin1, b"caf\xe9") {
+            Body::Text(text) => assert_eq!(text, "café"),
+            _ => panic!("latin-1 body was not decoded as text"),
+        }
+        let shift_jis = headers(&[("content-type", "application/json;charset=Shift_JIS")]);
+        match decode_body(&shift_jis, b"{\"name\":\"\x93\xfa\x96\x7b\"}") {
+            Body::Json(json) => assert_eq!(json, json!({ "name": "日本" })),
+            _ => panic!("Shift_JIS body was not decoded as JSON"),
+        }
+        assert_eq!(decode_text("é".as_bytes(), Some("no-such-charset")), "é");
+    }
+
+    #[test]
+    fn keeps_binary_bodies_as_bytes() {
+        let png = headers(&[("content-type", "image/png")]);
+        match decode_body(&png, b"\x89PNG\r\n") {
+            Body::Binary(binary) => assert_eq!(binary.summary(), "6 bytes of image/png"),
+            _ => panic!("png body was not kept as binary"),
+        }
+        match decode_body(&HeaderMap::new(), b"plain") {
+            Body::Text(text) => assert_eq!(text, "plain"),
+            _ => panic!("untyped UTF-8 body was not decoded as text"),
+        }
+        match decode_body(&HeaderMap::new(), b"\xff\xfe") {
+            Body::Binary(binary) => assert_eq!(binary.summary(), "2 bytes of unknown content type"),
+            _ => panic!("untyped binary body was not kept as binary"),
+        }
+        match decode_body(&headers(&[("content-type", "application/json")]), b"{oops") {
+            Body::Text(text) => assert_eq!(text, "{oops"),
+            _ => panic!("invalid JSON was not kept as text"),
+        }
+    }
+}
@@ -0,0 +1,891 @@
+//! Turns curl commands, as copied from browser devtools or docs, into saved requests.
+
+use crate::structs::{Auth, HttpRequest};
+use crate::utils::{print_line, read_http_request_file, write_http_request_file};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::Read;
+
+/// Options that take a value but don't change the request, skipped with their value.
+const SILENT_OPTIONS_WITH_VALUE: &[&str] = &[
+    "-o",
+    "--output",
+    "--output-dir",
+    "-m",
+    "--max-time",
+    "--connect-timeout",
+    "--retry",
+    "--retry-delay",
+    "--retry-max-time",
+    "-w",
+    "--write-out",
+    "-c",
+    "--cookie-jar",
+    "-D",
+    "--dump-header",
+    "--etag-save",
+    "--stderr",
+    "--trace",
+    "--trace-ascii",
+    "--trace-config",
+    "--libcurl",
+    "--limit-rate",
+    "--max-redirs",
+    "--max-filesize",
+    "--expect100-timeout",
+    "--keepalive-time",
+    "--happy-eyeballs-timeout-ms",
+    "--parallel-max",
+    "--rate",
+    "-Y",
+    "--speed-limit",
+    "-y",
+    "--speed-time",
+    "--create-file-mode",
+];
+
+/// Options that take a value and change how the request is sent in ways xhtp doesn't
+/// support. They are skipped with their value and reported.
+const IGNORED_OPTIONS_WITH_VALUE: &[&str] = &[
+    "--abstract-unix-socket",
+    "--alt-svc",
+    "--aws-sigv4",
+    "--cacert",
+    "--capath",
+    "-E",
+    "--cert",
+    "--cert-type",
+    "--ciphers",
+    "-K",
+    "--config",
+    "--connect-to",
+    "-C",
+    "--continue-at",
+    "--crlfile",
+    "--curves",
+    "--delegation",
+    "--dns-interface",
+    "--dns-ipv4-addr",
+    "--dns-ipv6-addr",
+    "--dns-servers",
+    "--doh-url",
+    "--etag-compare",
+    "--haproxy-clientip",
+    "--hsts",
+    "--interface",
+    "--key",
+    "--key-type",
+    "--local-port",
+    "--login-options",
+    "--netrc-file",
+    "--noproxy",
+    "--pass",
+    "--pinnedpubkey",
+    "--preproxy",
+    "--proto",
+    "--proto-default",
+    "--proto-redir",
+    "-x",
+    "--proxy",
+    "--proxy-cacert",
+    "--proxy-capath",
+    "--proxy-cert",
+    "--proxy-cert-type",
+    "--proxy-ciphers",
+    "--proxy-crlfile",
+    "--proxy-header",
+    "--proxy-key",
+    "--proxy-key-type",
+    "--proxy-pass",
+    "--proxy-pinnedpubkey",
+    "--proxy-service-name",
+    "--proxy-tls13-ciphers",
+    "--proxy-tlsauthtype",
+    "--proxy-tlspassword",
+    "--proxy-tlsuser",
+    "-U",
+    "--proxy-user",
+    "--pubkey",
+    "-Q",
+    "--quote",
+    "-r",
+    "--range",
+    "--request-target",
+    "--resolve",
+    "--service-name",
+    "--socks4",
+    "--socks4a",
+    "--socks5",
+    "--socks5-gssapi-service",
+    "--socks5-hostname",
+    "-t",
+    "--telnet-option",
+    "-z",
+    "--time-cond",
+    "--tls-max",
+    "--tls13-ciphers",
+    "--tlsauthtype",
+    "--tlspassword",
+    "--tlsuser",
+    "--unix-socket",
+    "-T",
+    "--upload-file",
+    "--url-query",
+    "--variable",
+];
+
+/// Options that need a value and are turned into the request, in their short and long forms.
+const OPTIONS_WITH_VALUE: &[&str] = &[
+    "-X",
+    "--request",
+    "-H",
+    "--header",
+    "-d",
+    "--data",
+    "--data-ascii",
+    "--data-raw",
+    "--data-binary",
+    "--data-urlencode",
+    "--json",
+    "-F",
+    "--form",
+    "--form-string",
+    "-u",
+    "--user",
+    "--oauth2-bearer",
+    "-b",
+    "--cookie",
+    "-A",
+    "--user-agent",
+    "-e",
+    "--referer",
+    "--url",
+];
+
+/// Flags that don't change the request, or only in ways xhtp already behaves like.
+const SILENT_FLAGS: &[&str] = &[
+    // Compressed responses are always accepted and redirects always followed.
+    "--compressed",
+    "-L",
+    "--location",
+    "--location-trusted",
+    // Basic is what `-u` sends unless `--digest` is passed.
+    "--basic",
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-v",
+    "--verbose",
+    "-i",
+    "--include",
+    "-f",
+    "--fail",
+    "--fail-with-body",
+    "-g",
+    "--globoff",
+    "-#",
+    "--progress-bar",
+    "--no-progress-meter",
+    "-N",
+    "--no-buffer",
+    "-O",
+    "--remote-name",
+    "-J",
+    "--remote-header-name",
+    "-R",
+    "--remote-time",
+    "--create-dirs",
+    "--retry-connrefused",
+    "--retry-all-errors",
+    "-Z",
+    "--parallel",
+    "-q",
+    "--disable",
+];
+
+/// Flags that change how the request is sent in ways xhtp doesn't support, reported as ignored.
+const IGNORED_FLAGS: &[&str] = &[
+    "-0",
+    "--http1.0",
+    "--http1.1",
+    "--http2",
+    "--http2-prior-knowledge",
+    "--http3",
+    "--http3-only",
+    "-4",
+    "--ipv4",
+    "-6",
+    "--ipv6",
+    "-1",
+    "--tlsv1",
+    "--tlsv1.0",
+    "--tlsv1.1",
+    "--tlsv1.2",
+    "--tlsv1.3",
+    "--ssl",
+    "--ssl-reqd",
+    "--ssl-no-revoke",
+    "--proxy-insecure",
+    "--proxytunnel",
+    "-p",
+    "--ntlm",
+    "--ntlm-wb",
+    "--negotiate",
+    "--anyauth",
+    "-n",
+    "--netrc",
+    "--netrc-optional",
+    "-j",
+    "--junk-session-cookies",
+    "--path-as-is",
+    "--raw",
+    "--tr-encoding",
+    "--no-keepalive",
+    "--no-sessionid",
+    "--tcp-nodelay",
+    "--tcp-fastopen",
+    "--post301",
+    "--post302",
+    "--post303",
+    "-a",
+    "--append",
+    "-B",
+    "--use-ascii",
+    "-l",
+    "--list-only",
+    "--cert-status",
+    "--false-start",
+];
+
+fn takes_value(option: &str) -> bool {
+    OPTIONS_WITH_VALUE.contains(&option)
+        || SILENT_OPTIONS_WITH_VALUE.contains(&option)
+        || IGNORED_OPTIONS_WITH_VALUE.contains(&option)
+}
+
+/// Splits a command into words like a POSIX shell, including `$'...'` strings and
+/// backslash line continuations.
+fn split_words(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            ' ' | '\t' | '\n' | '\r' => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(char) => word.get_or_insert_with(String::new).push(char),
+                None => {}
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(char) => word.push(char),
+                        None => return Err("Unterminated ' in the curl command".to_string()),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => word.push_str(&read_ansi_c_escape(&mut chars)),
+                        Some(char) => word.push(char),
+                        None => return Err("Unterminated $' in the curl command".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(char @ ('"' | '\\' | '$' | '`')) => word.push(char),
+                            Some(char) => {
+                                word.push('\\');
+                                word.push(char);
+                            }
+                            None => {}
+                        },
+                        Some(char) => word.push(char),
+                        None => return Err("Unterminated \" in the curl command".to_string()),
+                    }
+                }
+            }
+            char => word.get_or_insert_with(String::new).push(char),
+        }
+    }
+    if let Some(word) = word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+fn read_hex_char(chars: &mut std::iter::Peekable<std::str::Chars>, max: usize) -> String {
+    let mut hex = String::new();
+    while hex.len() < max && chars.peek().is_some_and(|char| char.is_ascii_hexdigit()) {
+        hex.push(chars.next().unwrap());
+    }
+    u32::from_str_radix(&hex, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .map(|char| char.to_string())
+        .unwrap_or_default()
+}
+
+fn read_ansi_c_escape(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    match chars.next() {
+        Some('n') => "\n".to_string(),
+        Some('t') => "\t".to_string(),
+        Some('r') => "\r".to_string(),
+        Some('e') | Some('E') => "\x1b".to_string(),
+        Some('x') => read_hex_char(chars, 2),
+        Some('u') => read_hex_char(chars, 4),
+        Some('U') => read_hex_char(chars, 8),
+        Some(char) => char.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Reads `@file` values, like curl does for `-d` and `--data-binary`.
+fn read_data_value(value: &str, strip_newlines: bool) -> Result<String, String> {
+    let Some(path) = value.strip_prefix('@') else {
+        return Ok(value.to_string());
+    };
+    if path == "-" {
+        return Err("Reading data from stdin (@-) is not supported".to_string());
+    }
+    let text =
+        fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+    if strip_newlines {
+        Ok(text.replace(['\r', '\n'], ""))
+    } else {
+        Ok(text)
+    }
+}
+
+/// Encodes a `--data-urlencode` value: `content`, `=content`, `name=content`, `@file` or `name@file`.
+fn encode_data_value(value: &str) -> Result<String, String> {
+    let encode = |text: &str| utf8_percent_encode(text, NON_ALPHANUMERIC).to_string();
+    if let Some((name, content)) = value.split_once('=') {
+        return Ok(if name.is_empty() {
+            encode(content)
+        } else {
+            format!("{}={}", name, encode(content))
+        });
+    }
+    match value.split_once('@') {
+        Some((name, path)) => {
+            let content = read_data_value(&format!("@{}", path), false)?;
+            Ok(if name.is_empty() {
+                encode(&content)
+            } else {
+                format!("{}={}", name, encode(&content))
+            })
+        }
+        None => Ok(encode(value)),
+    }
+}
+
+/// Parses `a=1&b=2` into an object, or returns `None` when the body doesn't look like a form.
+fn parse_form(body: &str) -> Option<Value> {
+    let decode = |text: &str| {
+        percent_decode_str(&text.replace('+', " "))
+            .decode_utf8()
+            .map(|text| text.to_string())
+            .ok()
+    };
+    let mut form = Map::new();
+    for pair in body.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=')?;
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return None;
+        }
+        form.insert(decode(name)?, Value::String(decode(value)?));
+    }
+    (!form.is_empty()).then_some(Value::Object(form))
+}
+
+/// Parses a `-F` field, where `@file` uploads the file and `<file` sends its contents.
+fn parse_form_field(field: &str, literal: bool) -> Result<(String, String), String> {
+    let Some((name, value)) = field.split_once('=') else {
+        return Err(format!(
+            "Invalid form field `{}`, expected name=value",
+            field
+        ));
+    };
+    if literal {
+        return Ok((name.to_string(), value.to_string()));
+    }
+    // Options such as `;type=image/png` are left to the upload.
+    let value = match value.strip_prefix('@') {
+        Some(path) => format!("@{}", path.split(';').next().unwrap_or(path)),
+        None => match value.strip_prefix('<') {
+            Some(path) => {
+                let path = path.split(';').next().unwrap_or(path);
+                fs::read_to_string(path)
+                    .map_err(|err| format!("Failed to read {}: {}", path, err))?
+            }
+            None => value.to_string(),
+        },
+    };
+    Ok((name.to_string(), value))
+}
+
+fn get_header_value<'a>(headers: &'a [String], name: &str) -> Option<&'a str> {
+    headers.iter().find_map(|header| {
+        let (header_name, value) = header.split_once(':')?;
+        header_name
+            .trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+fn remove_header(headers: &mut Vec<String>, name: &str) {
+    headers.retain(|header| {
+        header
+            .split_once(':')
+            .map(|(header_name, _)| !header_name.trim().eq_ignore_ascii_case(name))
+            .unwrap_or(true)
+    });
+}
+
+/// Parses a curl command into a request, returning it with the options that were ignored.
+pub fn parse_curl_command(command: &str) -> Result<(HttpRequest, Vec<String>), String> {
+    let words = split_words(command.trim())?;
+    let mut words = words.into_iter().peekable();
+    if words.peek().map(String::as_str) == Some("curl") {
+        words.next();
+    }
+
+    let mut method = None;
+    let mut url = None;
+    let mut headers = Vec::new();
+    let mut data = Vec::new();
+    let mut form = Map::new();
+    let mut use_query = false;
+    let mut head = false;
+    let mut credentials = None;
+    let mut digest = false;
+    let mut insecure = false;
+    let mut json = false;
+    let mut bearer = None;
+    let mut ignored = Vec::new();
+
+    while let Some(word) = words.next() {
+        if word == "--digest" {
+            digest = true;
+            continue;
+        }
+        if word == "--" || !word.starts_with('-') || word.len() == 1 {
+            if word != "--" {
+                url = Some(word);
+            }
+            continue;
+        }
+        // Short options can be grouped (`-sSL`) or carry their value (`-XPOST`).
+        let (option, attached) = if word.starts_with("--") {
+            (word.clone(), None)
+        } else {
+            let mut option = String::new();
+            let mut attached = None;
+            for (index, char) in word.char_indices().skip(1) {
+                let short = format!("-{}", char);
+                if takes_value(&short) {
+                    let rest = &word[index + char.len_utf8()..];
+                    if !option.is_empty() {
+                        handle_flag(
+                            &option,
+                            &mut head,
+                            &mut use_query,
+                            &mut insecure,
+                            &mut ignored,
+                        )?;
+                    }
+                    option = short;
+                    attached = (!rest.is_empty()).then(|| rest.to_string());
+                    break;
+                }
+                if !option.is_empty() {
+                    handle_flag(
+                        &option,
+                        &mut head,
+                        &mut use_query,
+                        &mut insecure,
+                        &mut ignored,
+                    )?;
+                }
+                option = short;
+            }
+            (option, attached)
+        };
+
+        if !takes_value(&option) {
+            handle_flag(
+                &option,
+                &mut head,
+                &mut use_query,
+                &mut insecure,
+                &mut ignored,
+            )?;
+            continue;
+        }
+        let Some(value) = attached.or_else(|| words.next()) else {
+            return Err(format!("Missing value for {}", option));
+        };
+        match option.as_str() {
+            "-X" | "--request" => method = Some(value.to_uppercase()),
+            "-H" | "--header" if value.contains(':') => headers.push(value),
+            // `Name;` sends the header with an empty value.
+            "-H" | "--header" if value.ends_with(';') => {
+                headers.push(format!("{}:", value.trim_end_matches(';')))
+            }
+            "-H" | "--header" => ignored.push(format!("{} {} (not a header)", option, value)),
+            "-d" | "--data" | "--data-ascii" => data.push(read_data_value(&value, true)?),
+            "--data-raw" => data.push(value),
+            "--data-binary" => data.push(read_data_value(&value, false)?),
+            "--data-urlencode" => data.push(encode_data_value(&value)?),
+            "-F" | "--form" | "--form-string" => {
+                let (name, value) = parse_form_field(&value, option == "--form-string")?;
+                form.insert(name, Value::String(value));
+            }
+            "-u" | "--user" => {
+                let (username, password) = value.split_once(':').unwrap_or((&value, ""));
+                credentials = Some((username.to_string(), password.to_string()));
+            }
+            "-b" | "--cookie" if value.contains('=') => headers.push(format!("Cookie: {}", value)),
+            "-b" | "--cookie" => ignored.push(format!("{} {} (cookie files)", option, value)),
+            "-A" | "--user-agent" => headers.push(format!("User-Agent: {}", value)),
+            "-e" | "--referer" => headers.push(format!("Referer: {}", value)),
+            "--url" => url = Some(value),
+            "--json" => {
+                data.push(read_data_value(&value, false)?);
+                json = true;
+            }
+            "--oauth2-bearer" => bearer = Some(value),
+            option if IGNORED_OPTIONS_WITH_VALUE.contains(&option) => {
+                ignored.push(format!("{} {}", option, value))
+            }
+            _ => {}
+        }
+    }
+
+    let Some(mut url) = url else {
+        return Err("The curl command has no url".to_string());
+    };
+    // curl defaults to http where xhtp defaults to https.
+    if !url.contains("://") && !url.starts_with("{{") {
+        url = format!("http://{}", url);
+    }
+    // `--json` sends its data as JSON unless the headers say otherwise.
+    if json {
+        for header in ["Content-Type", "Accept"] {
+            if get_header_value(&headers, header).is_none() {
+                headers.push(format!("{}: application/json", header));
+            }
+        }
+    }
+    let data = data.join(if json { "" } else { "&" });
+    let mut body_type = None;
+    let mut body = None;
+    if use_query && !data.is_empty() {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(&data);
+    } else if !form.is_empty() {
+        remove_header(&mut headers, "Content-Type");
+        body_type = Some("multipart".to_string());
+        body = Some(Value::Object(form));
+    } else if !data.is_empty() {
+        let content_type = get_header_value(&headers, "Content-Type")
+            .unwrap_or_default()
+            .to_lowercase();
+        let json = serde_json::from_str::<Value>(&data).ok().filter(|value| {
+            content_type.contains("json")
+                || (content_type.is_empty() && (value.is_object() || value.is_array()))
+        });
+        let form = parse_form(&data)
+            .filter(|_| content_type.is_empty() || content_type.contains("x-www-form-urlencoded"));
+        if let Some(json) = json {
+            body_type = Some("json".to_string());
+            body = Some(json);
+        } else if let Some(form) = form {
+            remove_header(&mut headers, "Content-Type");
+            body_type = Some("form".to_string());
+            body = Some(form);
+        } else {
+            if content_type.is_empty() {
+                headers.push("Content-Type: application/x-www-form-urlencoded".to_string());
+            }
+            body_type = Some("text".to_string());
+            body = Some(Value::String(data));
+        }
+    }
+
+    let method = method.unwrap_or_else(|| {
+        if head {
+            "HEAD".to_string()
+        } else if body.is_some() {
+            "POST".to_string()
+        } else {
+            "GET".to_string()
+        }
+    });
+
+    let auth = credentials
+        .map(|(username, password)| match digest {
+            true => Auth::Digest { username, password },
+            false => Auth::Basic { username, password },
+        })
+        .or(bearer.map(|token| Auth::Bearer { token }));
+
+    let request = HttpRequest {
+        method,
+        url,
+        headers,
+        body_type,
+        body,
+        auth,
+        insecure: insecure.then_some(true),
+        ..Default::default()
+    };
+    Ok((request, ignored))
+}
+
+/// Applies a flag, failing on options curl may read a value for, which would otherwise be
+/// taken as the url.
+fn handle_flag(
+    option: &str,
+    head: &mut bool,
+    use_query: &mut bool,
+    insecure: &mut bool,
+    ignored: &mut Vec<String>,
+) -> Result<(), String> {
+    match option {
+        "-I" | "--head" => *head = true,
+        "-G" | "--get" => *use_query = true,
+        "-k" | "--insecure" => *insecure = true,
+        option if SILENT_FLAGS.contains(&option) => {}
+        option if IGNORED_FLAGS.contains(&option) => ignored.push(option.to_string()),
+        option => {
+            return Err(format!(
+                "Unknown curl option {}, remove it from the command to import it",
+                option
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Imports a curl command, read from stdin when it's missing or `-`, and appends it to the
+/// saved requests.
+pub fn handle_curl_import(command: Option<&str>) -> Result<(), String> {
+    let command = match command {
+        Some(command) if command != "-" => command.to_string(),
+        _ => {
+            let mut command = String::new();
+            std::io::stdin()
+                .read_to_string(&mut command)
+                .map_err(|err| format!("Failed to read the curl command from stdin: {}", err))?;
+            command
+        }
+    };
+    let (request, ignored) = parse_curl_command(&command)?;
+    if !ignored.is_empty() {
+        print_line(&format!("Ignored {}", ignored.join(", ")));
+    }
+    let mut requests = read_http_request_file();
+    requests.push(request);
+    write_http_request_file(&requests);
+    print_line(&format!(
+        "Saved the curl command as request {}",
+        requests.len()
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(command: &str) -> (HttpRequest, Vec<String>) {
+        parse_curl_command(command).unwrap()
+    }
+
+    #[test]
+    fn splits_words_like_a_shell() {
+        assert_eq!(
+            split_words("curl 'a b' \"c \\\"d\\\" \\$e \\x\" f\\ g $'h\\ti\\x41\\u00e9' j'k'\"l\"")
+                .unwrap(),
+            ["curl", "a b", "c \"d\" $e \\x", "f g", "h\tiAé", "jkl"]
+        );
+        assert_eq!(
+            split_words("curl \\\n  -H 'A: 1' \\\r\n  x").unwrap(),
+            ["curl", "-H", "A: 1", "x"]
+        );
+        assert_eq!(split_words("curl ''").unwrap(), ["curl", ""]);
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        assert!(split_words("curl 'a").is_err());
+        assert!(split_words("curl \"a").is_err());
+        assert!(split_words("curl $'a").is_err());
+    }
+
+    #[test]
+    fn parses_a_devtools_command() {
+        let (request, ignored) = parse(
+            "curl 'https://api.example.com/items?page=2' \\
+              -H 'accept: application/json' \\
+              -H 'content-type: application/json' \\
+              -b 'session=abc' \\
+              --data-raw '{\"name\":\"a\"}' \\
+              --compressed",
+        );
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "https://api.example.com/items?page=2");
+        assert_eq!(
+            request.headers,
+            [
+                "accept: application/json",
+                "content-type: application/json",
+                "Cookie: session=abc"
+            ]
+        );
+        assert_eq!(request.body_type.as_deref(), Some("json"));
+        assert_eq!(request.body, Some(json!({ "name": "a" })));
+        assert!(ignored.is_empty());
+    }
+
+    #[test]
+    fn reads_grouped_and_attached_short_options() {
+        let (request, _) = parse("curl -sSLXPUT -kuuser:pass -Hx:1 example.com");
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.url, "http://example.com");
+        assert_eq!(request.headers, ["x:1"]);
+        assert_eq!(request.insecure, Some(true));
+        assert_eq!(
+            request.auth,
+            Some(Auth::Basic {
+                username: "user".to_string(),
+                password: "pass".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn skips_the_values_of_options_it_does_not_use() {
+        let (request, ignored) = parse(
+            "curl https://api.example.com/b --max-redirs 5 -o out.json -m 3 \
+             --cacert ca.pem -E cert.pem -r 0-99 --resolve api.example.com:443:127.0.0.1 \
+             -T file.txt --http2",
+        );
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.url, "https://api.example.com/b");
+        assert_eq!(
+            ignored,
+            [
+                "--cacert ca.pem",
+                "-E cert.pem",
+                "-r 0-99",
+                "--resolve api.example.com:443:127.0.0.1",
+                "-T file.txt",
+                "--http2"
+            ]
+        );
+    }
+
+    #[test]
+    fn fails_on_unknown_options() {
+        assert_eq!(
+            parse_curl_command("curl https://api.example.com --frobnicate 5").unwrap_err(),
+            "Unknown curl option --frobnicate, remove it from the command to import it"
+        );
+        assert_eq!(
+            parse_curl_command("curl -H").unwrap_err(),
+            "Missing value for -H"
+        );
+        assert_eq!(
+            parse_curl_command("curl -s").unwrap_err(),
+            "The curl command has no url"
+        );
+    }
+
+    #[test]
+    fn reports_header_values_without_a_colon() {
+        let (request, ignored) = parse("curl example.com -H 'X-Empty;' -H 'oops'");
+        assert_eq!(request.headers, ["X-Empty:"]);
+        assert_eq!(ignored, ["-H oops (not a header)"]);
+    }
+
+    #[test]
+    fn sends_json_data_with_json_headers() {
+        let (request, _) = parse("curl example.com --json '{\"a\":' --json '1}'");
+        assert_eq!(request.method, "POST");
+        assert_eq!(
+            request.headers,
+            ["Content-Type: application/json", "Accept: application/json"]
+        );
+        assert_eq!(request.body, Some(json!({ "a": 1 })));
+    }
+
+    #[test]
+    fn turns_data_into_form_query_and_text_bodies() {
+        let (request, _) = parse("curl example.com -d a=1 --data-urlencode 'b=x y'");
+        assert_eq!(request.body_type.as_deref(), Some("form"));
+        assert_eq!(request.body, Some(json!({ "a": "1", "b": "x y" })));
+
+        let (request, _) = parse("curl -G example.com/s?x=1 -d q=rust");
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.url, "http://example.com/s?x=1&q=rust");
+        assert_eq!(request.body, None);
+
+        let (request, _) = parse("curl example.com -H 'Content-Type: text/plain' -d 'hi there'");
+        assert_eq!(request.body_type.as_deref(), Some("text"));
+        assert_eq!(request.body, Some(json!("hi there")));
+
+        let (request, _) = parse("curl example.com -F name=a -F 'file=@photo.png;type=image/png'");
+        assert_eq!(request.body_type.as_deref(), Some("multipart"));
+        assert_eq!(
+            request.body,
+            Some(json!({ "name": "a", "file": "@photo.png" }))
+        );
+    }
+
+    #[test]
+    fn reads_auth_options() {
+        let (request, _) = parse("curl example.com -u alice --digest");
+        assert_eq!(
+            request.auth,
+            Some(Auth::Digest {
+                username: "alice".to_string(),
+                password: String::new()
+            })
+        );
+        let (request, _) = parse("curl -I example.com --oauth2-bearer t0k3n");
+        assert_eq!(request.method, "HEAD");
+        assert_eq!(
+            request.auth,
+            Some(Auth::Bearer {
+                token: "t0k3n".to_string()
+            })
+        );
+    }
+}
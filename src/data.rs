@@ -0,0 +1,56 @@
+use crate::structs::GlobalVariable;
+use crate::utils;
+use serde_json::Value;
+use std::fs;
+
+fn read_csv_rows(path: &str) -> Result<Vec<Vec<GlobalVariable>>, String> {
+    let mut reader = csv::Reader::from_path(path).map_err(|err| err.to_string())?;
+    let headers = reader.headers().map_err(|err| err.to_string())?.clone();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|err| err.to_string())?;
+        rows.push(
+            headers
+                .iter()
+                .zip(record.iter())
+                .map(|(key, value)| GlobalVariable {
+                    key: key.trim().to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+        );
+    }
+    Ok(rows)
+}
+
+fn read_json_rows(path: &str) -> Result<Vec<Vec<GlobalVariable>>, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let json: Value = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+    let Value::Array(items) = json else {
+        return Err("expected an array of objects".to_string());
+    };
+    items
+        .iter()
+        .map(|item| match item {
+            Value::Object(map) => Ok(map
+                .iter()
+                .map(|(key, value)| GlobalVariable {
+                    key: key.clone(),
+                    value: utils::json_value_to_variable(value),
+                })
+                .collect()),
+            _ => Err(format!("expected an object but found {}", item)),
+        })
+        .collect()
+}
+
+/// Reads a CSV file with a header row, or a JSON array of objects, into one
+/// set of variables per row.
+pub fn read_data_rows(path: &str) -> Result<Vec<Vec<GlobalVariable>>, String> {
+    let rows = if path.to_lowercase().ends_with(".json") {
+        read_json_rows(path)
+    } else {
+        read_csv_rows(path)
+    };
+    rows.map_err(|err| format!("Failed to read data from {}: {}", path, err))
+}
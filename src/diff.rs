@@ -0,0 +1,443 @@
+//! Structural comparison of two responses, e.g. a history entry against a live run.
+
+use crate::client::ReceivedResponse;
+use crate::history;
+use crate::structs::HistoryEntry;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Headers that differ between any two responses, always left out of the comparison.
+const VOLATILE_HEADERS: [&str; 1] = ["date"];
+
+pub const REDACTED: &str = "[redacted]";
+
+/// One side of a comparison.
+pub struct DiffSide {
+    pub label: String,
+    pub status_code: Option<u16>,
+    pub headers: Vec<String>,
+    pub body: Option<Value>,
+}
+
+impl DiffSide {
+    pub fn from_entry(entry: &HistoryEntry) -> DiffSide {
+        DiffSide {
+            label: format!("history {} ({})", entry.id, entry.timestamp),
+            status_code: entry.status_code,
+            headers: entry.response_headers.clone(),
+            body: entry.response_body.clone(),
+        }
+    }
+
+    /// Masks the response like the history does, so it can be compared against a history entry.
+    pub fn from_received(label: &str, received: &ReceivedResponse) -> DiffSide {
+        DiffSide {
+            label: label.to_string(),
+            status_code: Some(received.status_code),
+            headers: history::mask_response_headers(received),
+            body: history::mask_response_body(received),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn format_path(path: &[Segment]) -> String {
+    let mut formatted = "$".to_string();
+    for segment in path {
+        match segment {
+            Segment::Key(key) => formatted.push_str(&format!(".{}", key)),
+            Segment::Index(index) => formatted.push_str(&format!("[{}]", index)),
+        }
+    }
+    formatted
+}
+
+/// Splits `$.items[0].id` into `["items", "0", "id"]`.
+fn parse_pattern(pattern: &str) -> Vec<String> {
+    pattern
+        .trim_start_matches('$')
+        .replace('[', ".")
+        .replace(']', "")
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.to_string())
+        .collect()
+}
+
+/// A pattern with a single segment, like `id`, matches that key at any depth. Longer patterns,
+/// and any starting with `$`, match from the root, with `*` matching any key or index, like
+/// `items.*.updatedAt`.
+fn matches_any(path: &[Segment], patterns: &[String]) -> bool {
+    let segment_matches = |pattern: &str, segment: &Segment| match segment {
+        _ if pattern == "*" => true,
+        Segment::Key(key) => key == pattern,
+        Segment::Index(index) => index.to_string() == pattern,
+    };
+    patterns.iter().any(|pattern| {
+        let rooted = pattern.starts_with('$');
+        let pattern = parse_pattern(pattern);
+        match pattern.as_slice() {
+            [] => false,
+            [single] if !rooted => path
+                .last()
+                .map(|segment| segment_matches(single, segment))
+                .unwrap_or(false),
+            _ => {
+                pattern.len() == path.len()
+                    && pattern
+                        .iter()
+                        .zip(path)
+                        .all(|(pattern, segment)| segment_matches(pattern, segment))
+            }
+        }
+    })
+}
+
+fn redact_values(value: &mut Value, path: &mut Vec<Segment>, patterns: &[String]) {
+    if !path.is_empty() && matches_any(path, patterns) {
+        *value = Value::String(REDACTED.to_string());
+        return;
+    }
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                path.push(Segment::Key(key.clone()));
+                redact_values(item, path, patterns);
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                path.push(Segment::Index(index));
+                redact_values(item, path, patterns);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces the values at the paths matching `patterns` with `"[redacted]"`, so that only
+/// their presence is compared.
+pub fn redact(value: &mut Value, patterns: &[String]) {
+    redact_values(value, &mut Vec::new(), patterns);
+}
+
+pub enum Change {
+    Added(String, Value),
+    Removed(String, Value),
+    Changed(String, Value, Value),
+}
+
+impl Change {
+    pub fn describe(&self) -> String {
+        match self {
+            Change::Added(path, value) => format!("+ {}: {}", path, value),
+            Change::Removed(path, value) => format!("- {}: {}", path, value),
+            Change::Changed(path, old, new) => format!("~ {}: {} -> {}", path, old, new),
+        }
+    }
+}
+
+fn diff_values(
+    left: &Value,
+    right: &Value,
+    path: &mut Vec<Segment>,
+    ignore: &[String],
+    changes: &mut Vec<Change>,
+) {
+    if matches_any(path, ignore) {
+        return;
+    }
+    match (left, right) {
+        (Value::Object(left_map), Value::Object(right_map)) => {
+            for (key, left_value) in left_map {
+                path.push(Segment::Key(key.clone()));
+                match right_map.get(key) {
+                    Some(right_value) => {
+                        diff_values(left_value, right_value, path, ignore, changes)
+                    }
+                    None if !matches_any(path, ignore) => {
+                        changes.push(Change::Removed(format_path(path), left_value.clone()))
+                    }
+                    None => {}
+                }
+                path.pop();
+            }
+            for (key, right_value) in right_map {
+                if left_map.contains_key(key) {
+                    continue;
+                }
+                path.push(Segment::Key(key.clone()));
+                if !matches_any(path, ignore) {
+                    changes.push(Change::Added(format_path(path), right_value.clone()));
+                }
+                path.pop();
+            }
+        }
+        (Value::Array(left_items), Value::Array(right_items)) => {
+            for index in 0..left_items.len().max(right_items.len()) {
+                path.push(Segment::Index(index));
+                match (left_items.get(index), right_items.get(index)) {
+                    (Some(left_item), Some(right_item)) => {
+                        diff_values(left_item, right_item, path, ignore, changes)
+                    }
+                    _ if matches_any(path, ignore) => {}
+                    (Some(left_item), None) => {
+                        changes.push(Change::Removed(format_path(path), left_item.clone()))
+                    }
+                    (None, Some(right_item)) => {
+                        changes.push(Change::Added(format_path(path), right_item.clone()))
+                    }
+                    (None, None) => {}
+                }
+                path.pop();
+            }
+        }
+        _ if left != right => changes.push(Change::Changed(
+            format_path(path),
+            left.clone(),
+            right.clone(),
+        )),
+        _ => {}
+    }
+}
+
+/// Lists the paths that were added, removed or changed between two JSON values.
+pub fn diff_json(left: &Value, right: &Value, ignore: &[String]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_values(left, right, &mut Vec::new(), ignore, &mut changes);
+    changes
+}
+
+/// Header names are compared case-insensitively, repeated headers are joined with `, `.
+fn headers_to_json(headers: &[String], ignore: &[String]) -> Value {
+    let mut map: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for header in headers {
+        let (name, value) = header.split_once(':').unwrap_or((header, ""));
+        let name = name.trim().to_lowercase();
+        let ignored = VOLATILE_HEADERS.contains(&name.as_str())
+            || ignore
+                .iter()
+                .any(|pattern| pattern.eq_ignore_ascii_case(&name));
+        if !ignored {
+            map.entry(name).or_default().push(value.trim().to_string());
+        }
+    }
+    Value::Object(
+        map.into_iter()
+            .map(|(name, values)| (name, Value::String(values.join(", "))))
+            .collect(),
+    )
+}
+
+pub struct Comparison {
+    pub status: Option<(Option<u16>, Option<u16>)>,
+    pub headers: Vec<Change>,
+    pub body: Vec<Change>,
+}
+
+impl Comparison {
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none() && self.headers.is_empty() && self.body.is_empty()
+    }
+
+    pub fn print(&self, left: &DiffSide, right: &DiffSide) {
+        println!("\x1b[31m--- {}\x1b[0m", left.label);
+        println!("\x1b[32m+++ {}\x1b[0m", right.label);
+        if self.is_empty() {
+            println!("No differences");
+            return;
+        }
+        if let Some((left_status, right_status)) = self.status {
+            let format_status = |status: Option<u16>| {
+                status
+                    .map(|status| status.to_string())
+                    .unwrap_or_else(|| "none".to_string())
+            };
+            println!(
+                "status: \x1b[33m{} -> {}\x1b[0m",
+                format_status(left_status),
+                format_status(right_status)
+            );
+        }
+        if !self.headers.is_empty() {
+            println!("headers:");
+            print_changes(&self.headers, true);
+        }
+        if !self.body.is_empty() {
+            println!("body:");
+            print_changes(&self.body, false);
+        }
+    }
+}
+
+fn print_changes(changes: &[Change], headers: bool) {
+    let format_value = |value: &Value| match value {
+        Value::String(text) if headers => text.clone(),
+        value => value.to_string(),
+    };
+    let format_path = |path: &str| {
+        if headers {
+            path.trim_start_matches("$.").to_string()
+        } else {
+            path.to_string()
+        }
+    };
+    for change in changes {
+        match change {
+            Change::Added(path, value) => println!(
+                "  \x1b[32m+ {}: {}\x1b[0m",
+                format_path(path),
+                format_value(value)
+            ),
+            Change::Removed(path, value) => println!(
+                "  \x1b[31m- {}: {}\x1b[0m",
+                format_path(path),
+                format_value(value)
+            ),
+            Change::Changed(path, old, new) => println!(
+                "  \x1b[33m~ {}: {} -> {}\x1b[0m",
+                format_path(path),
+                format_value(old),
+                format_value(new)
+            ),
+        }
+    }
+}
+
+/// Compares the status code, headers and body of two responses, skipping the `ignore` paths.
+pub fn compare(left: &DiffSide, right: &DiffSide, ignore: &[String]) -> Comparison {
+    let headers = diff_json(
+        &headers_to_json(&left.headers, ignore),
+        &headers_to_json(&right.headers, ignore),
+        &[],
+    );
+    let body = diff_json(
+        left.body.as_ref().unwrap_or(&Value::Null),
+        right.body.as_ref().unwrap_or(&Value::Null),
+        ignore,
+    );
+    Comparison {
+        status: (left.status_code != right.status_code)
+            .then_some((left.status_code, right.status_code)),
+        headers,
+        body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn describe(changes: &[Change]) -> Vec<String> {
+        changes.iter().map(Change::describe).collect()
+    }
+
+    fn ignore(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn lists_added_removed_and_changed_paths() {
+        let left = json!({ "id": 1, "name": "Ada", "tags": ["a", "b"], "meta": { "v": 1 } });
+        let right =
+            json!({ "id": 1, "name": "Grace", "tags": ["a"], "meta": { "v": 1, "w": null } });
+        assert_eq!(
+            describe(&diff_json(&left, &right, &[])),
+            vec![
+                "+ $.meta.w: null",
+                "~ $.name: \"Ada\" -> \"Grace\"",
+                "- $.tags[1]: \"b\"",
+            ]
+        );
+        assert!(diff_json(&left, &left, &[]).is_empty());
+        assert_eq!(
+            describe(&diff_json(&json!([1]), &json!({ "a": 1 }), &[])),
+            vec!["~ $: [1] -> {\"a\":1}"]
+        );
+    }
+
+    #[test]
+    fn skips_ignored_paths() {
+        let left = json!({
+            "id": 1,
+            "items": [{ "id": 10, "updatedAt": "mon" }],
+            "owner": { "id": 5 }
+        });
+        let right = json!({
+            "id": 2,
+            "items": [{ "id": 11, "updatedAt": "tue" }, { "id": 12 }],
+            "owner": { "id": 6 }
+        });
+        // A single segment matches the key at any depth.
+        assert_eq!(
+            describe(&diff_json(&left, &right, &ignore(&["id"]))),
+            vec![
+                "~ $.items[0].updatedAt: \"mon\" -> \"tue\"",
+                "+ $.items[1]: {\"id\":12}"
+            ]
+        );
+        // Longer patterns match from the root, with `*` for any key or index.
+        assert_eq!(
+            describe(&diff_json(
+                &left,
+                &right,
+                &ignore(&["$.items[*].updatedAt", "items.1", "$.id"])
+            )),
+            vec!["~ $.items[0].id: 10 -> 11", "~ $.owner.id: 5 -> 6"]
+        );
+    }
+
+    #[test]
+    fn compares_status_and_headers() {
+        let side = |status_code: u16, headers: &[&str], body: Value| DiffSide {
+            label: "side".to_string(),
+            status_code: Some(status_code),
+            headers: headers.iter().map(|header| header.to_string()).collect(),
+            body: Some(body),
+        };
+        let left = side(
+            200,
+            &[
+                "Date: mon",
+                "ETag: 1",
+                "Vary: Accept",
+                "vary: Origin",
+                "X-Id: a",
+            ],
+            json!({ "id": 1 }),
+        );
+        let right = side(
+            201,
+            &["date: tue", "etag: 2", "Vary: Accept, Origin"],
+            json!({ "id": 1 }),
+        );
+        let comparison = compare(&left, &right, &ignore(&["x-id"]));
+        assert_eq!(comparison.status, Some((Some(200), Some(201))));
+        assert_eq!(
+            describe(&comparison.headers),
+            vec!["~ $.etag: \"1\" -> \"2\""]
+        );
+        assert!(comparison.body.is_empty());
+
+        let comparison = compare(&left, &left, &[]);
+        assert!(comparison.is_empty());
+    }
+
+    #[test]
+    fn redacts_matching_values() {
+        let mut value = json!({ "token": "abc", "items": [{ "createdAt": 1, "name": "x" }] });
+        redact(&mut value, &ignore(&["token", "items.*.createdAt"]));
+        assert_eq!(
+            value,
+            json!({ "token": REDACTED, "items": [{ "createdAt": REDACTED, "name": "x" }] })
+        );
+    }
+}
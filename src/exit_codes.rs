@@ -0,0 +1,52 @@
+//! Process exit codes. Status based codes are only used when `--check-status` is passed.
+
+use crate::client::SendError;
+use crate::structs::RequestOutcome;
+
+pub const SUCCESS: i32 = 0;
+pub const ERROR: i32 = 1;
+// 2 is left to clap, which exits with it on invalid arguments.
+pub const REDIRECTION: i32 = 3;
+pub const CLIENT_ERROR: i32 = 4;
+pub const SERVER_ERROR: i32 = 5;
+pub const NETWORK_ERROR: i32 = 6;
+pub const ASSERTION_FAILED: i32 = 7;
+pub const DIFFERENT: i32 = 8;
+pub const TIMEOUT: i32 = 9;
+
+pub fn from_status(status_code: u16) -> i32 {
+    match status_code {
+        300..=399 => REDIRECTION,
+        400..=499 => CLIENT_ERROR,
+        500..=599 => SERVER_ERROR,
+        _ => SUCCESS,
+    }
+}
+
+/// Failed assertions take precedence over the response status.
+pub fn from_outcome(outcome: &RequestOutcome, check_status: bool) -> i32 {
+    if outcome
+        .assertion_results
+        .iter()
+        .any(|result| !result.passed)
+    {
+        ASSERTION_FAILED
+    } else if check_status {
+        from_status(outcome.status_code)
+    } else {
+        SUCCESS
+    }
+}
+
+pub fn from_error(err: &SendError) -> i32 {
+    let SendError::Http(err) = err else {
+        return ERROR;
+    };
+    if err.is_timeout() {
+        TIMEOUT
+    } else if err.is_builder() {
+        ERROR
+    } else {
+        NETWORK_ERROR
+    }
+}
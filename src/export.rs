@@ -0,0 +1,760 @@
+//! Renders saved requests as snippets for curl, HTTPie, Python, JavaScript and Rust.
+
+use crate::auth;
+use crate::structs::{Auth, HttpRequest};
+use crate::utils::get_url_with_https;
+use serde_json::Value;
+
+pub enum SnippetFormat {
+    Curl,
+    Httpie,
+    PythonRequests,
+    JsFetch,
+    RustReqwest,
+}
+
+impl SnippetFormat {
+    pub fn from_arg(format: Option<&str>) -> Result<SnippetFormat, String> {
+        match format.unwrap_or("curl") {
+            "curl" => Ok(SnippetFormat::Curl),
+            "httpie" => Ok(SnippetFormat::Httpie),
+            "python-requests" => Ok(SnippetFormat::PythonRequests),
+            "js-fetch" => Ok(SnippetFormat::JsFetch),
+            "rust-reqwest" => Ok(SnippetFormat::RustReqwest),
+            format => Err(format!(
+                "Unknown format {}, use curl, httpie, python-requests, js-fetch or rust-reqwest",
+                format
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Language {
+    Python,
+    JavaScript,
+    Rust,
+}
+
+impl Language {
+    fn string(self, text: &str) -> String {
+        match self {
+            Language::Rust => format!("{:?}", text),
+            _ => Value::String(text.to_string()).to_string(),
+        }
+    }
+
+    fn literal(self, value: &Value) -> String {
+        match (self, value) {
+            (Language::Python, Value::Null) => "None".to_string(),
+            (Language::Python, Value::Bool(true)) => "True".to_string(),
+            (Language::Python, Value::Bool(false)) => "False".to_string(),
+            (_, Value::String(text)) => self.string(text),
+            (_, value) => value.to_string(),
+        }
+    }
+
+    fn indentation(self) -> &'static str {
+        match self {
+            Language::JavaScript => "  ",
+            _ => "    ",
+        }
+    }
+
+    /// Pretty prints a JSON value as a Python dict, a JavaScript object or the input of `json!`.
+    fn value(self, value: &Value, indent: usize) -> String {
+        let padding = self.indentation().repeat(indent + 1);
+        let closing = self.indentation().repeat(indent);
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                let fields: Vec<String> = map
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{}{}: {},\n",
+                            padding,
+                            self.string(key),
+                            self.value(value, indent + 1)
+                        )
+                    })
+                    .collect();
+                format!("{{\n{}{}}}", fields.concat(), closing)
+            }
+            Value::Array(items) if !items.is_empty() => {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|item| format!("{}{},\n", padding, self.value(item, indent + 1)))
+                    .collect();
+                format!("[\n{}{}]", items.concat(), closing)
+            }
+            value => self.literal(value),
+        }
+    }
+}
+
+/// Quotes a word for a POSIX shell.
+fn shell_quote(word: &str) -> String {
+    if !word.is_empty()
+        && word
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || "-_./:=@%+,".contains(char))
+    {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+fn split_header(header: &str) -> (String, String) {
+    let (name, value) = header.split_once(':').unwrap_or((header, ""));
+    (name.trim().to_string(), value.trim().to_string())
+}
+
+fn has_header(request: &HttpRequest, name: &str) -> bool {
+    request
+        .headers
+        .iter()
+        .any(|header| split_header(header).0.eq_ignore_ascii_case(name))
+}
+
+/// Form and multipart fields, with numbers and booleans as text.
+fn get_fields(body: &Value) -> Vec<(String, String)> {
+    let Value::Object(fields) = body else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .map(|(name, value)| match value {
+            Value::String(text) => (name.clone(), text.clone()),
+            value => (name.clone(), value.to_string()),
+        })
+        .collect()
+}
+
+fn get_text_body(body: &Value) -> String {
+    match body {
+        Value::String(text) => text.clone(),
+        body => body.to_string(),
+    }
+}
+
+/// Basic and digest credentials, which the clients send in their own way.
+enum Credentials<'a> {
+    Basic(&'a str, &'a str),
+    Digest(&'a str, &'a str),
+}
+
+fn get_credentials(request: &HttpRequest) -> Option<Credentials<'_>> {
+    match &request.auth {
+        Some(Auth::Basic { username, password }) => Some(Credentials::Basic(username, password)),
+        Some(Auth::Digest { username, password }) => Some(Credentials::Digest(username, password)),
+        _ => None,
+    }
+}
+
+/// Urls keep their placeholder when they start with one, like `{{baseUrl}}/users`.
+fn get_url(request: &HttpRequest) -> String {
+    if request.url.starts_with("{{") {
+        request.url.clone()
+    } else {
+        get_url_with_https(&request.url)
+    }
+}
+
+fn render_curl(request: &HttpRequest) -> String {
+    let mut lines = vec![format!(
+        "curl -X {} {}",
+        shell_quote(&request.method),
+        shell_quote(&get_url(request))
+    )];
+    if request.insecure == Some(true) {
+        lines.push("-k".to_string());
+    }
+    match get_credentials(request) {
+        Some(Credentials::Basic(username, password)) => {
+            lines.push(format!(
+                "-u {}",
+                shell_quote(&format!("{}:{}", username, password))
+            ));
+        }
+        Some(Credentials::Digest(username, password)) => {
+            lines.push(format!(
+                "--digest -u {}",
+                shell_quote(&format!("{}:{}", username, password))
+            ));
+        }
+        None => {}
+    }
+    if let Some(Auth::AwsSigv4 {
+        access_key_id,
+        secret_access_key,
+        session_token,
+        region,
+        service,
+    }) = &request.auth
+    {
+        // Credentials left to the environment are read from it by the shell.
+        let from_env = |value: &str, name: &str| match value {
+            "" => format!("${}", name),
+            value => value.replace('"', "\\\""),
+        };
+        lines.push(format!(
+            "--aws-sigv4 \"aws:amz:{}:{}\"",
+            from_env(region, "AWS_REGION"),
+            service
+        ));
+        lines.push(format!(
+            "-u \"{}:{}\"",
+            from_env(access_key_id, "AWS_ACCESS_KEY_ID"),
+            from_env(secret_access_key, "AWS_SECRET_ACCESS_KEY")
+        ));
+        if !session_token.is_empty() || access_key_id.is_empty() {
+            lines.push(format!(
+                "-H \"x-amz-security-token: {}\"",
+                from_env(session_token, "AWS_SESSION_TOKEN")
+            ));
+        }
+    }
+    for header in &request.headers {
+        lines.push(format!("-H {}", shell_quote(header)));
+    }
+    match (request.body_type.as_deref(), &request.body) {
+        (_, None) => {}
+        (Some("form"), Some(body)) => {
+            for (name, value) in get_fields(body) {
+                lines.push(format!(
+                    "--data-urlencode {}",
+                    shell_quote(&format!("{}={}", name, value))
+                ));
+            }
+        }
+        (Some("multipart"), Some(body)) => {
+            for (name, value) in get_fields(body) {
+                let option = if value.starts_with('@') {
+                    "-F"
+                } else {
+                    "--form-string"
+                };
+                lines.push(format!(
+                    "{} {}",
+                    option,
+                    shell_quote(&format!("{}={}", name, value))
+                ));
+            }
+        }
+        (Some("text"), Some(body)) => {
+            lines.push(format!("--data-raw {}", shell_quote(&get_text_body(body))));
+        }
+        (_, Some(body)) => {
+            if !has_header(request, "Content-Type") {
+                lines.push("-H 'Content-Type: application/json'".to_string());
+            }
+            lines.push(format!("--data-raw {}", shell_quote(&body.to_string())));
+        }
+    }
+    lines.join(" \\\n  ")
+}
+
+fn render_httpie(request: &HttpRequest) -> String {
+    let mut words = vec!["http".to_string()];
+    if request.insecure == Some(true) {
+        words.push("--verify=no".to_string());
+    }
+    match get_credentials(request) {
+        Some(Credentials::Basic(username, password)) => {
+            words.push(format!(
+                "-a {}",
+                shell_quote(&format!("{}:{}", username, password))
+            ));
+        }
+        Some(Credentials::Digest(username, password)) => {
+            words.push(format!(
+                "-A digest -a {}",
+                shell_quote(&format!("{}:{}", username, password))
+            ));
+        }
+        None => {}
+    }
+    let mut items = Vec::new();
+    for header in &request.headers {
+        let (name, value) = split_header(header);
+        items.push(shell_quote(&format!("{}:{}", name, value)));
+    }
+    match (request.body_type.as_deref(), &request.body) {
+        (_, None) => {}
+        (Some("form"), Some(body)) => {
+            words.push("--form".to_string());
+            for (name, value) in get_fields(body) {
+                items.push(shell_quote(&format!("{}={}", name, value)));
+            }
+        }
+        (Some("multipart"), Some(body)) => {
+            words.push("--multipart".to_string());
+            for (name, value) in get_fields(body) {
+                let item = match value.strip_prefix('@') {
+                    Some(path) => format!("{}@{}", name, path),
+                    None => format!("{}={}", name, value),
+                };
+                items.push(shell_quote(&item));
+            }
+        }
+        (Some("text"), Some(body)) => {
+            words.push(format!("--raw {}", shell_quote(&get_text_body(body))));
+        }
+        (_, Some(body)) => {
+            words.push(format!("--raw {}", shell_quote(&body.to_string())));
+            if !has_header(request, "Content-Type") {
+                items.push("Content-Type:application/json".to_string());
+            }
+        }
+    }
+    words.push(shell_quote(&request.method));
+    words.push(shell_quote(&get_url(request)));
+    words.extend(items);
+    words.join(" ")
+}
+
+fn render_python_requests(request: &HttpRequest) -> String {
+    let language = Language::Python;
+    let mut arguments = vec![
+        language.string(&request.method),
+        language.string(&get_url(request)),
+    ];
+    if !request.headers.is_empty() {
+        let headers: serde_json::Map<String, Value> = request
+            .headers
+            .iter()
+            .map(|header| {
+                let (name, value) = split_header(header);
+                (name, Value::String(value))
+            })
+            .collect();
+        arguments.push(format!(
+            "headers={}",
+            language.value(&Value::Object(headers), 0)
+        ));
+    }
+    let mut imports = "import requests\n".to_string();
+    match get_credentials(request) {
+        Some(Credentials::Basic(username, password)) => arguments.push(format!(
+            "auth=({}, {})",
+            language.string(username),
+            language.string(password)
+        )),
+        Some(Credentials::Digest(username, password)) => {
+            imports.push_str("from requests.auth import HTTPDigestAuth\n");
+            arguments.push(format!(
+                "auth=HTTPDigestAuth({}, {})",
+                language.string(username),
+                language.string(password)
+            ));
+        }
+        None => {}
+    }
+    match (request.body_type.as_deref(), &request.body) {
+        (_, None) => {}
+        (Some("form"), Some(body)) => {
+            arguments.push(format!("data={}", language.value(body, 0)));
+        }
+        (Some("multipart"), Some(body)) => {
+            let (files, data): (Vec<_>, Vec<_>) = get_fields(body)
+                .into_iter()
+                .partition(|(_, value)| value.starts_with('@'));
+            if !data.is_empty() {
+                let data: Vec<String> = data
+                    .iter()
+                    .map(|(name, value)| {
+                        format!(
+                            "    {}: {},\n",
+                            language.string(name),
+                            language.string(value)
+                        )
+                    })
+                    .collect();
+                arguments.push(format!("data={{\n{}}}", data.concat()));
+            }
+            if !files.is_empty() {
+                let files: Vec<String> = files
+                    .iter()
+                    .map(|(name, value)| {
+                        format!(
+                            "    {}: open({}, \"rb\"),\n",
+                            language.string(name),
+                            language.string(&value[1..])
+                        )
+                    })
+                    .collect();
+                arguments.push(format!("files={{\n{}}}", files.concat()));
+            }
+        }
+        (Some("text"), Some(body)) => {
+            arguments.push(format!("data={}", language.string(&get_text_body(body))));
+        }
+        (_, Some(body)) => arguments.push(format!("json={}", language.value(body, 0))),
+    }
+    format!(
+        "{}\nresponse = requests.request(\n{})\nprint(response.status_code)\nprint(response.text)",
+        imports,
+        indent_arguments(&arguments)
+    )
+}
+
+/// Puts each argument on its own indented line.
+fn indent_arguments(arguments: &[String]) -> String {
+    arguments
+        .iter()
+        .map(|argument| format!("    {},\n", argument.replace('\n', "\n    ")))
+        .collect()
+}
+
+fn render_js_fetch(request: &HttpRequest) -> String {
+    let language = Language::JavaScript;
+    let mut imports = String::new();
+    let mut setup = String::new();
+    let mut options = vec![format!("method: {}", language.string(&request.method))];
+    let mut headers: serde_json::Map<String, Value> = request
+        .headers
+        .iter()
+        .map(|header| {
+            let (name, value) = split_header(header);
+            (name, Value::String(value))
+        })
+        .collect();
+    match get_credentials(request) {
+        Some(Credentials::Basic(username, password)) => {
+            let (name, value) = split_header(&auth::basic_header(username, password));
+            headers.insert(name, Value::String(value));
+        }
+        Some(Credentials::Digest(..)) => {
+            setup.push_str("// fetch can't answer digest challenges, use a digest auth library.\n");
+        }
+        None => {}
+    }
+    // fetch sends strings as text/plain, unlike the json helpers of the other clients.
+    let is_json = request.body.is_some()
+        && !matches!(
+            request.body_type.as_deref(),
+            Some("form") | Some("multipart") | Some("text")
+        );
+    if is_json && !has_header(request, "Content-Type") {
+        headers.insert(
+            "Content-Type".to_string(),
+            Value::String("application/json".to_string()),
+        );
+    }
+    if !headers.is_empty() {
+        options.push(format!(
+            "headers: {}",
+            language.value(&Value::Object(headers), 0)
+        ));
+    }
+    match (request.body_type.as_deref(), &request.body) {
+        (_, None) => {}
+        (Some("form"), Some(body)) => {
+            options.push(format!(
+                "body: new URLSearchParams({})",
+                language.value(body, 0)
+            ));
+        }
+        (Some("multipart"), Some(body)) => {
+            setup.push_str("const form = new FormData();\n");
+            for (name, value) in get_fields(body) {
+                match value.strip_prefix('@') {
+                    Some(path) => {
+                        imports = "import fs from \"node:fs\";\n\n".to_string();
+                        let file_name = std::path::Path::new(path)
+                            .file_name()
+                            .map(|file_name| file_name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        setup.push_str(&format!(
+                            "form.append({}, new Blob([fs.readFileSync({})]), {});\n",
+                            language.string(&name),
+                            language.string(path),
+                            language.string(&file_name)
+                        ));
+                    }
+                    None => setup.push_str(&format!(
+                        "form.append({}, {});\n",
+                        language.string(&name),
+                        language.string(&value)
+                    )),
+                }
+            }
+            setup.push('\n');
+            options.push("body: form".to_string());
+        }
+        (Some("text"), Some(body)) => {
+            options.push(format!("body: {}", language.string(&get_text_body(body))));
+        }
+        (_, Some(body)) => {
+            options.push(format!("body: JSON.stringify({})", language.value(body, 0)));
+        }
+    }
+    format!(
+        "{}{}const response = await fetch({}, {{\n{}}});\nconsole.log(response.status, await response.text());",
+        imports,
+        setup,
+        language.string(&get_url(request)),
+        options
+            .iter()
+            .map(|option| format!("  {},\n", option.replace('\n', "\n  ")))
+            .collect::<String>()
+    )
+}
+
+fn render_rust_reqwest(request: &HttpRequest) -> String {
+    let language = Language::Rust;
+    let method = request.method.to_lowercase();
+    let mut calls = vec![if ["get", "post", "put", "patch", "delete", "head"]
+        .contains(&method.as_str())
+    {
+        format!(".{}({})", method, language.string(&get_url(request)))
+    } else {
+        format!(
+            ".request(reqwest::Method::from_bytes(b{})?, {})",
+            language.string(&request.method),
+            language.string(&get_url(request))
+        )
+    }];
+    let mut comment = "";
+    match get_credentials(request) {
+        Some(Credentials::Basic(username, password)) => calls.push(format!(
+            ".basic_auth({}, Some({}))",
+            language.string(username),
+            language.string(password)
+        )),
+        Some(Credentials::Digest(..)) => {
+            comment = "// reqwest can't answer digest challenges, use a digest auth crate.\n";
+        }
+        None => {}
+    }
+    for header in &request.headers {
+        let (name, value) = split_header(header);
+        calls.push(format!(
+            ".header({}, {})",
+            language.string(&name),
+            language.string(&value)
+        ));
+    }
+    match (request.body_type.as_deref(), &request.body) {
+        (_, None) => {}
+        (Some("form"), Some(body)) => {
+            let fields: Vec<String> = get_fields(body)
+                .iter()
+                .map(|(name, value)| {
+                    format!("({}, {})", language.string(name), language.string(value))
+                })
+                .collect();
+            calls.push(format!(".form(&[{}])", fields.join(", ")));
+        }
+        (Some("multipart"), Some(body)) => {
+            let mut form = vec!["reqwest::multipart::Form::new()".to_string()];
+            for (name, value) in get_fields(body) {
+                match value.strip_prefix('@') {
+                    Some(path) => {
+                        let file_name = std::path::Path::new(path)
+                            .file_name()
+                            .map(|file_name| file_name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        form.push(format!(
+                            "    .part({}, reqwest::multipart::Part::bytes(std::fs::read({})?).file_name({}))",
+                            language.string(&name),
+                            language.string(path),
+                            language.string(&file_name)
+                        ));
+                    }
+                    None => form.push(format!(
+                        "    .text({}, {})",
+                        language.string(&name),
+                        language.string(&value)
+                    )),
+                }
+            }
+            calls.push(format!(".multipart(\n    {},\n)", form.join("\n    ")));
+        }
+        (Some("text"), Some(body)) => {
+            calls.push(format!(".body({})", language.string(&get_text_body(body))));
+        }
+        (_, Some(body)) => {
+            calls.push(format!(
+                ".json(&serde_json::json!({}))",
+                language.value(body, 0)
+            ));
+        }
+    }
+    calls.push(".send()".to_string());
+    calls.push(".await?;".to_string());
+    let calls: String = calls
+        .iter()
+        .map(|call| format!("        {}\n", call.replace('\n', "\n        ")))
+        .collect();
+    format!(
+        "{}#[tokio::main]\nasync fn main() -> Result<(), Box<dyn std::error::Error>> {{\n    let response = reqwest::Client::new()\n{}    println!(\"{{}} {{}}\", response.status(), response.text().await?);\n    Ok(())\n}}",
+        comment, calls
+    )
+}
+
+/// Signatures depend on the time of sending, so apart from curl with AWS SigV4, the snippets
+/// only say that the request has to be signed.
+fn get_signing_note(request: &HttpRequest, format: &SnippetFormat) -> Option<&'static str> {
+    match (&request.auth, format) {
+        (Some(Auth::AwsSigv4 { .. }), SnippetFormat::Curl) => None,
+        (Some(Auth::AwsSigv4 { .. }), _) => {
+            Some("Sign the request with AWS Signature Version 4 before sending it.")
+        }
+        (Some(Auth::Hmac { .. }), _) => {
+            Some("Add the HMAC-SHA256 signature header before sending the request.")
+        }
+        _ => None,
+    }
+}
+
+pub fn render(request: &HttpRequest, format: &SnippetFormat) -> String {
+    let request = &auth::move_to_headers(request);
+    let snippet = match format {
+        SnippetFormat::Curl => render_curl(request),
+        SnippetFormat::Httpie => render_httpie(request),
+        SnippetFormat::PythonRequests => render_python_requests(request),
+        SnippetFormat::JsFetch => render_js_fetch(request),
+        SnippetFormat::RustReqwest => render_rust_reqwest(request),
+    };
+    let comment = match format {
+        SnippetFormat::JsFetch | SnippetFormat::RustReqwest => "//",
+        _ => "#",
+    };
+    match get_signing_note(request, format) {
+        Some(note) => format!("{} {}\n{}", comment, note, snippet),
+        None => snippet,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request() -> HttpRequest {
+        HttpRequest {
+            method: "POST".to_string(),
+            url: "{{baseUrl}}/notes?tag=a b".to_string(),
+            headers: vec!["X-Note: it's \"quoted\"".to_string()],
+            body: Some(json!({ "text": "say \"hi\"", "quote": "it's" })),
+            ..Default::default()
+        }
+    }
+
+    fn text_request() -> HttpRequest {
+        HttpRequest {
+            body_type: Some("text".to_string()),
+            body: Some(Value::String("it's \"raw\"\n".to_string())),
+            ..request()
+        }
+    }
+
+    fn render_as(request: &HttpRequest, format: &str) -> String {
+        render(request, &SnippetFormat::from_arg(Some(format)).unwrap())
+    }
+
+    #[test]
+    fn renders_curl() {
+        assert_eq!(
+            render_as(&request(), "curl"),
+            r#"curl -X POST '{{baseUrl}}/notes?tag=a b' \
+  -H 'X-Note: it'\''s "quoted"' \
+  -H 'Content-Type: application/json' \
+  --data-raw '{"quote":"it'\''s","text":"say \"hi\""}'"#
+        );
+        assert_eq!(
+            render_as(&text_request(), "curl"),
+            "curl -X POST '{{baseUrl}}/notes?tag=a b' \\\n  -H 'X-Note: it'\\''s \"quoted\"' \\\n  --data-raw 'it'\\''s \"raw\"\n'"
+        );
+        let request = HttpRequest {
+            method: "PURGE; rm".to_string(),
+            url: "https://example.com".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_as(&request, "curl"),
+            "curl -X 'PURGE; rm' https://example.com"
+        );
+        assert_eq!(
+            render_as(&request, "httpie"),
+            "http 'PURGE; rm' https://example.com"
+        );
+    }
+
+    #[test]
+    fn renders_httpie() {
+        assert_eq!(
+            render_as(&request(), "httpie"),
+            r#"http --raw '{"quote":"it'\''s","text":"say \"hi\""}' POST '{{baseUrl}}/notes?tag=a b' 'X-Note:it'\''s "quoted"' Content-Type:application/json"#
+        );
+    }
+
+    #[test]
+    fn renders_python_requests() {
+        assert_eq!(
+            render_as(&request(), "python-requests"),
+            r#"import requests
+
+response = requests.request(
+    "POST",
+    "{{baseUrl}}/notes?tag=a b",
+    headers={
+        "X-Note": "it's \"quoted\"",
+    },
+    json={
+        "quote": "it's",
+        "text": "say \"hi\"",
+    },
+)
+print(response.status_code)
+print(response.text)"#
+        );
+        assert!(
+            render_as(&text_request(), "python-requests").contains(r#"    data="it's \"raw\"\n","#)
+        );
+    }
+
+    #[test]
+    fn renders_js_fetch() {
+        assert_eq!(
+            render_as(&request(), "js-fetch"),
+            r#"const response = await fetch("{{baseUrl}}/notes?tag=a b", {
+  method: "POST",
+  headers: {
+    "Content-Type": "application/json",
+    "X-Note": "it's \"quoted\"",
+  },
+  body: JSON.stringify({
+    "quote": "it's",
+    "text": "say \"hi\"",
+  }),
+});
+console.log(response.status, await response.text());"#
+        );
+        assert!(render_as(&text_request(), "js-fetch").contains(r#"  body: "it's \"raw\"\n","#));
+    }
+
+    #[test]
+    fn renders_rust_reqwest() {
+        assert_eq!(
+            render_as(&request(), "rust-reqwest"),
+            r#"#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let response = reqwest::Client::new()
+        .post("{{baseUrl}}/notes?tag=a b")
+        .header("X-Note", "it's \"quoted\"")
+        .json(&serde_json::json!({
+            "quote": "it's",
+            "text": "say \"hi\"",
+        }))
+        .send()
+        .await?;
+    println!("{} {}", response.status(), response.text().await?);
+    Ok(())
+}"#
+        );
+        assert!(render_as(&text_request(), "rust-reqwest")
+            .contains(r#"        .body("it's \"raw\"\n")"#));
+    }
+}
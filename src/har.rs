@@ -0,0 +1,355 @@
+//! Imports requests from HAR 1.2 files and exports the history as HAR.
+
+use crate::auth;
+use crate::import::Imported;
+use crate::structs::{Auth, HistoryEntry, HttpRequest};
+use crate::utils;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::fs;
+
+/// Headers set by the browser or the connection rather than by the request.
+const SKIPPED_HEADERS: [&str; 5] = [
+    "host",
+    "content-length",
+    "connection",
+    "accept-encoding",
+    "transfer-encoding",
+];
+
+/// Limits which entries of a HAR file are imported.
+#[derive(Default)]
+pub struct HarFilter {
+    pub hosts: Vec<String>,
+    pub methods: Vec<String>,
+    pub url_pattern: Option<Regex>,
+}
+
+impl HarFilter {
+    fn matches(&self, method: &str, url: &str) -> bool {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()))
+            .unwrap_or_default();
+        (self.hosts.is_empty() || self.hosts.contains(&host))
+            && (self.methods.is_empty()
+                || self
+                    .methods
+                    .iter()
+                    .any(|filter| filter.eq_ignore_ascii_case(method)))
+            && self
+                .url_pattern
+                .as_ref()
+                .map(|pattern| pattern.is_match(url))
+                .unwrap_or(true)
+    }
+}
+
+fn get_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+fn get_name_values(value: &Value, key: &str) -> Vec<(String, String)> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            Some((
+                get_str(item, "name")?.to_string(),
+                get_str(item, "value").unwrap_or_default().to_string(),
+            ))
+        })
+        .collect()
+}
+
+fn get_content_type(headers: &[String]) -> Option<String> {
+    headers.iter().find_map(|header| {
+        let (name, value) = header.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("content-type")
+            .then(|| value.trim().to_string())
+    })
+}
+
+fn remove_content_type(headers: &mut Vec<String>) {
+    headers.retain(|header| {
+        !header
+            .split_once(':')
+            .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+    });
+}
+
+fn parse_form(text: &str) -> Value {
+    Value::Object(
+        reqwest::Url::parse(&format!("http://form/?{}", text))
+            .map(|url| {
+                url.query_pairs()
+                    .map(|(name, value)| (name.to_string(), Value::String(value.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    )
+}
+
+/// Maps the posted data to a body type from its mime type. Uploaded files are not part of
+/// HAR files, so they are reported instead.
+fn convert_post_data(
+    post_data: &Value,
+    headers: &mut Vec<String>,
+    context: &str,
+    imported: &mut Imported,
+) -> (Option<String>, Option<Value>) {
+    let mime_type = get_str(post_data, "mimeType")
+        .map(|mime_type| mime_type.to_string())
+        .or_else(|| get_content_type(headers))
+        .unwrap_or_default()
+        .to_lowercase();
+    let text = get_str(post_data, "text").unwrap_or_default();
+    let params = post_data.get("params").and_then(Value::as_array);
+    if mime_type.contains("json") {
+        if let Ok(json) = serde_json::from_str::<Value>(text) {
+            return (Some("json".to_string()), Some(json));
+        }
+    }
+    if mime_type.starts_with("application/x-www-form-urlencoded") {
+        remove_content_type(headers);
+        let form = match params {
+            Some(_) => Value::Object(
+                get_name_values(post_data, "params")
+                    .into_iter()
+                    .map(|(name, value)| (name, Value::String(value)))
+                    .collect(),
+            ),
+            None => parse_form(text),
+        };
+        return (Some("form".to_string()), Some(form));
+    }
+    if mime_type.starts_with("multipart/form-data") {
+        if let Some(params) = params {
+            remove_content_type(headers);
+            let mut form = Map::new();
+            for param in params {
+                let Some(name) = get_str(param, "name") else {
+                    continue;
+                };
+                let value = match get_str(param, "fileName") {
+                    Some(file_name) => {
+                        imported.warn(
+                            context,
+                            &format!("the file {} of the form field {}", file_name, name),
+                        );
+                        format!("@{}", file_name)
+                    }
+                    None => get_str(param, "value").unwrap_or_default().to_string(),
+                };
+                form.insert(name.to_string(), Value::String(value));
+            }
+            return (Some("multipart".to_string()), Some(Value::Object(form)));
+        }
+    }
+    if text.is_empty() {
+        return (None, None);
+    }
+    (
+        Some("text".to_string()),
+        Some(Value::String(text.to_string())),
+    )
+}
+
+/// Converts the entries that match the filter. Requests with the same method, url and body
+/// are only imported once.
+pub fn convert(har: &Value, filter: &HarFilter) -> Result<Imported, String> {
+    let entries = har
+        .get("log")
+        .and_then(|log| log.get("entries"))
+        .and_then(Value::as_array)
+        .ok_or("Not a HAR file, it has no log.entries")?;
+    let mut imported = Imported::default();
+    for entry in entries {
+        let request = &entry["request"];
+        let method = get_str(request, "method").unwrap_or("GET").to_uppercase();
+        let url = get_str(request, "url").unwrap_or_default().to_string();
+        if !url.starts_with("http") || !filter.matches(&method, &url) {
+            continue;
+        }
+        let context = format!("{} {}", method, url);
+        let mut headers: Vec<String> = get_name_values(request, "headers")
+            .into_iter()
+            .filter(|(name, _)| {
+                let name = name.to_lowercase();
+                // HTTP/2 pseudo headers like `:authority`.
+                !name.starts_with(':') && !SKIPPED_HEADERS.contains(&name.as_str())
+            })
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect();
+        let (body_type, body) = match request.get("postData") {
+            Some(post_data) => convert_post_data(post_data, &mut headers, &context, &mut imported),
+            None => (None, None),
+        };
+        let is_duplicate = imported.requests.iter().any(|existing| {
+            existing.method == method
+                && existing.url == url
+                && existing.body_type == body_type
+                && existing.body == body
+        });
+        if is_duplicate {
+            continue;
+        }
+        imported.requests.push(HttpRequest {
+            method,
+            url,
+            headers,
+            body_type,
+            body,
+            ..Default::default()
+        });
+    }
+    Ok(imported)
+}
+
+pub fn read(path: &str, filter: &HarFilter) -> Result<Imported, String> {
+    let text =
+        fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+    let har: Value =
+        serde_json::from_str(&text).map_err(|err| format!("Failed to parse {}: {}", path, err))?;
+    convert(&har, filter)
+}
+
+fn to_name_values(headers: &[String]) -> Vec<Value> {
+    headers
+        .iter()
+        .map(|header| {
+            let (name, value) = header.split_once(':').unwrap_or((header, ""));
+            json!({ "name": name.trim(), "value": value.trim() })
+        })
+        .collect()
+}
+
+fn get_post_data(request: &HttpRequest) -> Option<Value> {
+    let body = request.body.as_ref()?;
+    let content_type = get_content_type(&request.headers);
+    let fields = || -> Vec<(String, String)> {
+        body.as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, value)| (name.clone(), utils::json_value_to_variable(value)))
+            .collect()
+    };
+    Some(match request.body_type.as_deref() {
+        Some("form") => {
+            let fields = fields();
+            let text = reqwest::Url::parse_with_params("http://form/", &fields)
+                .ok()
+                .and_then(|url| url.query().map(|query| query.to_string()))
+                .unwrap_or_default();
+            let params: Vec<Value> = fields
+                .iter()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect();
+            json!({
+                "mimeType": "application/x-www-form-urlencoded",
+                "text": text,
+                "params": params,
+            })
+        }
+        Some("multipart") => {
+            let params: Vec<Value> = fields()
+                .iter()
+                .map(|(name, value)| match value.strip_prefix('@') {
+                    Some(path) => json!({ "name": name, "fileName": path }),
+                    None => json!({ "name": name, "value": value }),
+                })
+                .collect();
+            json!({ "mimeType": "multipart/form-data", "params": params })
+        }
+        Some("text") => json!({
+            "mimeType": content_type.unwrap_or_else(|| "text/plain".to_string()),
+            "text": utils::json_value_to_variable(body),
+        }),
+        _ => json!({
+            "mimeType": content_type.unwrap_or_else(|| "application/json".to_string()),
+            "text": body.to_string(),
+        }),
+    })
+}
+
+/// Converts a history entry, whose secrets are already masked, to a HAR entry.
+fn to_har_entry(entry: &HistoryEntry) -> Value {
+    let mut request = auth::move_to_headers(&entry.request);
+    match request.auth.take() {
+        Some(Auth::Basic { .. }) => request
+            .headers
+            .push("Authorization: Basic ****".to_string()),
+        Some(Auth::Digest { .. }) => request
+            .headers
+            .push("Authorization: Digest ****".to_string()),
+        _ => {}
+    }
+    let request = &request;
+    let url = utils::get_url_with_https(&request.url);
+    let query: Vec<Value> = reqwest::Url::parse(&url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut har_request = json!({
+        "method": request.method,
+        "url": url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": to_name_values(&request.headers),
+        "queryString": query,
+        "headersSize": -1,
+        "bodySize": -1,
+    });
+    if let Some(post_data) = get_post_data(request) {
+        har_request["postData"] = post_data;
+    }
+    let mut content = json!({
+        "size": entry.response_size,
+        "mimeType": get_content_type(&entry.response_headers).unwrap_or_default(),
+    });
+    if let Some(body) = &entry.response_body {
+        content["text"] = json!(utils::json_value_to_variable(body));
+    }
+    let mut har_entry = json!({
+        "startedDateTime": entry.timestamp,
+        "time": entry.duration_ms,
+        "request": har_request,
+        "response": {
+            "status": entry.status_code.unwrap_or(0),
+            "statusText": entry
+                .status_code
+                .and_then(|status| reqwest::StatusCode::from_u16(status).ok())
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or_default(),
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": to_name_values(&entry.response_headers),
+            "content": content,
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": entry.response_size,
+        },
+        "cache": {},
+        "timings": { "send": 0, "wait": entry.duration_ms, "receive": 0 },
+    });
+    if let Some(error) = &entry.error {
+        har_entry["comment"] = json!(error);
+    }
+    har_entry
+}
+
+pub fn to_har(entries: &[HistoryEntry]) -> Value {
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "xhtp", "version": env!("CARGO_PKG_VERSION") },
+            "entries": entries.iter().map(to_har_entry).collect::<Vec<Value>>(),
+        }
+    })
+}
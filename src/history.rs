@@ -0,0 +1,360 @@
+use crate::client::{ReceivedResponse, SendError};
+use crate::content::Body;
+use crate::structs::{HistoryEntry, HttpRequest};
+use crate::utils;
+use serde_json::Value;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Oldest entries are removed once the history grows past this size.
+const MAX_ENTRIES: usize = 500;
+
+/// Response bodies larger than this are not kept in the history.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+const MASK: &str = "****";
+
+const SENSITIVE_NAMES: [&str; 12] = [
+    "authorization",
+    "cookie",
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "api-key",
+    "api_key",
+    "apikey",
+    "signature",
+    "session",
+    "credential",
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static SAVE_BODIES: AtomicBool = AtomicBool::new(true);
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn configure(enabled: bool, save_bodies: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    SAVE_BODIES.store(save_bodies, Ordering::SeqCst);
+}
+
+pub fn is_sensitive_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name == "key" || SENSITIVE_NAMES.iter().any(|word| name.contains(word))
+}
+
+/// Masks a secret but keeps `{{variable}}` placeholders and the auth scheme, e.g. `Bearer ****`.
+pub fn mask_value(value: &str) -> String {
+    if value.contains("{{") {
+        return value.to_string();
+    }
+    match value.trim().split_once(' ') {
+        Some((scheme, _)) if scheme.chars().all(|c| c.is_ascii_alphabetic()) => {
+            format!("{} {}", scheme, MASK)
+        }
+        _ => MASK.to_string(),
+    }
+}
+
+/// Maps the value of a header with a sensitive name, `map` gets its name and value.
+fn map_header(header: &str, map: &mut impl FnMut(&str, &str) -> String) -> String {
+    match header.split_once(':') {
+        Some((name, value)) if is_sensitive_name(name) => {
+            format!("{}: {}", name, map(name, value.trim()))
+        }
+        _ => header.to_string(),
+    }
+}
+
+fn mask_header(header: &str) -> String {
+    map_header(header, &mut |_, value| mask_value(value))
+}
+
+/// Maps the values of the query parameters with sensitive names.
+fn map_url(url: &str, map: &mut impl FnMut(&str, &str) -> String) -> String {
+    let Some((base, rest)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let (query, fragment) = match rest.split_once('#') {
+        Some((query, fragment)) => (query, Some(fragment)),
+        None => (rest, None),
+    };
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, value)) if is_sensitive_name(name) => {
+                format!("{}={}", name, map(name, value))
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("&");
+    match fragment {
+        Some(fragment) => format!("{}?{}#{}", base, query, fragment),
+        None => format!("{}?{}", base, query),
+    }
+}
+
+/// Maps the string, number and boolean values of object keys with sensitive names, at any depth.
+fn map_json(value: &Value, map: &mut impl FnMut(&str, &str) -> String) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, item)| match item {
+                    Value::String(text) if is_sensitive_name(key) => {
+                        (key.clone(), Value::String(map(key, text)))
+                    }
+                    Value::Number(_) | Value::Bool(_) if is_sensitive_name(key) => {
+                        (key.clone(), Value::String(map(key, &item.to_string())))
+                    }
+                    _ => (key.clone(), map_json(item, map)),
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|item| map_json(item, map)).collect()),
+        other => other.clone(),
+    }
+}
+
+fn mask_json(value: &Value) -> Value {
+    map_json(value, &mut |_, value| mask_value(value))
+}
+
+/// Returns a copy of the request with credentials in headers, query parameters,
+/// body fields and its auth replaced by `****`. Response bodies are masked the same way.
+pub fn mask_request(request: &HttpRequest) -> HttpRequest {
+    let mut masked = request.clone();
+    masked.url = map_url(&request.url, &mut |_, value| mask_value(value));
+    masked.headers = request
+        .headers
+        .iter()
+        .map(|header| mask_header(header))
+        .collect();
+    masked.body = request.body.as_ref().map(mask_json);
+    masked.auth = request
+        .auth
+        .as_ref()
+        .map(|auth| crate::auth::mask(auth, mask_value));
+    masked
+}
+
+pub fn mask_response_headers(received: &ReceivedResponse) -> Vec<String> {
+    received
+        .headers
+        .iter()
+        .map(|(name, value)| mask_header(&format!("{}: {}", name, value.to_str().unwrap_or(""))))
+        .collect()
+}
+
+/// The response body as stored in the history, binary bodies are not kept.
+pub fn mask_response_body(received: &ReceivedResponse) -> Option<Value> {
+    match &received.body {
+        Body::Json(json) => Some(mask_json(json)),
+        Body::Text(text) => Some(Value::String(text.clone())),
+        Body::Binary(_) => None,
+    }
+}
+
+fn get_entry_path(id: u64) -> String {
+    format!("{}/{}.json", utils::get_history_dir_path(), id)
+}
+
+fn get_entry_ids() -> Vec<u64> {
+    let mut ids: Vec<u64> = fs::read_dir(utils::get_history_dir_path())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().into_string().ok()?;
+                    name.strip_suffix(".json")?.parse::<u64>().ok()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    ids.sort();
+    ids
+}
+
+fn write_entry(entry: &mut HistoryEntry) -> Result<(), std::io::Error> {
+    let _lock = HISTORY_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    fs::create_dir_all(utils::get_history_dir_path())?;
+    let ids = get_entry_ids();
+    entry.id = ids.last().map(|id| id + 1).unwrap_or(1);
+    fs::write(
+        get_entry_path(entry.id),
+        serde_json::to_string_pretty(entry).unwrap(),
+    )?;
+    let excess = (ids.len() + 1).saturating_sub(MAX_ENTRIES);
+    for id in ids.iter().take(excess) {
+        fs::remove_file(get_entry_path(*id))?;
+    }
+    Ok(())
+}
+
+/// Records a sent request and its response, and returns the entry as recorded. Failing to
+/// write the history never fails the request, and with `--no-history` the entry isn't written.
+pub fn record(
+    saved_request: Option<&HttpRequest>,
+    resolved: &HttpRequest,
+    result: Result<&ReceivedResponse, &SendError>,
+) -> HistoryEntry {
+    let mut entry = HistoryEntry {
+        id: 0,
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        saved_request: saved_request.map(mask_request),
+        request: mask_request(resolved),
+        status_code: None,
+        response_headers: Vec::new(),
+        duration_ms: 0,
+        response_size: 0,
+        response_body: None,
+        error: None,
+    };
+    match result {
+        Ok(received) => {
+            entry.status_code = Some(received.status_code);
+            entry.response_headers = mask_response_headers(received);
+            entry.duration_ms = received.duration.as_millis();
+            entry.response_size = received.size;
+            if SAVE_BODIES.load(Ordering::SeqCst) && received.size <= MAX_BODY_BYTES {
+                entry.response_body = mask_response_body(received);
+            }
+        }
+        Err(err) => entry.error = Some(err.to_string()),
+    }
+    if ENABLED.load(Ordering::SeqCst) {
+        if let Err(err) = write_entry(&mut entry) {
+            eprintln!("Failed to write the request history: {}", err);
+        }
+    }
+    entry
+}
+
+pub fn read_entries() -> Vec<HistoryEntry> {
+    get_entry_ids().into_iter().filter_map(read_entry).collect()
+}
+
+pub fn read_entry(id: u64) -> Option<HistoryEntry> {
+    let content = fs::read_to_string(get_entry_path(id)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Entries whose method, url, request name, status code or response body contain the query.
+pub fn search(query: &str) -> Vec<HistoryEntry> {
+    let query = query.to_lowercase();
+    read_entries()
+        .into_iter()
+        .filter(|entry| {
+            let name = entry
+                .saved_request
+                .as_ref()
+                .and_then(|request| request.name.clone())
+                .unwrap_or_default();
+            let status = entry
+                .status_code
+                .map(|status| status.to_string())
+                .unwrap_or_default();
+            let body = entry
+                .response_body
+                .as_ref()
+                .map(utils::json_value_to_variable)
+                .unwrap_or_default();
+            [
+                &entry.request.method,
+                &entry.request.url,
+                &name,
+                &status,
+                &body,
+            ]
+            .iter()
+            .any(|field| field.to_lowercase().contains(&query))
+        })
+        .collect()
+}
+
+pub fn print_entries(entries: &[HistoryEntry]) {
+    for entry in entries {
+        let status = entry
+            .status_code
+            .map(|status| status.to_string())
+            .unwrap_or_else(|| "error".to_string());
+        println!(
+            "{}: {} {} {} {} ({} ms)",
+            entry.id,
+            entry.timestamp,
+            utils::get_ansi_colored_request_method(&entry.request.method),
+            entry.request.url,
+            status,
+            entry.duration_ms
+        );
+    }
+}
+
+pub fn print_entry(entry: &HistoryEntry) {
+    println!("{}", serde_json::to_string_pretty(entry).unwrap());
+}
+
+/// The most recent entry recorded for a saved request, matched by name or by its contents.
+pub fn find_latest(request: &HttpRequest) -> Option<HistoryEntry> {
+    let masked = mask_request(request);
+    read_entries().into_iter().rev().find(|entry| {
+        entry
+            .saved_request
+            .as_ref()
+            .is_some_and(|saved| match &request.name {
+                Some(name) => saved.name.as_ref() == Some(name),
+                None => *saved == masked,
+            })
+    })
+}
+
+/// The saved request of an entry, or else the request as sent. Values that were masked become
+/// `{{variable}}` placeholders named after their header, parameter or field, and the names of
+/// those variables are returned so that they can be set before the request is sent.
+pub fn get_replay_request(entry: &HistoryEntry) -> (HttpRequest, Vec<String>) {
+    let request = entry.saved_request.as_ref().unwrap_or(&entry.request);
+    let mut variables: Vec<String> = Vec::new();
+    let mut unmask = |name: &str, value: &str| {
+        let (scheme, masked) = match value.rsplit_once(' ') {
+            Some((scheme, masked)) => (Some(scheme), masked),
+            None => (None, value),
+        };
+        if masked != MASK {
+            return value.to_string();
+        }
+        let variable: String = name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let placeholder = format!("{{{{{}}}}}", variable);
+        if !variables.contains(&variable) {
+            variables.push(variable);
+        }
+        match scheme {
+            Some(scheme) => format!("{} {}", scheme, placeholder),
+            None => placeholder,
+        }
+    };
+
+    let mut replay = request.clone();
+    replay.url = map_url(&request.url, &mut unmask);
+    replay.headers = request
+        .headers
+        .iter()
+        .map(|header| map_header(header, &mut unmask))
+        .collect();
+    replay.body = request
+        .body
+        .as_ref()
+        .map(|body| map_json(body, &mut unmask));
+    replay.auth = request.auth.as_ref().map(|auth| {
+        crate::auth::map_values(auth, |value, secret| match secret {
+            Some(name) => unmask(name, value),
+            None => value.to_string(),
+        })
+    });
+    (replay, variables)
+}
@@ -0,0 +1,603 @@
+//! Reads and writes `.http`/`.rest` files, as used by the VS Code REST Client and the
+//! JetBrains HTTP client.
+
+use crate::auth;
+use crate::import::Imported;
+use crate::structs::{Auth, ExtractVariable, GlobalVariable, HttpRequest};
+use crate::utils;
+use regex::Regex;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+const METHODS: [&str; 9] = [
+    "GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "TRACE", "CONNECT",
+];
+
+const BOUNDARY: &str = "xhtp-boundary";
+
+pub fn is_http_file(path: &str) -> bool {
+    path.ends_with(".http") || path.ends_with(".rest")
+}
+
+/// Returns the value of a `# @name login` or `// @name login` comment.
+fn get_annotation<'a>(line: &'a str, annotation: &str) -> Option<&'a str> {
+    let comment = line
+        .strip_prefix('#')
+        .or_else(|| line.strip_prefix("//"))?
+        .trim_start();
+    let value = comment.strip_prefix('@')?.strip_prefix(annotation)?;
+    (value.is_empty() || value.starts_with(char::is_whitespace)).then(|| value.trim())
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with("//")
+}
+
+/// Parses `GET https://example.com HTTP/1.1`, or just a url for a GET request.
+fn parse_request_line(line: &str) -> (String, String) {
+    let mut parts = line.split_whitespace();
+    let first = parts.next().unwrap_or_default();
+    let (method, url) = if METHODS.contains(&first.to_uppercase().as_str()) {
+        (first.to_uppercase(), parts.next().unwrap_or_default())
+    } else {
+        ("GET".to_string(), first)
+    };
+    (method, url.to_string())
+}
+
+/// Replaces `< ./file` lines with the contents of the file, relative to the `.http` file.
+fn include_files(body: &str, base_dir: &Path, context: &str, imported: &mut Imported) -> String {
+    body.lines()
+        .map(|line| {
+            let Some(path) = line
+                .strip_prefix("<@")
+                .or_else(|| line.strip_prefix('<'))
+                .filter(|path| path.starts_with(char::is_whitespace))
+            else {
+                return line.to_string();
+            };
+            let path = base_dir.join(path.trim());
+            match fs::read_to_string(&path) {
+                Ok(content) => content.trim_end_matches('\n').to_string(),
+                Err(err) => {
+                    imported.warn(
+                        context,
+                        &format!("failed to include {}: {}", path.display(), err),
+                    );
+                    line.to_string()
+                }
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn get_content_type(headers: &[String]) -> String {
+    headers
+        .iter()
+        .find_map(|header| {
+            let (name, value) = header.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-type")
+                .then(|| value.trim().to_lowercase())
+        })
+        .unwrap_or_default()
+}
+
+/// JSON bodies become `json` bodies, anything else is sent as written.
+fn convert_body(body: String, headers: &[String]) -> (Option<String>, Option<Value>) {
+    if body.trim().is_empty() {
+        return (None, None);
+    }
+    if get_content_type(headers).contains("json") {
+        if let Ok(json) = serde_json::from_str::<Value>(&body) {
+            return (Some("json".to_string()), Some(json));
+        }
+    }
+    (Some("text".to_string()), Some(Value::String(body)))
+}
+
+/// Takes the `Authorization: Basic user password` (or `user:password`) and
+/// `Authorization: Digest user password` shorthands out of the headers.
+fn take_auth(headers: &mut Vec<String>) -> Option<Auth> {
+    let (index, auth) = headers.iter().enumerate().find_map(|(index, header)| {
+        let (name, value) = header.split_once(':')?;
+        if !name.trim().eq_ignore_ascii_case("authorization") {
+            return None;
+        }
+        let words: Vec<&str> = value.split_whitespace().collect();
+        let (username, password) = match words[..] {
+            [_, username, password] => (username, password),
+            // Base64 credentials never contain a colon.
+            [_, credentials] => credentials.split_once(':')?,
+            _ => return None,
+        };
+        let (username, password) = (username.to_string(), password.to_string());
+        let auth = match words[0].to_lowercase().as_str() {
+            "basic" => Auth::Basic { username, password },
+            "digest" => Auth::Digest { username, password },
+            _ => return None,
+        };
+        Some((index, auth))
+    })?;
+    headers.remove(index);
+    Some(auth)
+}
+
+fn parse_block(
+    lines: &[&str],
+    name: Option<String>,
+    base_dir: &Path,
+    imported: &mut Imported,
+) -> Option<HttpRequest> {
+    let mut name = name;
+    let mut lines = lines.iter().map(|line| line.trim_end()).peekable();
+    let mut request_line = None;
+    for line in lines.by_ref() {
+        let trimmed = line.trim();
+        if let Some(value) = get_annotation(trimmed, "name") {
+            name = Some(value.to_string());
+        } else if let Some(definition) = trimmed.strip_prefix('@') {
+            if let Some((key, value)) = definition.split_once('=') {
+                // Definitions can use the ones before them.
+                let value = utils::replace_variables(value.trim(), &imported.variables);
+                imported.variables.push(GlobalVariable {
+                    key: key.trim().to_string(),
+                    value,
+                });
+            }
+        } else if !trimmed.is_empty() && !is_comment(trimmed) {
+            request_line = Some(trimmed.to_string());
+            break;
+        }
+    }
+    let (method, mut url) = parse_request_line(&request_line?);
+    let context = name
+        .clone()
+        .unwrap_or_else(|| format!("{} {}", method, url));
+
+    // Query parameters can continue on the following lines.
+    while let Some(line) = lines.peek() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('?') || trimmed.starts_with('&') {
+            url.push_str(trimmed);
+            lines.next();
+        } else {
+            break;
+        }
+    }
+
+    let mut headers = Vec::new();
+    for line in lines.by_ref() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            break;
+        }
+        if is_comment(trimmed) {
+            continue;
+        }
+        headers.push(trimmed.to_string());
+    }
+
+    let mut body_lines = Vec::new();
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.starts_with("> ") || trimmed.starts_with(">>") || trimmed.starts_with("<> ") {
+            imported.warn(&context, "response handlers and redirections");
+            continue;
+        }
+        body_lines.push(line);
+    }
+    while body_lines.last().is_some_and(|line| line.trim().is_empty()) {
+        body_lines.pop();
+    }
+    let body = include_files(&body_lines.join("\n"), base_dir, &context, imported);
+    let (body_type, body) = convert_body(body, &headers);
+    let auth = take_auth(&mut headers);
+
+    Some(HttpRequest {
+        name,
+        method,
+        url,
+        headers,
+        body_type,
+        body,
+        auth,
+        ..Default::default()
+    })
+}
+
+/// Replaces every string inside the value, leaving object keys as they are.
+fn replace_strings(value: &mut Value, replace: &mut impl FnMut(&str) -> String) {
+    match value {
+        Value::String(text) => *text = replace(text),
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| replace_strings(item, replace)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| replace_strings(item, replace)),
+        _ => {}
+    }
+}
+
+/// `{{login.response.body.$.token}}` becomes `{{login.token}}`, extracted from the response
+/// of the `login` request. The references are replaced in the url, headers, body and auth.
+fn convert_request_variables(imported: &mut Imported) {
+    let reference =
+        Regex::new(r"\{\{\s*([\w-]+)\.response\.(body|headers)\.([^}]*?)\s*\}\}").unwrap();
+    let mut extractions: Vec<(String, String)> = Vec::new();
+    let mut unsupported_requests = Vec::new();
+    let mut failed_requests = Vec::new();
+    for request in imported.requests.iter_mut() {
+        let mut json = serde_json::to_value(&*request).unwrap();
+        let mut found = false;
+        let mut unsupported = false;
+        replace_strings(&mut json, &mut |text| {
+            let replaced = reference.replace_all(text, |captures: &regex::Captures| {
+                found = true;
+                let path = captures[3].trim_start_matches('$').trim_start_matches('.');
+                if &captures[2] == "headers" || path.contains('*') {
+                    unsupported = true;
+                    return captures[0].to_string();
+                }
+                extractions.push((captures[1].to_string(), path.to_string()));
+                format!("{{{{{}.{}}}}}", &captures[1], path)
+            });
+            replaced.into_owned()
+        });
+        if !found {
+            continue;
+        }
+        if unsupported {
+            unsupported_requests.push(utils::get_request_label(request));
+        }
+        match serde_json::from_value(json) {
+            Ok(converted) => *request = converted,
+            Err(_) => failed_requests.push(utils::get_request_label(request)),
+        }
+    }
+    for context in failed_requests {
+        imported.warn(
+            &context,
+            "references to other responses could not be converted",
+        );
+    }
+    for context in unsupported_requests {
+        imported.warn(&context, "references to response headers or wildcards");
+    }
+    for (name, path) in extractions {
+        let Some(request) = imported
+            .requests
+            .iter_mut()
+            .find(|request| request.name.as_deref() == Some(name.as_str()))
+        else {
+            continue;
+        };
+        let extract = ExtractVariable {
+            key_path: path.clone(),
+            variable_name: format!("{}.{}", name, path),
+        };
+        let extract_variables = request.extract_variables.get_or_insert_with(Vec::new);
+        if !extract_variables.contains(&extract) {
+            extract_variables.push(extract);
+        }
+    }
+}
+
+/// Parses the requests separated by `###`, the `@variable = value` definitions and `# @name`
+/// annotations. Text after `###` also names the request, like in the JetBrains client.
+pub fn parse(text: &str, base_dir: &Path) -> Imported {
+    let mut imported = Imported::default();
+    let mut blocks: Vec<(Option<String>, Vec<&str>)> = vec![(None, Vec::new())];
+    for line in text.lines() {
+        match line.trim().strip_prefix("###") {
+            Some(title) => {
+                let title = title.trim();
+                blocks.push(((!title.is_empty()).then(|| title.to_string()), Vec::new()));
+            }
+            None => blocks.last_mut().unwrap().1.push(line),
+        }
+    }
+    for (name, lines) in blocks {
+        if let Some(request) = parse_block(&lines, name, base_dir, &mut imported) {
+            imported.requests.push(request);
+        }
+    }
+    convert_request_variables(&mut imported);
+    if text.contains("{{$") {
+        imported.warn(
+            "file",
+            "dynamic variables such as {{$uuid}} are not resolved",
+        );
+    }
+    imported
+}
+
+pub fn read(path: &str) -> Result<Imported, String> {
+    let text =
+        fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+    let base_dir = Path::new(path).parent().unwrap_or(Path::new("."));
+    Ok(parse(&text, base_dir))
+}
+
+fn export_body(request: &HttpRequest, lines: &mut Vec<String>) {
+    let Some(body) = &request.body else {
+        return;
+    };
+    let has_content_type = !get_content_type(&request.headers).is_empty();
+    let fields: Vec<(String, String)> = body
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| (name.clone(), utils::json_value_to_variable(value)))
+        .collect();
+    match request.body_type.as_deref() {
+        Some("form") => {
+            if !has_content_type {
+                lines.push("Content-Type: application/x-www-form-urlencoded".to_string());
+            }
+            let form = reqwest::Url::parse_with_params("http://form/", &fields)
+                .ok()
+                .and_then(|url| url.query().map(|query| query.to_string()))
+                .unwrap_or_default();
+            lines.push(String::new());
+            lines.push(form);
+        }
+        Some("multipart") => {
+            lines.push(format!(
+                "Content-Type: multipart/form-data; boundary={}",
+                BOUNDARY
+            ));
+            lines.push(String::new());
+            for (name, value) in fields {
+                lines.push(format!("--{}", BOUNDARY));
+                match value.strip_prefix('@') {
+                    Some(path) => {
+                        let file_name = Path::new(path)
+                            .file_name()
+                            .map(|file_name| file_name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        lines.push(format!(
+                            "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"",
+                            name, file_name
+                        ));
+                        lines.push(String::new());
+                        lines.push(format!("< {}", path));
+                    }
+                    None => {
+                        lines.push(format!("Content-Disposition: form-data; name=\"{}\"", name));
+                        lines.push(String::new());
+                        lines.push(value);
+                    }
+                }
+            }
+            lines.push(format!("--{}--", BOUNDARY));
+        }
+        Some("text") => {
+            lines.push(String::new());
+            lines.push(utils::json_value_to_variable(body));
+        }
+        _ => {
+            if !has_content_type {
+                lines.push("Content-Type: application/json".to_string());
+            }
+            lines.push(String::new());
+            lines.push(serde_json::to_string_pretty(body).unwrap());
+        }
+    }
+}
+
+/// Writes the requests as a `.http` file, one `###` block per request.
+pub fn export(requests: &[HttpRequest]) -> String {
+    let blocks: Vec<String> = requests
+        .iter()
+        .map(|request| {
+            let mut lines = match &request.name {
+                Some(name) => vec![format!("### {}", name), format!("# @name {}", name)],
+                None => vec!["###".to_string()],
+            };
+            let request = auth::move_to_headers(request);
+            lines.push(format!("{} {}", request.method, request.url));
+            lines.extend(request.headers.iter().cloned());
+            match &request.auth {
+                Some(Auth::Basic { username, password }) => {
+                    lines.push(format!("Authorization: Basic {} {}", username, password))
+                }
+                Some(Auth::Digest { username, password }) => {
+                    lines.push(format!("Authorization: Digest {} {}", username, password))
+                }
+                _ => {}
+            }
+            export_body(&request, &mut lines);
+            lines.join("\n")
+        })
+        .collect();
+    format!("{}\n", blocks.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FILE: &str = r#"@host = https://api.test
+@baseUrl = {{host}}/v1
+
+### login
+POST {{baseUrl}}/login HTTP/1.1
+Content-Type: application/json
+Authorization: Basic ada secret
+
+{
+  "user": "ada"
+}
+
+> {% client.global.set("token", response.body.token); %}
+
+###
+# @name fetch
+GET {{baseUrl}}/users
+    ?page=2
+    &sort=name
+# a comment between the headers
+Authorization: Bearer {{login.response.body.$.token}}
+X-Request-Id: {{$uuid}}
+
+###
+// @name note
+PUT {{baseUrl}}/notes/{{fetch.response.body.$.items[0].id}}
+Content-Type: text/plain
+X-Etag: {{fetch.response.headers.ETag}}
+
+say "{{fetch.response.body.$.items[0].name}}"
+
+### Upload
+POST {{baseUrl}}/upload
+
+< ./missing.txt
+"#;
+
+    fn parse_file() -> Imported {
+        parse(FILE, Path::new("/nonexistent"))
+    }
+
+    #[test]
+    fn parses_requests_and_variables() {
+        let imported = parse_file();
+        let variables: Vec<(&str, &str)> = imported
+            .variables
+            .iter()
+            .map(|variable| (variable.key.as_str(), variable.value.as_str()))
+            .collect();
+        assert_eq!(
+            variables,
+            vec![
+                ("host", "https://api.test"),
+                ("baseUrl", "https://api.test/v1")
+            ]
+        );
+
+        let requests = &imported.requests;
+        assert_eq!(requests.len(), 4);
+        let login = &requests[0];
+        assert_eq!(login.name.as_deref(), Some("login"));
+        assert_eq!(login.method, "POST");
+        assert_eq!(login.url, "{{baseUrl}}/login");
+        assert_eq!(login.headers, vec!["Content-Type: application/json"]);
+        assert_eq!(
+            login.auth,
+            Some(Auth::Basic {
+                username: "ada".to_string(),
+                password: "secret".to_string()
+            })
+        );
+        assert_eq!(login.body_type.as_deref(), Some("json"));
+        assert_eq!(login.body, Some(json!({ "user": "ada" })));
+
+        let fetch = &requests[1];
+        assert_eq!(fetch.name.as_deref(), Some("fetch"));
+        assert_eq!(fetch.url, "{{baseUrl}}/users?page=2&sort=name");
+        assert_eq!(fetch.body, None);
+
+        assert_eq!(requests[3].name.as_deref(), Some("Upload"));
+        assert_eq!(requests[3].body, Some(json!("< ./missing.txt")));
+    }
+
+    #[test]
+    fn converts_references_to_other_responses() {
+        let imported = parse_file();
+        let requests = &imported.requests;
+        assert_eq!(
+            requests[1].headers,
+            vec![
+                "Authorization: Bearer {{login.token}}",
+                "X-Request-Id: {{$uuid}}"
+            ]
+        );
+        let note = &requests[2];
+        assert_eq!(note.url, "{{baseUrl}}/notes/{{fetch.items[0].id}}");
+        assert_eq!(
+            note.headers,
+            vec![
+                "Content-Type: text/plain",
+                "X-Etag: {{fetch.response.headers.ETag}}"
+            ]
+        );
+        assert_eq!(note.body, Some(json!("say \"{{fetch.items[0].name}}\"")));
+
+        let extracted = |request: &HttpRequest| -> Vec<(String, String)> {
+            request
+                .extract_variables
+                .iter()
+                .flatten()
+                .map(|extract| (extract.key_path.clone(), extract.variable_name.clone()))
+                .collect()
+        };
+        assert_eq!(
+            extracted(&requests[0]),
+            vec![("token".to_string(), "login.token".to_string())]
+        );
+        assert_eq!(
+            extracted(&requests[1]),
+            vec![
+                (
+                    "items[0].name".to_string(),
+                    "fetch.items[0].name".to_string()
+                ),
+                ("items[0].id".to_string(), "fetch.items[0].id".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_unsupported_constructs() {
+        let warnings = parse_file().warnings;
+        assert_eq!(warnings.len(), 4, "{:?}", warnings);
+        assert_eq!(warnings[0], "login: response handlers and redirections");
+        assert!(
+            warnings[1].starts_with("Upload: failed to include /nonexistent/./missing.txt"),
+            "{}",
+            warnings[1]
+        );
+        assert_eq!(
+            warnings[2],
+            "note: references to response headers or wildcards"
+        );
+        assert_eq!(
+            warnings[3],
+            "file: dynamic variables such as {{$uuid}} are not resolved"
+        );
+    }
+
+    #[test]
+    fn exports_requests_that_parse_back() {
+        let requests = vec![
+            HttpRequest {
+                name: Some("login".to_string()),
+                method: "POST".to_string(),
+                url: "{{baseUrl}}/login".to_string(),
+                headers: vec!["Content-Type: application/json".to_string()],
+                body_type: Some("json".to_string()),
+                body: Some(json!({ "user": "ada", "tags": ["a"] })),
+                auth: Some(Auth::Digest {
+                    username: "ada".to_string(),
+                    password: "{{password}}".to_string(),
+                }),
+                ..Default::default()
+            },
+            HttpRequest {
+                method: "DELETE".to_string(),
+                url: "https://api.test/sessions".to_string(),
+                headers: vec!["Content-Type: text/plain".to_string()],
+                body_type: Some("text".to_string()),
+                body: Some(json!("bye")),
+                ..Default::default()
+            },
+        ];
+        let imported = parse(&export(&requests), Path::new("."));
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        assert_eq!(imported.requests, requests);
+    }
+}
@@ -0,0 +1,93 @@
+//! Saving the requests and variables converted from other tools' collections.
+
+use crate::structs::{GlobalVariable, HttpRequest, OAuth2Grant};
+use crate::utils::{
+    get_global_variables, print_line, read_http_request_file, save_to_environment,
+    save_to_global_variables, write_http_request_file,
+};
+
+/// The result of converting a collection, with what could not be converted.
+#[derive(Default)]
+pub struct Imported {
+    pub requests: Vec<HttpRequest>,
+    pub variables: Vec<GlobalVariable>,
+    /// Named sets of variables, saved as xhtp environments.
+    pub environments: Vec<(String, Vec<GlobalVariable>)>,
+    pub warnings: Vec<String>,
+}
+
+impl Imported {
+    pub fn warn(&mut self, context: &str, message: &str) {
+        let warning = format!("{}: {}", context, message);
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+}
+
+/// The OAuth 2.0 grant of the names Postman, Insomnia and Bruno use. The implicit grant
+/// isn't supported.
+pub fn get_oauth2_grant(name: &str) -> Option<OAuth2Grant> {
+    match name {
+        "client_credentials" => Some(OAuth2Grant::ClientCredentials),
+        "password" | "password_credentials" => Some(OAuth2Grant::Password),
+        "authorization_code" | "authorization_code_with_pkce" => {
+            Some(OAuth2Grant::AuthorizationCode)
+        }
+        "refresh_token" => Some(OAuth2Grant::RefreshToken),
+        "device_code" | "urn:ietf:params:oauth:grant-type:device_code" => {
+            Some(OAuth2Grant::DeviceCode)
+        }
+        _ => None,
+    }
+}
+
+/// Appends the requests that aren't saved yet, saves the variables and prints a report.
+pub fn save(imported: Imported, source: &str) {
+    let mut requests = read_http_request_file();
+    let saved_count = requests.len();
+    for request in imported.requests {
+        if !requests.contains(&request) {
+            requests.push(request);
+        }
+    }
+    let added = requests.len() - saved_count;
+    write_http_request_file(&requests);
+    // Empty values, like the secrets left out of exported collections, don't replace set ones.
+    let global_variables = get_global_variables();
+    for variable in &imported.variables {
+        let is_set = global_variables
+            .iter()
+            .any(|global| global.key == variable.key && !global.value.is_empty());
+        if !variable.value.is_empty() || !is_set {
+            save_to_global_variables(variable.key.clone(), variable.value.clone());
+        }
+    }
+    for (name, variables) in &imported.environments {
+        save_to_environment(name, variables);
+    }
+    print_line(&format!(
+        "Imported {} requests and {} variables from {}",
+        added,
+        imported.variables.len(),
+        source
+    ));
+    if !imported.environments.is_empty() {
+        let names: Vec<&str> = imported
+            .environments
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        print_line(&format!("Saved the environments {}", names.join(", ")));
+    }
+    print_warnings(&imported.warnings);
+}
+
+pub fn print_warnings(warnings: &[String]) {
+    if !warnings.is_empty() {
+        print_line("Could not convert:");
+        for warning in warnings {
+            println!("- {}", warning);
+        }
+    }
+}
@@ -0,0 +1,643 @@
+//! Imports Insomnia exports (format 4): requests, folders, environments and auth.
+
+use crate::auth;
+use crate::import::{self, Imported};
+use crate::structs::{
+    ApiKeyLocation, Auth, ClientAuthentication, GlobalVariable, HttpRequest, OAuth2,
+};
+use crate::utils;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs;
+
+fn get_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+fn get_array<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+fn is_enabled(item: &Value) -> bool {
+    item.get("disabled").and_then(Value::as_bool) != Some(true)
+}
+
+/// `{{ _.baseUrl }}` becomes `{{baseUrl}}`. Template tags such as `{% response %}` are kept
+/// and reported.
+fn convert_template(text: &str, context: &str, imported: &mut Imported) -> String {
+    let variable = Regex::new(r"\{\{\s*(?:_\.)?([\w.-]+)\s*\}\}").unwrap();
+    if text.contains("{%") {
+        imported.warn(
+            context,
+            "template tags such as {% response %} or {% uuid %}",
+        );
+    }
+    variable.replace_all(text, "{{$1}}").to_string()
+}
+
+/// Nested environment values are flattened to `parent.child` keys, which is how they are
+/// referenced in templates.
+fn flatten_environment(data: &Value, prefix: &str, variables: &mut Vec<GlobalVariable>) {
+    let Some(data) = data.as_object() else {
+        return;
+    };
+    for (key, value) in data {
+        let key = match prefix {
+            "" => key.clone(),
+            prefix => format!("{}.{}", prefix, key),
+        };
+        match value {
+            Value::Object(_) => flatten_environment(value, &key, variables),
+            value => variables.push(GlobalVariable {
+                key,
+                value: utils::json_value_to_variable(value),
+            }),
+        }
+    }
+}
+
+/// Bearer tokens with a prefix other than `Bearer` stay a header, as do api keys sent as cookies.
+fn convert_auth(
+    auth: &Value,
+    headers: &mut Vec<String>,
+    context: &str,
+    imported: &mut Imported,
+) -> Option<Auth> {
+    if !is_enabled(auth) {
+        return None;
+    }
+    let parameter = |key: &str| get_str(auth, key).unwrap_or_default().to_string();
+    match get_str(auth, "type").unwrap_or("none") {
+        "none" => None,
+        "bearer" => match get_str(auth, "prefix").filter(|prefix| !prefix.is_empty()) {
+            Some(prefix) if prefix != "Bearer" => {
+                headers.push(format!("Authorization: {} {}", prefix, parameter("token")));
+                None
+            }
+            _ => Some(Auth::Bearer {
+                token: parameter("token"),
+            }),
+        },
+        "basic" => Some(Auth::Basic {
+            username: parameter("username"),
+            password: parameter("password"),
+        }),
+        "digest" => Some(Auth::Digest {
+            username: parameter("username"),
+            password: parameter("password"),
+        }),
+        "apikey" => match get_str(auth, "addTo") {
+            Some("cookie") => {
+                headers.push(format!(
+                    "Cookie: {}={}",
+                    parameter("key"),
+                    parameter("value")
+                ));
+                None
+            }
+            add_to => Some(Auth::ApiKey {
+                name: parameter("key"),
+                value: parameter("value"),
+                location: match add_to {
+                    Some("queryParams") => ApiKeyLocation::Query,
+                    _ => ApiKeyLocation::Header,
+                },
+            }),
+        },
+        "oauth2" => {
+            let grant = import::get_oauth2_grant(&parameter("grantType"));
+            let token_url = parameter("accessTokenUrl");
+            if let (Some(grant), false) = (grant, token_url.is_empty()) {
+                let in_body = auth.get("credentialsInBody").and_then(Value::as_bool);
+                return Some(Auth::OAuth2(OAuth2 {
+                    grant,
+                    token_url,
+                    client_id: parameter("clientId"),
+                    client_secret: parameter("clientSecret"),
+                    scope: parameter("scope"),
+                    username: parameter("username"),
+                    password: parameter("password"),
+                    auth_url: parameter("authorizationUrl"),
+                    redirect_uri: parameter("redirectUrl"),
+                    client_authentication: match in_body {
+                        Some(true) => ClientAuthentication::Body,
+                        _ => ClientAuthentication::Basic,
+                    },
+                    ..Default::default()
+                }));
+            }
+            let token = get_str(auth, "accessToken")
+                .filter(|token| !token.is_empty())
+                .unwrap_or("{{access_token}}");
+            Some(Auth::Bearer {
+                token: token.to_string(),
+            })
+        }
+        "iam" => Some(Auth::AwsSigv4 {
+            access_key_id: parameter("accessKeyId"),
+            secret_access_key: parameter("secretAccessKey"),
+            session_token: parameter("sessionToken"),
+            region: parameter("region"),
+            service: parameter("service"),
+        }),
+        auth_type => {
+            imported.warn(context, &format!("{} auth is not supported", auth_type));
+            None
+        }
+    }
+}
+
+fn has_content_type(headers: &[String]) -> bool {
+    headers.iter().any(|header| {
+        header
+            .split_once(':')
+            .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+    })
+}
+
+fn remove_content_type(headers: &mut Vec<String>) {
+    headers.retain(|header| {
+        !header
+            .split_once(':')
+            .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+    });
+}
+
+fn get_params(body: &Value, context: &str, imported: &mut Imported) -> Value {
+    let mut fields = Map::new();
+    for param in get_array(body, "params").filter(|param| is_enabled(param)) {
+        let Some(name) = get_str(param, "name") else {
+            continue;
+        };
+        let value = if get_str(param, "type") == Some("file") {
+            match get_str(param, "fileName").filter(|file| !file.is_empty()) {
+                Some(file) => format!("@{}", file),
+                None => {
+                    imported.warn(context, &format!("the file of the form field {}", name));
+                    continue;
+                }
+            }
+        } else {
+            convert_template(
+                get_str(param, "value").unwrap_or_default(),
+                context,
+                imported,
+            )
+        };
+        fields.insert(name.to_string(), Value::String(value));
+    }
+    Value::Object(fields)
+}
+
+/// Maps the mime type of the body to a body type: JSON and GraphQL become `json`,
+/// url encoded forms `form`, multipart forms `multipart` and anything else `text`.
+fn convert_body(
+    body: &Value,
+    headers: &mut Vec<String>,
+    context: &str,
+    imported: &mut Imported,
+) -> (Option<String>, Option<Value>) {
+    let mime_type = get_str(body, "mimeType").unwrap_or_default();
+    let text = convert_template(get_str(body, "text").unwrap_or_default(), context, imported);
+    match mime_type {
+        "application/x-www-form-urlencoded" => {
+            remove_content_type(headers);
+            (
+                Some("form".to_string()),
+                Some(get_params(body, context, imported)),
+            )
+        }
+        "multipart/form-data" => {
+            remove_content_type(headers);
+            (
+                Some("multipart".to_string()),
+                Some(get_params(body, context, imported)),
+            )
+        }
+        _ if text.is_empty() => (None, None),
+        "application/graphql" => {
+            let query =
+                serde_json::from_str::<Value>(&text).unwrap_or_else(|_| json!({ "query": text }));
+            (Some("json".to_string()), Some(query))
+        }
+        mime_type => {
+            if mime_type.contains("json") {
+                if let Ok(json) = serde_json::from_str::<Value>(&text) {
+                    return (Some("json".to_string()), Some(json));
+                }
+            }
+            if !mime_type.is_empty() && !has_content_type(headers) {
+                headers.push(format!("Content-Type: {}", mime_type));
+            }
+            (Some("text".to_string()), Some(Value::String(text)))
+        }
+    }
+}
+
+fn has_scripts(resource: &Value) -> bool {
+    ["preRequestScript", "afterResponseScript"]
+        .iter()
+        .any(|key| get_str(resource, key).is_some_and(|script| !script.trim().is_empty()))
+}
+
+/// Auth set to `{}` or missing is inherited from the closest folder that has one.
+fn has_auth(resource: &Value) -> bool {
+    resource
+        .get("authentication")
+        .and_then(|auth| get_str(auth, "type"))
+        .is_some()
+}
+
+struct Resources<'a> {
+    by_id: HashMap<&'a str, &'a Value>,
+}
+
+impl<'a> Resources<'a> {
+    fn get_parent(&self, resource: &Value) -> Option<&'a Value> {
+        let parent = self.by_id.get(get_str(resource, "parentId")?)?;
+        (get_str(parent, "_type") == Some("request_group")).then_some(*parent)
+    }
+
+    /// Nested folders are joined into `parent/child` folder names.
+    fn get_folder(&self, resource: &Value) -> Option<String> {
+        let mut names = Vec::new();
+        let mut parent = self.get_parent(resource);
+        while let Some(folder) = parent {
+            names.push(get_str(folder, "name").unwrap_or("folder"));
+            parent = self.get_parent(folder);
+        }
+        names.reverse();
+        (!names.is_empty()).then(|| names.join("/"))
+    }
+
+    fn get_auth(&self, resource: &'a Value) -> Option<&'a Value> {
+        let mut current = Some(resource);
+        while let Some(resource) = current {
+            if has_auth(resource) {
+                return resource.get("authentication");
+            }
+            current = self.get_parent(resource);
+        }
+        None
+    }
+
+    /// Headers set on folders apply to the requests they contain, unless a request sets them.
+    fn get_folder_headers(&self, resource: &Value) -> Vec<&'a Value> {
+        let mut headers = Vec::new();
+        let mut parent = self.get_parent(resource);
+        while let Some(folder) = parent {
+            headers.extend(get_array(folder, "headers"));
+            parent = self.get_parent(folder);
+        }
+        headers
+    }
+}
+
+fn convert_request(request: &Value, resources: &Resources, imported: &mut Imported) {
+    let name = get_str(request, "name").unwrap_or("request").to_string();
+    let folder = resources.get_folder(request);
+    let context = match &folder {
+        Some(folder) => format!("{}/{}", folder, name),
+        None => name.clone(),
+    };
+    let mut url = convert_template(
+        get_str(request, "url").unwrap_or_default(),
+        &context,
+        imported,
+    );
+    let query: Vec<String> = get_array(request, "parameters")
+        .filter(|parameter| is_enabled(parameter))
+        .filter_map(|parameter| {
+            Some(format!(
+                "{}={}",
+                get_str(parameter, "name")?,
+                get_str(parameter, "value").unwrap_or_default()
+            ))
+        })
+        .collect();
+    let mut headers: Vec<String> = Vec::new();
+    let folder_headers = resources.get_folder_headers(request);
+    for header in get_array(request, "headers").chain(folder_headers) {
+        let Some(header_name) = get_str(header, "name").filter(|_| is_enabled(header)) else {
+            continue;
+        };
+        let exists = headers.iter().any(|existing| {
+            existing
+                .split_once(':')
+                .is_some_and(|(existing, _)| existing.eq_ignore_ascii_case(header_name))
+        });
+        if !exists {
+            let value = get_str(header, "value").unwrap_or_default();
+            headers.push(format!("{}: {}", header_name, value));
+        }
+    }
+    let auth = resources
+        .get_auth(request)
+        .and_then(|auth| convert_auth(auth, &mut headers, &context, imported))
+        .map(|auth| {
+            auth::map_values(&auth, |value, _| {
+                convert_template(value, &context, imported)
+            })
+        });
+    for parameter in query {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(&convert_template(&parameter, &context, imported));
+    }
+    let mut headers: Vec<String> = headers
+        .iter()
+        .map(|header| convert_template(header, &context, imported))
+        .collect();
+    let (body_type, body) = match request.get("body") {
+        Some(body) => convert_body(body, &mut headers, &context, imported),
+        None => (None, None),
+    };
+    if has_scripts(request) {
+        imported.warn(&context, "pre-request and after-response scripts");
+    }
+    imported.requests.push(HttpRequest {
+        name: Some(name),
+        folder,
+        method: get_str(request, "method").unwrap_or("GET").to_uppercase(),
+        url,
+        headers,
+        body_type,
+        body,
+        auth,
+        ..Default::default()
+    });
+}
+
+/// The base environment of the workspace becomes global variables, and its sub environments
+/// xhtp environments of the same name.
+fn convert_environments(resources: &[Value], imported: &mut Imported) {
+    let environments: Vec<&Value> = resources
+        .iter()
+        .filter(|resource| get_str(resource, "_type") == Some("environment"))
+        .collect();
+    let is_base = |environment: &Value| {
+        get_str(environment, "parentId").is_some_and(|parent| parent.starts_with("wrk_"))
+    };
+    for environment in environments {
+        let mut variables = Vec::new();
+        flatten_environment(&environment["data"], "", &mut variables);
+        if is_base(environment) {
+            imported.variables.extend(variables);
+        } else {
+            let name = get_str(environment, "name").unwrap_or("insomnia");
+            imported.environments.push((name.to_string(), variables));
+        }
+    }
+}
+
+pub fn convert(export: &Value) -> Result<Imported, String> {
+    let resources = match (export.get("__export_format"), export.get("resources")) {
+        (Some(format), Some(Value::Array(resources))) if format.as_u64() == Some(4) => resources,
+        (Some(format), _) => {
+            return Err(format!(
+                "Unsupported Insomnia export format {}, export as Insomnia v4 (JSON)",
+                format
+            ))
+        }
+        _ => return Err("Not an Insomnia export".to_string()),
+    };
+    let by_id = resources
+        .iter()
+        .filter_map(|resource| Some((get_str(resource, "_id")?, resource)))
+        .collect();
+    let lookup = Resources { by_id };
+    let mut imported = Imported::default();
+    for resource in resources {
+        let name = get_str(resource, "name").unwrap_or_default();
+        match get_str(resource, "_type").unwrap_or_default() {
+            "request" => convert_request(resource, &lookup, &mut imported),
+            "request_group" => {
+                // Folder environments are merged into the global variables.
+                flatten_environment(&resource["environment"], "", &mut imported.variables);
+                if has_scripts(resource) {
+                    imported.warn(name, "folder scripts");
+                }
+            }
+            "grpc_request" => imported.warn(name, "gRPC requests are not supported"),
+            "websocket_request" => imported.warn(name, "WebSocket requests are not supported"),
+            _ => {}
+        }
+    }
+    convert_environments(resources, &mut imported);
+    Ok(imported)
+}
+
+pub fn read(path: &str) -> Result<Imported, String> {
+    let text =
+        fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+    let export: Value =
+        serde_json::from_str(&text).map_err(|err| format!("Failed to parse {}: {}", path, err))?;
+    convert(&export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export() -> Value {
+        json!({
+            "_type": "export",
+            "__export_format": 4,
+            "resources": [
+                { "_id": "wrk_1", "_type": "workspace", "name": "Shop" },
+                {
+                    "_id": "env_base",
+                    "_type": "environment",
+                    "parentId": "wrk_1",
+                    "data": { "baseUrl": "https://shop.test", "auth": { "user": "ada" } }
+                },
+                {
+                    "_id": "env_staging",
+                    "_type": "environment",
+                    "parentId": "env_base",
+                    "name": "Staging",
+                    "data": { "baseUrl": "https://staging.shop.test" }
+                },
+                {
+                    "_id": "fld_users",
+                    "_type": "request_group",
+                    "parentId": "wrk_1",
+                    "name": "Users",
+                    "environment": { "page": 1 },
+                    "headers": [
+                        { "name": "Accept", "value": "application/json" },
+                        { "name": "X-Team", "value": "shop" }
+                    ],
+                    "authentication": {
+                        "type": "basic",
+                        "username": "{{ _.auth.user }}",
+                        "password": "{{ _.password }}"
+                    }
+                },
+                {
+                    "_id": "fld_admin",
+                    "_type": "request_group",
+                    "parentId": "fld_users",
+                    "name": "Admin",
+                    "preRequestScript": "insomnia.log(1)"
+                },
+                {
+                    "_id": "req_get",
+                    "_type": "request",
+                    "parentId": "fld_admin",
+                    "name": "Get user",
+                    "method": "get",
+                    "url": "{{ _.baseUrl }}/users/{{ _.id }}",
+                    "parameters": [
+                        { "name": "page", "value": "{{ _.page }}" },
+                        { "name": "debug", "value": "1", "disabled": true }
+                    ],
+                    "headers": [
+                        { "name": "accept", "value": "application/xml" },
+                        { "name": "X-Off", "value": "1", "disabled": true }
+                    ],
+                    "authentication": {}
+                },
+                {
+                    "_id": "req_create",
+                    "_type": "request",
+                    "parentId": "wrk_1",
+                    "name": "Create",
+                    "method": "POST",
+                    "url": "{{ _.baseUrl }}/users",
+                    "headers": [{ "name": "X-Token", "value": "{% response 'body', 'req_get', '$.token' %}" }],
+                    "body": { "mimeType": "application/json", "text": "{\"name\": \"ada\"}" },
+                    "authentication": { "type": "bearer", "prefix": "Token", "token": "abc" }
+                },
+                {
+                    "_id": "req_upload",
+                    "_type": "request",
+                    "parentId": "wrk_1",
+                    "name": "Upload",
+                    "method": "POST",
+                    "url": "https://shop.test/upload",
+                    "headers": [{ "name": "Content-Type", "value": "multipart/form-data" }],
+                    "body": {
+                        "mimeType": "multipart/form-data",
+                        "params": [
+                            { "name": "title", "value": "{{ _.title }}" },
+                            { "name": "file", "type": "file", "fileName": "/tmp/cat.png" },
+                            { "name": "empty", "type": "file", "fileName": "" }
+                        ]
+                    },
+                    "authentication": { "type": "ntlm" }
+                },
+                {
+                    "_id": "req_csv",
+                    "_type": "request",
+                    "parentId": "wrk_1",
+                    "name": "Csv",
+                    "method": "PUT",
+                    "url": "https://shop.test/csv",
+                    "body": { "mimeType": "text/csv", "text": "a,b" },
+                    "authentication": { "type": "apikey", "addTo": "cookie", "key": "sid", "value": "1" }
+                },
+                { "_id": "grpc_1", "_type": "grpc_request", "parentId": "wrk_1", "name": "Stream" }
+            ]
+        })
+    }
+
+    #[test]
+    fn converts_requests_and_folders() {
+        let imported = convert(&export()).unwrap();
+        let requests = &imported.requests;
+        assert_eq!(requests.len(), 4);
+
+        let get_user = &requests[0];
+        assert_eq!(get_user.folder.as_deref(), Some("Users/Admin"));
+        assert_eq!(get_user.method, "GET");
+        assert_eq!(get_user.url, "{{baseUrl}}/users/{{id}}?page={{page}}");
+        assert_eq!(
+            get_user.headers,
+            vec!["accept: application/xml", "X-Team: shop"]
+        );
+        assert_eq!(
+            get_user.auth,
+            Some(Auth::Basic {
+                username: "{{auth.user}}".to_string(),
+                password: "{{password}}".to_string()
+            })
+        );
+
+        let create = &requests[1];
+        assert_eq!(create.folder, None);
+        assert_eq!(create.auth, None);
+        assert_eq!(
+            create.headers,
+            vec![
+                "X-Token: {% response 'body', 'req_get', '$.token' %}",
+                "Authorization: Token abc"
+            ]
+        );
+        assert_eq!(create.body_type.as_deref(), Some("json"));
+        assert_eq!(create.body, Some(json!({ "name": "ada" })));
+
+        let upload = &requests[2];
+        assert!(upload.headers.is_empty());
+        assert_eq!(upload.body_type.as_deref(), Some("multipart"));
+        assert_eq!(
+            upload.body,
+            Some(json!({ "title": "{{title}}", "file": "@/tmp/cat.png" }))
+        );
+
+        let csv = &requests[3];
+        assert_eq!(csv.headers, vec!["Cookie: sid=1", "Content-Type: text/csv"]);
+        assert_eq!(csv.body, Some(json!("a,b")));
+    }
+
+    #[test]
+    fn converts_environments() {
+        let imported = convert(&export()).unwrap();
+        let variables: Vec<(&str, &str)> = imported
+            .variables
+            .iter()
+            .map(|variable| (variable.key.as_str(), variable.value.as_str()))
+            .collect();
+        assert_eq!(
+            variables,
+            vec![
+                ("page", "1"),
+                ("auth.user", "ada"),
+                ("baseUrl", "https://shop.test")
+            ]
+        );
+        assert_eq!(imported.environments.len(), 1);
+        assert_eq!(imported.environments[0].0, "Staging");
+        assert_eq!(
+            imported.environments[0].1[0].value,
+            "https://staging.shop.test"
+        );
+    }
+
+    #[test]
+    fn warns_about_unsupported_resources() {
+        assert_eq!(
+            convert(&export()).unwrap().warnings,
+            vec![
+                "Admin: folder scripts",
+                "Create: template tags such as {% response %} or {% uuid %}",
+                "Upload: ntlm auth is not supported",
+                "Upload: the file of the form field empty",
+                "Stream: gRPC requests are not supported",
+            ]
+        );
+        let Err(err) = convert(&json!({ "__export_format": 3, "resources": [] })) else {
+            panic!("format 3 was accepted");
+        };
+        assert!(
+            err.starts_with("Unsupported Insomnia export format 3"),
+            "{}",
+            err
+        );
+        assert!(convert(&json!({ "resources": [] })).is_err());
+    }
+}
@@ -1,16 +1,40 @@
+use crate::snapshots::SnapshotKey;
 use crate::utils::read_http_request_file;
 use clap::Parser;
-use serde_json::Value;
 use std::fs::{self, File};
+mod assertions;
+mod auth;
+mod bench;
+mod bruno;
+mod client;
+mod content;
+mod curl;
+mod data;
+mod diff;
+mod exit_codes;
+mod export;
+mod har;
+mod history;
+mod http_file;
+mod import;
+mod insomnia;
+mod oauth2;
+mod openapi;
 mod openapi_structs;
+mod postman;
+mod reports;
+mod runner;
+mod signing;
+mod snapshots;
 mod structs;
+mod swagger;
 mod utils;
 use std::io::Write;
 use std::process::{Command, ExitStatus};
 
 fn open_requests_file_in_editor(request_index: &Option<&String>) {
-    if request_index.is_some() {
-        let index = request_index.unwrap().parse::<usize>().unwrap();
+    if let Some(request_index) = request_index {
+        let index = request_index.parse::<usize>().unwrap();
         let requests = read_http_request_file();
         let request = utils::get_request_from_saved_requests(&requests, index);
         let temp_file_path = "xhtp_tmp.json";
@@ -51,149 +75,112 @@ fn open_requests_file_in_editor(request_index: &Option<&String>) {
     }
 }
 
-fn print_http_response_as_json(http_response: &structs::HttpResponse) {
-    let json_http_response = serde_json::to_string(&http_response).unwrap();
-    println!("{}", json_http_response);
+/// Prints the entries as a list, or as a HAR file with `--as har`.
+fn print_history_entries(entries: &[structs::HistoryEntry], format: Option<&str>) {
+    match format {
+        Some("har") => println!(
+            "{}",
+            serde_json::to_string_pretty(&har::to_har(entries)).unwrap()
+        ),
+        _ => history::print_entries(entries),
+    }
 }
 
-async fn handle_response(
-    req: &structs::HttpRequest,
-    res: reqwest::Response,
-) -> Result<(), reqwest::Error> {
-    let mut http_response = structs::HttpResponse {
-        method: req.method.clone(),
-        url: req.url.clone(),
-        status_code: res.status().as_u16(),
-        json_data: None,
-        text_data: None,
+fn get_har_filter(args: &structs::Cli) -> Result<har::HarFilter, String> {
+    let url_pattern = match &args.url_pattern {
+        Some(pattern) => Some(
+            regex::Regex::new(pattern)
+                .map_err(|err| format!("Invalid --url-pattern {}: {}", pattern, err))?,
+        ),
+        None => None,
     };
+    Ok(har::HarFilter {
+        hosts: args.host.clone(),
+        methods: args.method.clone(),
+        url_pattern,
+    })
+}
 
-    let content_type = utils::get_content_type_from_header(res.headers());
-
-    if content_type == "application/json" {
-        let res_text = res.text().await?;
-        let json: Value = serde_json::from_str(&res_text).unwrap();
-
-        if req.extract_variables.is_some() {
-            let extract_variables = req.extract_variables.as_ref().unwrap();
-            for variable in extract_variables {
-                if let Some(value) = utils::get_json_value(&json, &variable.key_path) {
-                    utils::save_to_global_variables(
-                        variable.variable_name.clone(),
-                        value.to_string(),
-                    );
-                } else {
-                    println!(
-                        "The key '{}' was not found in the JSON.",
-                        &variable.key_path
-                    );
-                }
-            }
+fn get_history_entry(id: Option<&String>) -> structs::HistoryEntry {
+    let entry = id
+        .and_then(|id| id.parse::<u64>().ok())
+        .and_then(history::read_entry);
+    match entry {
+        Some(entry) => entry,
+        None => {
+            utils::print_line("Pass the id of a history entry, see `hl` for the list.");
+            std::process::exit(exit_codes::ERROR);
         }
-        http_response.json_data = Some(json.clone());
-        print_http_response_as_json(&http_response);
-    } else if res.status().as_u16() == 204 {
-        println!("204 No Content")
-    } else {
-        http_response.text_data = Some(res.text().await?);
-        print_http_response_as_json(&http_response);
     }
-
-    Ok(())
 }
 
-fn get_headers_from_vec(headers: &Vec<String>) -> reqwest::header::HeaderMap {
-    let mut header_map = reqwest::header::HeaderMap::new();
-    for header in headers {
-        let header_split: Vec<&str> = header.split(":").collect();
-        let header_name = header_split[0];
-        let header_value = header_split[1].trim().replace("\"", "");
-        header_map.insert(
-            reqwest::header::HeaderName::from_bytes(header_name.as_bytes()).unwrap(),
-            reqwest::header::HeaderValue::from_str(header_value.as_str()).unwrap(),
-        );
+async fn send_for_diff(
+    client: &reqwest::Client,
+    request: &structs::HttpRequest,
+    variables: &[structs::GlobalVariable],
+    label: &str,
+) -> diff::DiffSide {
+    match client::send_request(client, request, variables).await.0 {
+        Ok(received) => diff::DiffSide::from_received(label, &received),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(exit_codes::from_error(&err));
+        }
     }
-    header_map
 }
 
-async fn make_request(request: &structs::HttpRequest) -> Result<(), reqwest::Error> {
-    let global_variables = utils::get_global_variables();
-    let mut partial_url = request.url.clone();
-    let mut headers = request.headers.clone();
-
-    for global_variable in global_variables {
-        let key = format!("{{{{{}}}}}", global_variable.key);
-        let value = global_variable.value;
-        partial_url = partial_url.replace(&key, &value);
-        for header in &mut headers {
-            *header = header.replace(&key, &value);
+/// Two history entries, a saved request against its last history entry, or a saved
+/// request sent in two environments.
+async fn get_diff_sides(
+    client: &reqwest::Client,
+    requests: &[structs::HttpRequest],
+    second_arg: Option<&String>,
+    third_arg: Option<&String>,
+    environments: &[String],
+) -> (diff::DiffSide, diff::DiffSide) {
+    let read_environment = |name: &str| match utils::read_environment(name) {
+        Ok(variables) => variables,
+        Err(err) => {
+            utils::print_line(&err);
+            std::process::exit(exit_codes::ERROR);
         }
+    };
+    if third_arg.is_some() {
+        let left = get_history_entry(second_arg);
+        let right = get_history_entry(third_arg);
+        return (
+            diff::DiffSide::from_entry(&left),
+            diff::DiffSide::from_entry(&right),
+        );
     }
-
-    let full_url = utils::get_url_with_https(&partial_url);
-
-    if request.method == "GET" {
-        let response = reqwest::Client::new()
-            .get(&full_url)
-            .headers(get_headers_from_vec(&headers))
-            .send()
-            .await?;
-        handle_response(request, response).await?;
-    } else if request.method == "POST" {
-        if request.body_type.is_none() {
-            let response = reqwest::Client::new()
-                .post(&full_url)
-                .headers(get_headers_from_vec(&headers))
-                .send()
-                .await?;
-            handle_response(request, response).await?;
-            return Ok(());
-        } else if request.body_type.as_ref().unwrap() == "form" {
-            let response = reqwest::Client::new()
-                .post(&full_url)
-                .headers(get_headers_from_vec(&headers))
-                .form(&request.body)
-                .send()
-                .await?;
-            handle_response(request, response).await?;
-            return Ok(());
-        } else if request.body_type.as_ref().unwrap() == "json" {
-            let response = reqwest::Client::new()
-                .post(&full_url)
-                .headers(get_headers_from_vec(&headers))
-                .json(&request.body)
-                .send()
-                .await?;
-            handle_response(request, response).await?;
-            return Ok(());
-        } else if request.body_type.as_ref().unwrap() == "text" {
-            let response = reqwest::Client::new()
-                .post(&full_url)
-                .headers(get_headers_from_vec(&headers))
-                .body(request.body.as_ref().unwrap().to_string())
-                .send()
-                .await?;
-            handle_response(request, response).await?;
-            return Ok(());
+    let Some(index) = second_arg.and_then(|arg| utils::find_request_index(requests, arg)) else {
+        utils::print_line("Pass two history ids, or the number or name of a saved request.");
+        std::process::exit(exit_codes::ERROR);
+    };
+    let request = utils::get_request_from_saved_requests(requests, index);
+    match environments {
+        [left, right] => {
+            let left_side = send_for_diff(client, request, &read_environment(left), left).await;
+            let right_side = send_for_diff(client, request, &read_environment(right), right).await;
+            (left_side, right_side)
+        }
+        [] | [_] => {
+            let Some(entry) = history::find_latest(request) else {
+                utils::print_line("The request has no history yet, send it once before comparing.");
+                std::process::exit(exit_codes::ERROR);
+            };
+            let environment = environments
+                .first()
+                .map(|name| read_environment(name))
+                .unwrap_or_default();
+            let live = send_for_diff(client, request, &environment, "live").await;
+            (diff::DiffSide::from_entry(&entry), live)
+        }
+        _ => {
+            utils::print_line("Pass at most two environments to compare.");
+            std::process::exit(exit_codes::ERROR);
         }
-    } else if request.method == "PUT" {
-        let response = reqwest::Client::new()
-            .put(&full_url)
-            .headers(get_headers_from_vec(&headers))
-            .json(&request.body)
-            .send()
-            .await?;
-        handle_response(request, response).await?;
-    } else if request.method == "DELETE" {
-        let response = reqwest::Client::new()
-            .delete(&full_url)
-            .headers(get_headers_from_vec(&headers))
-            .send()
-            .await?;
-        handle_response(request, response).await?;
     }
-
-    Ok(())
 }
 
 #[tokio::main]
@@ -214,7 +201,17 @@ async fn main() -> Result<(), reqwest::Error> {
             "
         Usage: 
         Do a simple GET request by passing a url as an argument, alternatively you can select one of the following options:
-            i <path to openapi spec> - import an openapi spec and save the requests in the config file 
+            i <path, url or -> - import an openapi 3 or swagger 2 spec (json or yaml) and save the requests in the config file 
+            import bruno <folder> - save the requests and environments of a Bruno collection
+            import curl <command or -> - save a curl command as a request
+            import openapi <path, url or -> - the same as `i`
+            import har <path> - save the requests of a HAR file, filtered with --host, --method and --url-pattern
+            import http <path> - save the requests of a .http/.rest file and its variables
+            import insomnia <path> - save the requests and environments of an Insomnia v4 export
+            import postman <path> - save the requests and variables of a Postman collection or environment
+            export <request number or name> - print the request as a snippet to run without xhtp
+            export <selection> --as <postman|http> - print the selected requests (like `run`) as a Postman collection or .http file
+            sync <path, url or -> - update the requests imported from a spec, keeping local edits and flagging removed operations
             l - list all the urls in the config file
             l <request number> - list all the details of a specific request 
             e - open the requests config file in your editor
@@ -222,7 +219,45 @@ async fn main() -> Result<(), reqwest::Error> {
             gl - list all the global variables
             ga <variable name> <variable value> - add a global variable
             gd <variable name> - delete a global variable
+            auth - show the default auth of requests without their own
+            auth set <json> - set the default auth, with the same fields as the `auth` of a request
+            auth clear - remove the default auth
+            auth logout - forget the cached OAuth 2.0 tokens
+            run [selector] - run saved requests in order, e.g. `run 1-3,5`, `run login,create` or `run <folder>`
+            bench <number or name> - send a saved request many times and report latency percentiles, throughput and status codes
+            hl [count] - list the most recent requests in the history (default 20)
+            hf <text> - search the history by method, url, request name, status code or response body
+            hs <id> - show a history entry with its request and response
+            hr <id> - send a request from the history again
+            hp <id> - save a request from the history to the config file
+            diff <id> <id> - compare the responses of two history entries
+            diff <number or name> - send a saved request and compare the response to its last one in the history
+            diff <number or name> --env <a> --env <b> - send a saved request in two environments and compare the responses
             h - show help
+
+        Flags:
+            --check-status - exit with 3, 4 or 5 for 3xx, 4xx or 5xx responses, 9 on timeout and 6 on network errors
+                             (failed assertions always exit with 7)
+            --continue-on-failure - keep running the remaining steps of `run` after a step fails
+            --delay <ms> - wait between the steps of `run`
+            --report <path> - write a junit (.xml), json, tap or har report of `run`
+            --report-format <format> - junit, json, tap or har, when the extension doesn't tell
+            --data <file> - run the selection of `run` once per row of a csv or json file, using the columns as variables
+            --concurrency <n> - number of data rows (`run`) or requests (`bench`) in flight at the same time (default 1)
+            -n, --requests <n> - number of requests sent by `bench` (default 100)
+            --duration <seconds> - keep sending requests in `bench` for the given time instead
+            --rate <n> - maximum requests per second sent by `bench`, from 1 to 1000000
+            --output <table|json> - output format of `bench`
+            --timeout <seconds> - give up on a request after the given number of seconds
+            --no-history - don't record the request in the history
+            --no-history-body - record the request in the history without the response body
+            --env <name> - use the variables of ~/.xhtp/environments/<name>.json, before the global variables
+            --update-snapshots - overwrite the snapshots of the requests that are sent instead of comparing them
+            --server <n> - import with the nth server of the spec as its base url
+            --as <curl|httpie|python-requests|js-fetch|rust-reqwest|postman|http> - format of `export` (default curl), or har for `hl` and `hf`
+            --resolve - resolve the variables in the snippet of `export` instead of keeping the placeholders
+            --host <host>, --method <method>, --url-pattern <regex> - only import the matching HAR entries
+            --ignore <path> - leave a JSON path (e.g. `id` at any depth or `items.*.updatedAt`) or header out of `diff`
         "
         );
         return Ok(());
@@ -232,9 +267,25 @@ async fn main() -> Result<(), reqwest::Error> {
     let second_arg = args.second_arg.as_ref();
     let third_arg = args.third_arg.as_ref();
 
+    let client = utils::build_client(args.timeout, false);
+    client::configure(args.timeout);
+    history::configure(!args.no_history, !args.no_history_body);
+    snapshots::configure(args.update_snapshots);
+    let environment = if first_arg == "diff" {
+        Vec::new()
+    } else {
+        match utils::read_environments(&args.env) {
+            Ok(variables) => variables,
+            Err(err) => {
+                utils::print_line(&err);
+                std::process::exit(exit_codes::ERROR);
+            }
+        }
+    };
+
     if first_arg == "l" {
-        if second_arg.is_some() && utils::arg_is_number(&second_arg.as_ref().unwrap()) {
-            let index = second_arg.as_ref().unwrap().parse::<usize>().unwrap();
+        if let Some(index) = second_arg.filter(|arg| utils::arg_is_number(arg)) {
+            let index = index.parse::<usize>().unwrap();
             utils::print_full_saved_request_from_index(&requests, index)
         } else {
             utils::print_line("Pass the number of the request you want to use as an argument.");
@@ -262,43 +313,335 @@ async fn main() -> Result<(), reqwest::Error> {
         utils::save_to_global_variables(name, value);
         utils::list_global_variables();
         return Ok(());
-    } else if first_arg == "gd" && second_arg.is_some() && third_arg.is_none() {
-        let index_str = second_arg.as_ref().unwrap().to_string();
-        utils::delete_global_variable(index_str);
+    } else if let (true, Some(index_str), None) = (first_arg == "gd", second_arg, third_arg) {
+        utils::delete_global_variable(index_str.to_string());
         utils::list_global_variables();
         return Ok(());
-    } else if first_arg == "i" {
-        utils::handle_open_api_sepc_import(&second_arg.as_ref().unwrap())
-            .await
-            .unwrap();
+    } else if first_arg == "auth" {
+        let result = match (second_arg.map(|arg| arg.as_str()), third_arg) {
+            (None, _) => {
+                match auth::read_default() {
+                    Some(auth) => {
+                        utils::print_line(&format!("Default auth: {}", auth::describe(&auth)))
+                    }
+                    None => utils::print_line("No default auth is set."),
+                }
+                Ok(())
+            }
+            (Some("set"), Some(json)) => serde_json::from_str::<structs::Auth>(json)
+                .map_err(|err| format!("Invalid auth {}: {}", json, err))
+                .and_then(|auth| auth::save_default(Some(&auth))),
+            (Some("clear"), None) => auth::save_default(None),
+            (Some("logout"), None) => oauth2::forget_tokens(),
+            _ => Err("Use `auth`, `auth set <json>`, `auth clear` or `auth logout`.".to_string()),
+        };
+        if let Err(err) = result {
+            utils::print_line(&err);
+            std::process::exit(exit_codes::ERROR);
+        }
+        return Ok(());
+    } else if first_arg == "i" || first_arg == "sync" {
+        let Some(source) = second_arg else {
+            utils::print_line("Pass the path or url of the spec, or - to read it from stdin.");
+            std::process::exit(exit_codes::ERROR);
+        };
+        if let Err(err) =
+            openapi::handle_open_api_sepc_import(source, args.server, first_arg == "sync").await
+        {
+            utils::print_line(&err);
+            std::process::exit(exit_codes::ERROR);
+        }
+        return Ok(());
+    } else if first_arg == "import" {
+        let result = match second_arg.map(|arg| arg.as_str()) {
+            Some("bruno") => match third_arg {
+                Some(path) => bruno::read(path).map(|imported| import::save(imported, path)),
+                None => Err("Pass the path of the collection folder.".to_string()),
+            },
+            Some("curl") => curl::handle_curl_import(third_arg.map(|arg| arg.as_str())),
+            Some("har") => match third_arg {
+                Some(path) => get_har_filter(&args)
+                    .and_then(|filter| har::read(path, &filter))
+                    .map(|imported| import::save(imported, path)),
+                None => Err("Pass the path of the HAR file.".to_string()),
+            },
+            Some("http") => match third_arg {
+                Some(path) => http_file::read(path).map(|imported| import::save(imported, path)),
+                None => Err("Pass the path of the .http file.".to_string()),
+            },
+            Some("insomnia") => match third_arg {
+                Some(path) => insomnia::read(path).map(|imported| import::save(imported, path)),
+                None => Err("Pass the path of the Insomnia export.".to_string()),
+            },
+            Some("postman") => match third_arg {
+                Some(path) => postman::read(path).map(|imported| import::save(imported, path)),
+                None => Err("Pass the path of the collection or environment.".to_string()),
+            },
+            Some("openapi") => match third_arg {
+                Some(source) => {
+                    openapi::handle_open_api_sepc_import(source, args.server, false).await
+                }
+                None => {
+                    Err("Pass the path or url of the spec, or - to read it from stdin.".to_string())
+                }
+            },
+            _ => Err(
+                "Pass the format to import: bruno, curl, har, http, insomnia, openapi or postman."
+                    .to_string(),
+            ),
+        };
+        if let Err(err) = result {
+            utils::print_line(&err);
+            std::process::exit(exit_codes::ERROR);
+        }
+        return Ok(());
+    } else if first_arg == "export"
+        && matches!(
+            args.export_format.as_deref(),
+            Some("postman") | Some("http")
+        )
+    {
+        let indexes = match runner::select_requests(&requests, second_arg.map(|arg| arg.as_str())) {
+            Ok(indexes) => indexes,
+            Err(err) => {
+                utils::print_line(&err);
+                std::process::exit(exit_codes::ERROR);
+            }
+        };
+        let selected: Vec<structs::HttpRequest> = indexes
+            .iter()
+            .map(|index| auth::with_default(&requests[index - 1]))
+            .collect();
+        if args.export_format.as_deref() == Some("http") {
+            print!("{}", http_file::export(&selected));
+        } else {
+            let collection = postman::export_collection(&selected);
+            println!("{}", serde_json::to_string_pretty(&collection).unwrap());
+        }
+        return Ok(());
+    } else if first_arg == "export" {
+        let Some(index) = second_arg.and_then(|arg| utils::find_request_index(&requests, arg))
+        else {
+            utils::print_line("Pass the number or name of the request you want to export.");
+            utils::print_saved_requests(&requests);
+            std::process::exit(exit_codes::ERROR);
+        };
+        let format = match export::SnippetFormat::from_arg(args.export_format.as_deref()) {
+            Ok(format) => format,
+            Err(err) => {
+                utils::print_line(&err);
+                std::process::exit(exit_codes::ERROR);
+            }
+        };
+        let request = utils::get_request_from_saved_requests(&requests, index);
+        if args.resolve {
+            let mut resolved = client::resolve_with_global_variables(request, &environment);
+            auth::use_cached_token(&mut resolved);
+            println!("{}", export::render(&resolved, &format));
+        } else {
+            println!("{}", export::render(&auth::with_default(request), &format));
+        }
+        return Ok(());
+    } else if first_arg == "bench" {
+        let Some(index) = second_arg.and_then(|arg| utils::find_request_index(&requests, arg))
+        else {
+            utils::print_line("Pass the number or name of the request you want to benchmark.");
+            utils::print_saved_requests(&requests);
+            std::process::exit(exit_codes::ERROR);
+        };
+        let request = utils::get_request_from_saved_requests(&requests, index);
+        let resolved = client::resolve_with_global_variables(request, &environment);
+        let options = bench::BenchOptions {
+            requests: args.requests,
+            duration: args.duration,
+            concurrency: args.concurrency,
+            rate: args.rate,
+        };
+        let result = bench::run(&client, &resolved, &options).await;
+        if args.output.as_deref() == Some("json") {
+            result.print_json();
+        } else {
+            result.print_table();
+        }
+        return Ok(());
+    } else if first_arg == "hl" {
+        let count = second_arg
+            .and_then(|arg| arg.parse::<usize>().ok())
+            .unwrap_or(20);
+        let entries = history::read_entries();
+        print_history_entries(
+            &entries[entries.len().saturating_sub(count)..],
+            args.export_format.as_deref(),
+        );
+        return Ok(());
+    } else if first_arg == "hf" {
+        let Some(query) = second_arg else {
+            utils::print_line("Pass the text you want to search the history for.");
+            std::process::exit(exit_codes::ERROR);
+        };
+        print_history_entries(&history::search(query), args.export_format.as_deref());
+        return Ok(());
+    } else if first_arg == "hs" || first_arg == "hp" {
+        let entry = get_history_entry(second_arg);
+        if first_arg == "hs" {
+            history::print_entry(&entry);
+        } else {
+            let (request, variables) = history::get_replay_request(&entry);
+            requests.push(request);
+            utils::write_http_request_file(&requests);
+            utils::print_line(&format!(
+                "Saved history entry {} as request {}",
+                entry.id,
+                requests.len()
+            ));
+            if !variables.is_empty() {
+                utils::print_line(&format!(
+                    "Its masked secrets are now placeholders, set {} with `xhtp ga` before sending it.",
+                    variables.join(", ")
+                ));
+            }
+        }
+        return Ok(());
+    } else if first_arg == "diff" {
+        let (left, right) =
+            get_diff_sides(&client, &requests, second_arg, third_arg, &args.env).await;
+        let comparison = diff::compare(&left, &right, &args.ignore);
+        comparison.print(&left, &right);
+        if !comparison.is_empty() {
+            std::process::exit(exit_codes::DIFFERENT);
+        }
         return Ok(());
+    } else if first_arg == "run" {
+        // A `.http` file is run directly, with its variables before the environment.
+        let (requests, selector, environment) = match second_arg {
+            Some(path) if http_file::is_http_file(path) => {
+                let file = match http_file::read(path) {
+                    Ok(file) => file,
+                    Err(err) => {
+                        utils::print_line(&err);
+                        std::process::exit(exit_codes::ERROR);
+                    }
+                };
+                import::print_warnings(&file.warnings);
+                let mut variables = file.variables;
+                variables.extend(environment);
+                (file.requests, third_arg, variables)
+            }
+            _ => (requests, second_arg, environment),
+        };
+        let indexes = match runner::select_requests(&requests, selector.map(|arg| arg.as_str())) {
+            Ok(indexes) => indexes,
+            Err(err) => {
+                utils::print_line(&err);
+                std::process::exit(exit_codes::ERROR);
+            }
+        };
+        let report_format = match &args.report {
+            Some(path) => {
+                match reports::ReportFormat::from_args(path, args.report_format.as_deref()) {
+                    Ok(format) => Some(format),
+                    Err(err) => {
+                        utils::print_line(&err);
+                        std::process::exit(exit_codes::ERROR);
+                    }
+                }
+            }
+            None => None,
+        };
+        let options = runner::RunOptions {
+            continue_on_failure: args.continue_on_failure,
+            delay: args.delay,
+            concurrency: args.concurrency,
+            check_status: args.check_status,
+        };
+        let iterations = match &args.data {
+            Some(path) => {
+                let rows = match data::read_data_rows(path) {
+                    Ok(rows) => rows,
+                    Err(err) => {
+                        utils::print_line(&err);
+                        std::process::exit(exit_codes::ERROR);
+                    }
+                };
+                runner::run_data_rows(&client, &requests, &indexes, rows, &environment, &options)
+                    .await
+            }
+            None => vec![runner::IterationResult {
+                row: None,
+                variables: Vec::new(),
+                results: runner::run_requests(
+                    &client,
+                    &requests,
+                    &indexes,
+                    environment.clone(),
+                    &options,
+                    None,
+                )
+                .await,
+            }],
+        };
+        runner::print_summary(&requests, &indexes, &iterations);
+        if let (Some(path), Some(format)) = (&args.report, &report_format) {
+            if let Err(err) = reports::write_report(path, format, &requests, &indexes, &iterations)
+            {
+                utils::print_line(&format!("Failed to write the report to {}: {}", path, err));
+                std::process::exit(exit_codes::ERROR);
+            }
+            utils::print_line(&format!("Report written to {}", path));
+        }
+        match runner::get_exit_code(&iterations) {
+            exit_codes::SUCCESS => return Ok(()),
+            code => std::process::exit(code),
+        }
     }
 
-    if utils::arg_is_number(&first_arg) {
-        let index = utils::convert_option_to_number(&first_arg);
+    let result = if first_arg == "hr" {
+        let entry = get_history_entry(second_arg);
+        let (request, variables) = history::get_replay_request(&entry);
+        let global_variables = utils::get_global_variables();
+        let missing: Vec<String> = variables
+            .into_iter()
+            .filter(|name| {
+                !environment
+                    .iter()
+                    .chain(&global_variables)
+                    .any(|variable| &variable.key == name)
+            })
+            .collect();
+        if !missing.is_empty() {
+            utils::print_line(&format!(
+                "History entry {} has masked secrets, set {} with `xhtp ga` to send it again.",
+                entry.id,
+                missing.join(", ")
+            ));
+            std::process::exit(exit_codes::ERROR);
+        }
+        client::make_request(&client, &request, &environment, SnapshotKey::default()).await
+    } else if utils::arg_is_number(first_arg) {
+        let index = utils::convert_option_to_number(first_arg);
         if index > requests.len() {
             return utils::too_big(&requests);
         }
         let request = utils::get_request_from_saved_requests(&requests, index);
 
-        make_request(&request).await?;
+        client::make_request(&client, request, &environment, SnapshotKey::default()).await
     } else {
-        let full_url = utils::get_url_with_https(&first_arg);
-        let res = reqwest::get(full_url).await?;
-
-        println!("{}", res.status());
-
-        let content_type = utils::get_content_type_from_header(res.headers());
-
-        if content_type == "application/json" {
-            let res_text = res.text().await?;
-            let json: Value = serde_json::from_str(&res_text).unwrap();
+        client::make_ad_hoc_request(&client, first_arg).await
+    };
 
-            println!("{:#}", json);
-        } else {
-            println!("{}", res.text().await?);
+    match result {
+        Ok(outcome) => match exit_codes::from_outcome(&outcome, args.check_status) {
+            exit_codes::SUCCESS => Ok(()),
+            code => std::process::exit(code),
+        },
+        Err(err) if args.check_status => {
+            eprintln!("{}", err);
+            std::process::exit(exit_codes::from_error(&err))
+        }
+        Err(client::SendError::Http(err)) => Err(err),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(exit_codes::ERROR)
         }
     }
-
-    Ok(())
 }
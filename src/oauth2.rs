@@ -0,0 +1,911 @@
+//! Gets the access tokens of `oauth2` auth, caches them with their expiry and refreshes them.
+
+use crate::structs::{ClientAuthentication, OAuth2, OAuth2Grant};
+use crate::utils;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const DEFAULT_REDIRECT_URI: &str = "http://127.0.0.1:8400/callback";
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// Tokens are refreshed when they expire in less than this.
+const EXPIRY_MARGIN_SECONDS: i64 = 30;
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Serializes updates of the token cache between concurrent requests.
+static TOKENS_LOCK: Mutex<()> = Mutex::new(());
+/// One lock per cache key, held while a token is looked up and fetched, so that concurrent
+/// requests wait for a single fetch rather than each starting a grant of their own.
+static FETCH_LOCKS: OnceLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> = OnceLock::new();
+
+fn get_fetch_lock(key: &str) -> Arc<tokio::sync::Mutex<()>> {
+    let mut locks = FETCH_LOCKS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    locks.entry(key.to_string()).or_default().clone()
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct CachedToken {
+    access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    /// Unix seconds, unset when the server didn't say.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<i64>,
+}
+
+impl CachedToken {
+    fn is_fresh(&self) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| expires_at - EXPIRY_MARGIN_SECONDS > now())
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Tokens are shared by the requests with the same grant, credentials and scope. The
+/// credentials are hashed to keep them out of the cache file.
+fn get_key(config: &OAuth2) -> String {
+    let identity = serde_json::to_string(&[
+        serde_json::to_value(config.grant).unwrap(),
+        config.client_id.as_str().into(),
+        config.client_secret.as_str().into(),
+        config.username.as_str().into(),
+        config.password.as_str().into(),
+        config.refresh_token.as_str().into(),
+        config.scope.as_str().into(),
+    ])
+    .unwrap();
+    let hash = format!("{:x}", Sha256::digest(identity.as_bytes()));
+    format!("{} {}", config.token_url, &hash[..16])
+}
+
+fn read_tokens() -> BTreeMap<String, CachedToken> {
+    fs::read_to_string(utils::get_oauth2_tokens_file_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the tokens to a file only the user can read, then moves it over the cache so that
+/// readers never see half of it.
+fn write_tokens(path: &str, tokens: &BTreeMap<String, CachedToken>) -> std::io::Result<()> {
+    let temporary_path = format!("{}.{}.tmp", path, std::process::id());
+    let _ = fs::remove_file(&temporary_path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&temporary_path)?;
+    file.write_all(serde_json::to_string_pretty(tokens).unwrap().as_bytes())?;
+    drop(file);
+    fs::rename(&temporary_path, path)
+}
+
+/// Reads, updates and writes the cache while holding the lock. `update` returns whether it
+/// changed anything.
+fn update_tokens(update: impl FnOnce(&mut BTreeMap<String, CachedToken>) -> bool) {
+    let _lock = TOKENS_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let mut tokens = read_tokens();
+    if !update(&mut tokens) {
+        return;
+    }
+    let path = utils::get_oauth2_tokens_file_path();
+    if let Err(err) = write_tokens(&path, &tokens) {
+        eprintln!("Failed to write {}: {}", path, err);
+    }
+}
+
+fn save_token(config: &OAuth2, token: CachedToken) {
+    update_tokens(|tokens| {
+        tokens.insert(get_key(config), token);
+        true
+    });
+}
+
+/// Removes every cached token.
+pub fn forget_tokens() -> Result<(), String> {
+    let path = utils::get_oauth2_tokens_file_path();
+    match fs::metadata(&path) {
+        Ok(_) => {
+            fs::remove_file(&path).map_err(|err| format!("Failed to remove {}: {}", path, err))
+        }
+        Err(_) => Ok(()),
+    }
+}
+
+/// The cached access token of a resolved config, unless it is about to expire.
+pub fn cached_access_token(config: &OAuth2) -> Option<String> {
+    read_tokens()
+        .remove(&get_key(config))
+        .filter(CachedToken::is_fresh)
+        .map(|token| token.access_token)
+}
+
+/// Marks the cached access token as expired, keeping its refresh token, after the server
+/// rejected it. A token another request has already replaced is left alone.
+pub fn expire_access_token(config: &OAuth2, rejected: &str) {
+    update_tokens(|tokens| match tokens.get_mut(&get_key(config)) {
+        Some(token) if token.access_token == rejected => {
+            token.expires_at = Some(0);
+            true
+        }
+        _ => false,
+    });
+}
+
+/// The access token of a resolved config: the cached one, a refreshed one once it expires, or
+/// else one from the grant.
+pub async fn get_access_token(client: &reqwest::Client, config: &OAuth2) -> Result<String, String> {
+    let key = get_key(config);
+    let lock = get_fetch_lock(&key);
+    let _guard = lock.lock().await;
+    let cached = read_tokens().remove(&key);
+    if let Some(token) = cached.as_ref().filter(|token| token.is_fresh()) {
+        return Ok(token.access_token.clone());
+    }
+    let refresh_token = cached
+        .and_then(|token| token.refresh_token)
+        .or_else(|| {
+            (config.grant == OAuth2Grant::RefreshToken).then(|| config.refresh_token.clone())
+        })
+        .filter(|refresh_token| !refresh_token.is_empty());
+
+    let token = match refresh_token {
+        Some(refresh_token) => match refresh(client, config, &refresh_token).await {
+            Ok(token) => token,
+            Err(err) if config.grant == OAuth2Grant::RefreshToken => return Err(err),
+            Err(_) => request_grant(client, config).await?,
+        },
+        None => request_grant(client, config).await?,
+    };
+    let access_token = token.access_token.clone();
+    save_token(config, token);
+    Ok(access_token)
+}
+
+async fn request_grant(client: &reqwest::Client, config: &OAuth2) -> Result<CachedToken, String> {
+    let mut form = vec![];
+    match config.grant {
+        OAuth2Grant::ClientCredentials => {
+            form.push(("grant_type", "client_credentials".to_string()));
+        }
+        OAuth2Grant::Password => {
+            form.push(("grant_type", "password".to_string()));
+            form.push(("username", config.username.clone()));
+            form.push(("password", config.password.clone()));
+        }
+        OAuth2Grant::RefreshToken => {
+            return Err("The refresh token grant needs a refresh_token".to_string())
+        }
+        OAuth2Grant::AuthorizationCode => return authorize(client, config).await,
+        OAuth2Grant::DeviceCode => return authorize_device(client, config).await,
+    }
+    if !config.scope.is_empty() {
+        form.push(("scope", config.scope.clone()));
+    }
+    let body = post_form(client, config, &config.token_url, form).await?;
+    parse_token(&body, None)
+}
+
+async fn refresh(
+    client: &reqwest::Client,
+    config: &OAuth2,
+    refresh_token: &str,
+) -> Result<CachedToken, String> {
+    let mut form = vec![
+        ("grant_type", "refresh_token".to_string()),
+        ("refresh_token", refresh_token.to_string()),
+    ];
+    if !config.scope.is_empty() {
+        form.push(("scope", config.scope.clone()));
+    }
+    let body = post_form(client, config, &config.token_url, form).await?;
+    parse_token(&body, Some(refresh_token))
+}
+
+/// Posts a form to an endpoint of the authorization server. An error answer becomes the `Err`.
+async fn post_form(
+    client: &reqwest::Client,
+    config: &OAuth2,
+    url: &str,
+    form: Vec<(&str, String)>,
+) -> Result<Value, String> {
+    match send_form(client, config, url, form).await? {
+        Ok(body) => Ok(body),
+        Err((status, body)) => Err(format!(
+            "{} answered {}: {}",
+            utils::get_url_with_https(url),
+            status,
+            get_error(&body)
+        )),
+    }
+}
+
+/// Posts a form with the client credentials, returning the JSON answer, or its status and
+/// body when it is an error.
+async fn send_form(
+    client: &reqwest::Client,
+    config: &OAuth2,
+    url: &str,
+    mut form: Vec<(&str, String)>,
+) -> Result<Result<Value, (u16, Value)>, String> {
+    let url = utils::get_url_with_https(url);
+    let mut builder = client
+        .post(&url)
+        .header(reqwest::header::ACCEPT, "application/json");
+    match config.client_authentication {
+        ClientAuthentication::Basic if !config.client_secret.is_empty() => {
+            builder = builder.basic_auth(&config.client_id, Some(&config.client_secret));
+        }
+        _ => {
+            form.push(("client_id", config.client_id.clone()));
+            if !config.client_secret.is_empty() {
+                form.push(("client_secret", config.client_secret.clone()));
+            }
+        }
+    }
+    let response = builder
+        .form(&form)
+        .send()
+        .await
+        .map_err(|err| format!("Failed to reach {}: {}", url, err))?;
+    let status = response.status();
+    let text = response
+        .text()
+        .await
+        .map_err(|err| format!("Failed to read the answer of {}: {}", url, err))?;
+    let body: Value = serde_json::from_str(&text).map_err(|_| {
+        format!(
+            "{} answered {} without JSON: {}",
+            url,
+            status.as_u16(),
+            text
+        )
+    })?;
+    match status.is_success() && body.get("error").is_none() {
+        true => Ok(Ok(body)),
+        false => Ok(Err((status.as_u16(), body))),
+    }
+}
+
+fn get_error(body: &Value) -> String {
+    let error = body
+        .get("error")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown error");
+    match body.get("error_description").and_then(Value::as_str) {
+        Some(description) => format!("{} ({})", error, description),
+        None => error.to_string(),
+    }
+}
+
+/// Reads a number of seconds, which some servers send as a string.
+fn get_seconds(body: &Value, key: &str) -> Option<i64> {
+    match body.get(key)? {
+        Value::Number(number) => number.as_i64(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Servers may leave out the refresh token when refreshing, in which case the previous one
+/// stays valid.
+fn parse_token(body: &Value, previous_refresh_token: Option<&str>) -> Result<CachedToken, String> {
+    let access_token = body
+        .get("access_token")
+        .and_then(Value::as_str)
+        .ok_or("The token answer has no access_token")?;
+    Ok(CachedToken {
+        access_token: access_token.to_string(),
+        refresh_token: body
+            .get("refresh_token")
+            .and_then(Value::as_str)
+            .or(previous_refresh_token)
+            .map(str::to_string),
+        expires_at: get_seconds(body, "expires_in").map(|seconds| now() + seconds),
+    })
+}
+
+/// 32 bytes from the OS random number generator as 43 characters of url safe base64, for the
+/// PKCE code verifier and the state.
+fn get_random_string() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|err| format!("Failed to get random bytes: {}", err))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn open_browser(url: &str) {
+    let (program, args): (&str, Vec<&str>) = if cfg!(target_os = "macos") {
+        ("open", vec![url])
+    } else if cfg!(windows) {
+        ("rundll32", vec!["url.dll,FileProtocolHandler", url])
+    } else {
+        ("xdg-open", vec![url])
+    };
+    let _ = std::process::Command::new(program)
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+}
+
+/// The authorization code grant with PKCE: the browser is sent to the authorization endpoint,
+/// and the code comes back with the redirect to a listener on localhost.
+async fn authorize(client: &reqwest::Client, config: &OAuth2) -> Result<CachedToken, String> {
+    let redirect_uri = match config.redirect_uri.as_str() {
+        "" => DEFAULT_REDIRECT_URI,
+        redirect_uri => redirect_uri,
+    };
+    let redirect = reqwest::Url::parse(redirect_uri)
+        .map_err(|err| format!("Invalid redirect_uri {}: {}", redirect_uri, err))?;
+    let address = format!(
+        "{}:{}",
+        redirect.host_str().unwrap_or("127.0.0.1"),
+        redirect.port_or_known_default().unwrap_or(80)
+    );
+    let listener = tokio::net::TcpListener::bind(&address)
+        .await
+        .map_err(|err| format!("Failed to listen on {} for the redirect: {}", address, err))?;
+
+    let code_verifier = get_random_string()?;
+    let state = get_random_string()?;
+    let mut url = reqwest::Url::parse(&utils::get_url_with_https(&config.auth_url))
+        .map_err(|err| format!("Invalid auth_url {}: {}", config.auth_url, err))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", redirect_uri)
+        .append_pair("state", &state)
+        .append_pair(
+            "code_challenge",
+            &URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes())),
+        )
+        .append_pair("code_challenge_method", "S256");
+    if !config.scope.is_empty() {
+        url.query_pairs_mut().append_pair("scope", &config.scope);
+    }
+    eprintln!("Open this url to authorize xhtp:\n{}", url);
+    open_browser(url.as_str());
+
+    let code = tokio::time::timeout(
+        AUTHORIZATION_TIMEOUT,
+        wait_for_code(&listener, redirect.path(), &state),
+    )
+    .await
+    .map_err(|_| "Timed out waiting for the authorization".to_string())??;
+
+    let form = vec![
+        ("grant_type", "authorization_code".to_string()),
+        ("code", code),
+        ("redirect_uri", redirect_uri.to_string()),
+        ("code_verifier", code_verifier),
+    ];
+    let body = post_form(client, config, &config.token_url, form).await?;
+    parse_token(&body, None)
+}
+
+/// Answers the requests of the browser until the redirect with the code (or an error) arrives.
+async fn wait_for_code(
+    listener: &tokio::net::TcpListener,
+    path: &str,
+    state: &str,
+) -> Result<String, String> {
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let mut buffer = vec![0; 8192];
+        let mut length = 0;
+        while length < buffer.len() {
+            match stream.read(&mut buffer[length..]).await {
+                Ok(0) | Err(_) => break,
+                Ok(read) => length += read,
+            }
+            if buffer[..length]
+                .windows(4)
+                .any(|window| window == b"\r\n\r\n")
+            {
+                break;
+            }
+        }
+        let request = String::from_utf8_lossy(&buffer[..length]);
+        let target = request.split(' ').nth(1).unwrap_or("/");
+        let Ok(url) = reqwest::Url::parse(&format!("http://localhost{}", target)) else {
+            continue;
+        };
+        if url.path() != path {
+            let _ = stream
+                .write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await;
+            continue;
+        }
+        let parameter = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        let result = match (parameter("error"), parameter("code")) {
+            (Some(error), _) => Err(match parameter("error_description") {
+                Some(description) => {
+                    format!("The authorization failed: {} ({})", error, description)
+                }
+                None => format!("The authorization failed: {}", error),
+            }),
+            (None, Some(_)) if parameter("state").as_deref() != Some(state) => {
+                Err("The state of the redirect does not match".to_string())
+            }
+            (None, Some(code)) => Ok(code),
+            (None, None) => Err("The redirect has no code".to_string()),
+        };
+        let page = match &result {
+            Ok(_) => "xhtp is authorized, you can close this window.".to_string(),
+            Err(err) => err.clone(),
+        };
+        let _ = stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    page.len(),
+                    page
+                )
+                .as_bytes(),
+            )
+            .await;
+        return result;
+    }
+}
+
+/// The device code grant (RFC 8628): the user enters a code on another device while the token
+/// endpoint is polled.
+async fn authorize_device(
+    client: &reqwest::Client,
+    config: &OAuth2,
+) -> Result<CachedToken, String> {
+    let mut form = vec![];
+    if !config.scope.is_empty() {
+        form.push(("scope", config.scope.clone()));
+    }
+    let body = post_form(client, config, &config.device_url, form).await?;
+    let device_code = body
+        .get("device_code")
+        .and_then(Value::as_str)
+        .ok_or("The device authorization answer has no device_code")?;
+    let user_code = body
+        .get("user_code")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let verification_uri = ["verification_uri", "verification_url"]
+        .iter()
+        .find_map(|key| body.get(key).and_then(Value::as_str))
+        .unwrap_or_default();
+    match body
+        .get("verification_uri_complete")
+        .and_then(Value::as_str)
+    {
+        Some(complete) => eprintln!(
+            "Open {} to authorize xhtp, or enter the code {} at {}",
+            complete, user_code, verification_uri
+        ),
+        None => eprintln!(
+            "Open {} and enter the code {} to authorize xhtp",
+            verification_uri, user_code
+        ),
+    }
+
+    let mut interval = get_seconds(&body, "interval").unwrap_or(5).max(1) as u64;
+    let deadline = now() + get_seconds(&body, "expires_in").unwrap_or(600);
+    while now() < deadline {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        let form = vec![
+            ("grant_type", DEVICE_CODE_GRANT.to_string()),
+            ("device_code", device_code.to_string()),
+        ];
+        match send_form(client, config, &config.token_url, form).await? {
+            Ok(body) => return parse_token(&body, None),
+            Err((status, body)) => match body.get("error").and_then(Value::as_str) {
+                Some("authorization_pending") => {}
+                Some("slow_down") => interval += 5,
+                _ => {
+                    return Err(format!(
+                        "{} answered {}: {}",
+                        utils::get_url_with_https(&config.token_url),
+                        status,
+                        get_error(&body)
+                    ))
+                }
+            },
+        }
+    }
+    Err("The device code expired before it was authorized".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{self, SendError};
+    use crate::structs::{Auth, HttpRequest};
+    use std::sync::{Arc, Once};
+    use tokio::net::TcpListener;
+
+    /// The token cache is read from `~/.xhtp`, so the tests share a home of their own. Each
+    /// mock server has its own port, which keeps their tokens apart.
+    fn use_test_home() {
+        static HOME: Once = Once::new();
+        HOME.call_once(|| {
+            let home = std::env::temp_dir().join(format!("xhtp-oauth2-{}", std::process::id()));
+            fs::create_dir_all(home.join(".xhtp")).unwrap();
+            std::env::set_var("HOME", &home);
+        });
+    }
+
+    /// A stand-in server that answers one connection per canned answer, in order, and keeps
+    /// the requests it received with their header names lowercased.
+    struct MockServer {
+        url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockServer {
+        async fn start(answers: Vec<(u16, &'static str)>) -> MockServer {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let received = requests.clone();
+            tokio::spawn(async move {
+                for (status, body) in answers {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    let request = read_request(&mut stream).await;
+                    received.lock().unwrap().push(request);
+                    let answer = format!(
+                        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    stream.write_all(answer.as_bytes()).await.unwrap();
+                }
+            });
+            MockServer { url, requests }
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        /// The form posted in the nth request.
+        fn form(&self, index: usize) -> String {
+            let request = &self.requests()[index];
+            request.split_once("\r\n\r\n").unwrap().1.to_string()
+        }
+
+        fn config(&self, grant: OAuth2Grant) -> OAuth2 {
+            OAuth2 {
+                grant,
+                token_url: format!("{}/token", self.url),
+                client_id: "id".to_string(),
+                client_secret: "secret".to_string(),
+                scope: "read".to_string(),
+                ..Default::default()
+            }
+        }
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> String {
+        let mut buffer = Vec::new();
+        let mut chunk = [0; 4096];
+        loop {
+            let read = stream.read(&mut chunk).await.unwrap();
+            buffer.extend_from_slice(&chunk[..read]);
+            let text = String::from_utf8_lossy(&buffer).to_string();
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let head = head.to_lowercase();
+            let length = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .map_or(0, |length| length.trim().parse().unwrap());
+            if read == 0 || body.len() >= length {
+                return format!("{}\r\n\r\n{}", head, body);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn gets_a_client_credentials_token_and_caches_it() {
+        use_test_home();
+        let server =
+            MockServer::start(vec![(200, r#"{"access_token":"a1","expires_in":3600}"#)]).await;
+        let config = server.config(OAuth2Grant::ClientCredentials);
+        let client = reqwest::Client::new();
+
+        assert_eq!(get_access_token(&client, &config).await.unwrap(), "a1");
+        assert_eq!(get_access_token(&client, &config).await.unwrap(), "a1");
+        assert_eq!(server.requests().len(), 1);
+        assert!(server.requests()[0].starts_with("post /token "));
+        assert_eq!(
+            server.form(0),
+            "grant_type=client_credentials&scope=read&client_id=id&client_secret=secret"
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_the_resource_owner_for_the_password_grant() {
+        use_test_home();
+        let server = MockServer::start(vec![(200, r#"{"access_token":"a1"}"#)]).await;
+        let config = OAuth2 {
+            username: "alice".to_string(),
+            password: "p@ss".to_string(),
+            client_authentication: ClientAuthentication::Basic,
+            ..server.config(OAuth2Grant::Password)
+        };
+
+        let token = get_access_token(&reqwest::Client::new(), &config).await;
+        assert_eq!(token.unwrap(), "a1");
+        assert!(server.requests()[0].contains("authorization: basic awq6c2vjcmv0\r\n"));
+        assert_eq!(
+            server.form(0),
+            "grant_type=password&username=alice&password=p%40ss&scope=read"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_the_error_of_the_token_endpoint() {
+        use_test_home();
+        let server = MockServer::start(vec![(
+            401,
+            r#"{"error":"invalid_client","error_description":"Unknown client"}"#,
+        )])
+        .await;
+        let config = server.config(OAuth2Grant::ClientCredentials);
+
+        let err = get_access_token(&reqwest::Client::new(), &config)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            format!(
+                "{}/token answered 401: invalid_client (Unknown client)",
+                server.url
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn refreshes_expired_tokens_and_keeps_the_previous_refresh_token() {
+        use_test_home();
+        // Tokens expiring within the margin are refreshed on the next request.
+        let server = MockServer::start(vec![
+            (
+                200,
+                r#"{"access_token":"a1","refresh_token":"r1","expires_in":10}"#,
+            ),
+            (
+                200,
+                r#"{"access_token":"a2","refresh_token":"r2","expires_in":10}"#,
+            ),
+            (200, r#"{"access_token":"a3","expires_in":10}"#),
+            (200, r#"{"access_token":"a4","expires_in":3600}"#),
+        ])
+        .await;
+        let config = server.config(OAuth2Grant::ClientCredentials);
+        let client = reqwest::Client::new();
+
+        for expected in ["a1", "a2", "a3", "a4", "a4"] {
+            assert_eq!(get_access_token(&client, &config).await.unwrap(), expected);
+        }
+        assert_eq!(server.requests().len(), 4);
+        assert!(server
+            .form(1)
+            .starts_with("grant_type=refresh_token&refresh_token=r1&"));
+        assert!(server
+            .form(2)
+            .starts_with("grant_type=refresh_token&refresh_token=r2&"));
+        // The server left out the refresh token, so r2 is still used.
+        assert!(server
+            .form(3)
+            .starts_with("grant_type=refresh_token&refresh_token=r2&"));
+    }
+
+    #[tokio::test]
+    async fn treats_tokens_expiring_within_the_margin_as_expired() {
+        use_test_home();
+        let server = MockServer::start(vec![
+            (200, r#"{"access_token":"a1","expires_in":30}"#),
+            (200, r#"{"access_token":"a2","expires_in":40}"#),
+        ])
+        .await;
+        assert_eq!(EXPIRY_MARGIN_SECONDS, 30);
+        let config = server.config(OAuth2Grant::ClientCredentials);
+        let client = reqwest::Client::new();
+
+        assert_eq!(get_access_token(&client, &config).await.unwrap(), "a1");
+        assert_eq!(cached_access_token(&config), None);
+        assert_eq!(get_access_token(&client, &config).await.unwrap(), "a2");
+        assert_eq!(cached_access_token(&config).as_deref(), Some("a2"));
+        assert_eq!(get_access_token(&client, &config).await.unwrap(), "a2");
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn fetches_one_token_for_concurrent_requests() {
+        use_test_home();
+        // Only one answer, so a second fetch would find no server.
+        let server =
+            MockServer::start(vec![(200, r#"{"access_token":"a1","expires_in":3600}"#)]).await;
+        let config = server.config(OAuth2Grant::ClientCredentials);
+        let client = reqwest::Client::new();
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for _ in 0..8 {
+            let (client, config) = (client.clone(), config.clone());
+            tasks.spawn(async move { get_access_token(&client, &config).await });
+        }
+        while let Some(token) = tasks.join_next().await {
+            assert_eq!(token.unwrap().unwrap(), "a1");
+        }
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn polls_the_device_code_until_it_is_authorized() {
+        use_test_home();
+        let server = MockServer::start(vec![
+            (
+                200,
+                r#"{"device_code":"d1","user_code":"ABCD","verification_uri":"https://example.com/device","interval":1,"expires_in":600}"#,
+            ),
+            (400, r#"{"error":"authorization_pending"}"#),
+            (400, r#"{"error":"slow_down"}"#),
+            (200, r#"{"access_token":"a1"}"#),
+        ])
+        .await;
+        let config = OAuth2 {
+            device_url: format!("{}/device", server.url),
+            ..server.config(OAuth2Grant::DeviceCode)
+        };
+
+        let started = tokio::time::Instant::now();
+        let token = get_access_token(&reqwest::Client::new(), &config).await;
+        assert_eq!(token.unwrap(), "a1");
+        // One second before each of the first two polls, then six after slow_down.
+        assert_eq!(started.elapsed().as_secs(), 8);
+        assert!(server.requests()[0].starts_with("post /device "));
+        for index in 1..4 {
+            assert!(server.requests()[index].starts_with("post /token "));
+            assert!(server.form(index).starts_with(
+                "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code&device_code=d1&"
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_with_a_new_token_when_the_cached_one_is_rejected() {
+        use_test_home();
+        let server = MockServer::start(vec![
+            (401, "{}"),
+            (200, r#"{"access_token":"new"}"#),
+            (200, "{}"),
+        ])
+        .await;
+        let config = server.config(OAuth2Grant::ClientCredentials);
+        save_token(
+            &config,
+            CachedToken {
+                access_token: "revoked".to_string(),
+                refresh_token: None,
+                expires_at: None,
+            },
+        );
+        let request = HttpRequest {
+            method: "GET".to_string(),
+            url: format!("{}/api", server.url),
+            auth: Some(Auth::OAuth2(config.clone())),
+            ..Default::default()
+        };
+
+        let response = client::send(&reqwest::Client::new(), &request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let requests = server.requests();
+        assert!(requests[0].starts_with("get /api "));
+        assert!(requests[0].contains("authorization: bearer revoked\r\n"));
+        assert!(requests[1].starts_with("post /token "));
+        assert!(requests[2].starts_with("get /api "));
+        assert!(requests[2].contains("authorization: bearer new\r\n"));
+        assert_eq!(cached_access_token(&config).as_deref(), Some("new"));
+    }
+
+    #[tokio::test]
+    async fn does_not_send_the_request_without_a_token() {
+        use_test_home();
+        let server = MockServer::start(vec![(400, r#"{"error":"invalid_grant"}"#)]).await;
+        let request = HttpRequest {
+            method: "GET".to_string(),
+            url: format!("{}/api", server.url),
+            auth: Some(Auth::OAuth2(server.config(OAuth2Grant::ClientCredentials))),
+            ..Default::default()
+        };
+
+        let result = client::send(&reqwest::Client::new(), &request).await;
+        let Err(SendError::Request(err)) = result else {
+            panic!("expected a request error, got {:?}", result);
+        };
+        assert!(err.starts_with("Failed to get an OAuth 2.0 access token: "));
+        assert!(err.ends_with("answered 400: invalid_grant"));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_the_token_cache_for_the_user_only() {
+        use std::os::unix::fs::PermissionsExt;
+        use_test_home();
+        let config = OAuth2 {
+            token_url: "http://127.0.0.1:1/permissions".to_string(),
+            ..Default::default()
+        };
+        save_token(
+            &config,
+            CachedToken {
+                access_token: "a1".to_string(),
+                refresh_token: None,
+                expires_at: None,
+            },
+        );
+        let metadata = fs::metadata(utils::get_oauth2_tokens_file_path()).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn keeps_every_token_saved_concurrently() {
+        use_test_home();
+        let configs: Vec<OAuth2> = (0..16)
+            .map(|index| OAuth2 {
+                token_url: format!("http://127.0.0.1:1/concurrent/{}", index),
+                ..Default::default()
+            })
+            .collect();
+        std::thread::scope(|scope| {
+            for config in &configs {
+                scope.spawn(move || {
+                    save_token(
+                        config,
+                        CachedToken {
+                            access_token: config.token_url.clone(),
+                            refresh_token: None,
+                            expires_at: None,
+                        },
+                    )
+                });
+            }
+        });
+        for config in &configs {
+            assert_eq!(cached_access_token(config), Some(config.token_url.clone()));
+        }
+    }
+
+    #[test]
+    fn makes_random_strings_of_32_bytes() {
+        let first = get_random_string().unwrap();
+        assert_eq!(first.len(), 43);
+        assert_ne!(first, get_random_string().unwrap());
+    }
+}
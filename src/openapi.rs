@@ -0,0 +1,1293 @@
+//! Importing OpenAPI specs as saved requests.
+
+use crate::openapi_structs::{
+    Components, OpenAPI, Parameter, RequestBody, Schema, SecurityRequirement, SecurityScheme,
+    Server,
+};
+use crate::structs::{
+    ApiKeyLocation, Auth, GlobalVariable, HttpRequest, ImportedRequest, OAuth2, OAuth2Grant,
+    RequestOrigin,
+};
+use crate::utils::{
+    get_global_variables, get_url_with_https, json_value_to_variable, print_line,
+    read_http_request_file, save_to_global_variables, write_http_request_file,
+};
+use crate::{content, swagger};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// The global variable holding the server of a spec without a title.
+const BASE_URL_VARIABLE: &str = "baseUrl";
+
+/// Returns `Name` for a reference such as `#/components/schemas/Name`.
+fn get_component_name<'a>(reference: &'a str, kind: &str) -> Option<&'a str> {
+    reference
+        .strip_prefix("#/components/")?
+        .strip_prefix(kind)?
+        .strip_prefix('/')
+}
+
+fn resolve_parameter(parameter: &Parameter, components: &Components) -> Option<Parameter> {
+    match &parameter.reference {
+        Some(reference) => components
+            .parameters
+            .get(get_component_name(reference, "parameters")?)
+            .cloned(),
+        None => Some(parameter.clone()),
+    }
+}
+
+/// The first non-null type, guessing object or array from the properties or items.
+fn get_schema_type(schema: &Schema) -> Option<&str> {
+    match &schema.schema_type {
+        Some(Value::String(schema_type)) => Some(schema_type),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(|schema_type| schema_type.as_str())
+            .find(|schema_type| *schema_type != "null"),
+        _ if schema.properties.is_some() => Some("object"),
+        _ if schema.items.is_some() => Some("array"),
+        _ => None,
+    }
+}
+
+fn get_string_example(format: Option<&str>) -> Value {
+    let example = match format {
+        Some("date-time") => "2024-01-01T00:00:00Z",
+        Some("date") => "2024-01-01",
+        Some("time") => "12:00:00",
+        Some("email") => "user@example.com",
+        Some("uuid") => "00000000-0000-0000-0000-000000000000",
+        Some("uri") | Some("url") => "https://example.com",
+        Some("hostname") => "example.com",
+        Some("ipv4") => "127.0.0.1",
+        Some("ipv6") => "::1",
+        Some("binary") | Some("byte") => "",
+        _ => "string",
+    };
+    Value::String(example.to_string())
+}
+
+/// Builds an example value from the schema. `expanding` holds the referenced schemas that are
+/// being built, so a schema that refers to itself ends in `null` instead of recursing forever.
+pub fn get_schema_example(
+    schema: &Schema,
+    components: &Components,
+    expanding: &mut Vec<String>,
+) -> Value {
+    let Some(reference) = &schema.reference else {
+        return get_resolved_schema_example(schema, components, expanding);
+    };
+    let Some(name) = get_component_name(reference, "schemas") else {
+        return Value::Null;
+    };
+    let Some(resolved) = components.schemas.get(name) else {
+        return Value::Null;
+    };
+    if expanding.iter().any(|expanded| expanded == name) {
+        return Value::Null;
+    }
+    expanding.push(name.to_string());
+    let example = get_resolved_schema_example(resolved, components, expanding);
+    expanding.pop();
+    example
+}
+
+/// Prefers the `example`, `examples`, `default` and `enum` values of the schema. `allOf`
+/// parts are merged, and the first `oneOf`/`anyOf` variant is used.
+fn get_resolved_schema_example(
+    schema: &Schema,
+    components: &Components,
+    expanding: &mut Vec<String>,
+) -> Value {
+    let example = schema
+        .example
+        .as_ref()
+        .or_else(|| {
+            schema
+                .examples
+                .as_ref()
+                .and_then(|examples| examples.first())
+        })
+        .or(schema.default.as_ref())
+        .or_else(|| {
+            schema
+                .enum_values
+                .as_ref()
+                .and_then(|values| values.first())
+        });
+    if let Some(example) = example {
+        return example.clone();
+    }
+
+    let mut object = Map::new();
+    let variant = schema
+        .one_of
+        .as_ref()
+        .or(schema.any_of.as_ref())
+        .and_then(|variants| variants.first());
+    let parts = schema.all_of.iter().flatten().chain(variant);
+    for part in parts {
+        match get_schema_example(part, components, expanding) {
+            Value::Object(map) => object.extend(map),
+            Value::Null => {}
+            value if object.is_empty() && schema.properties.is_none() => return value,
+            _ => {}
+        }
+    }
+
+    match get_schema_type(schema) {
+        Some("array") => Value::Array(
+            schema
+                .items
+                .iter()
+                .map(|items| get_schema_example(items, components, expanding))
+                .collect(),
+        ),
+        Some("string") => get_string_example(schema.format.as_deref()),
+        Some("integer") | Some("number") => json!(0),
+        Some("boolean") => json!(false),
+        Some("object") | None if schema.properties.is_some() || !object.is_empty() => {
+            let required = schema.required.as_deref().unwrap_or_default();
+            for (name, property) in schema.properties.iter().flatten() {
+                let example = get_schema_example(property, components, expanding);
+                if !example.is_null() || required.contains(name) {
+                    object.insert(name.clone(), example);
+                }
+            }
+            Value::Object(object)
+        }
+        Some("object") => Value::Object(object),
+        _ => Value::Null,
+    }
+}
+
+/// Picks a JSON, form, multipart or other media type, in that order, and returns the
+/// body type, an example body and the content type header needed for text bodies.
+fn get_request_body(
+    request_body: &RequestBody,
+    components: &Components,
+) -> Option<(String, Value, Option<String>)> {
+    let request_body = match &request_body.reference {
+        Some(reference) => components
+            .request_bodies
+            .get(get_component_name(reference, "requestBodies")?)?,
+        None => request_body,
+    };
+    let find_media_type = |matches: fn(&str) -> bool| {
+        request_body
+            .content
+            .iter()
+            .find(|(media_type, _)| matches(&content::parse_content_type(media_type).0))
+    };
+    let (body_type, (media_type, media)) = [
+        ("json", content::is_json_mime_type as fn(&str) -> bool),
+        ("form", |mime_type: &str| {
+            mime_type == "application/x-www-form-urlencoded"
+        }),
+        ("multipart", |mime_type: &str| {
+            mime_type.starts_with("multipart/")
+        }),
+        ("text", |_: &str| true),
+    ]
+    .into_iter()
+    .find_map(|(body_type, matches)| Some((body_type, find_media_type(matches)?)))?;
+
+    let example = media
+        .example
+        .clone()
+        .or_else(|| {
+            media
+                .examples
+                .as_ref()?
+                .values()
+                .find_map(|example| match &example.reference {
+                    Some(reference) => components
+                        .examples
+                        .get(get_component_name(reference, "examples")?)?
+                        .value
+                        .clone(),
+                    None => example.value.clone(),
+                })
+        })
+        .or_else(|| {
+            let schema = media.schema.as_ref()?;
+            Some(get_schema_example(schema, components, &mut Vec::new()))
+        })
+        .unwrap_or(Value::Null);
+
+    match body_type {
+        "text" => {
+            let text = match example {
+                Value::String(text) => text,
+                Value::Null => String::new(),
+                example => example.to_string(),
+            };
+            Some((
+                body_type.to_string(),
+                Value::String(text),
+                Some(media_type.clone()),
+            ))
+        }
+        _ => Some((body_type.to_string(), example, None)),
+    }
+}
+
+/// The variable holding the token of bearer, oauth2 and OpenID Connect schemes.
+const ACCESS_TOKEN_VARIABLE: &str = "access_token";
+
+/// The credentials of a security requirement. Those that `auth` can't hold, such as a cookie
+/// or a second scheme of the same requirement, are sent in the headers, query or cookies.
+#[derive(Default)]
+struct Credentials {
+    auth: Option<Auth>,
+    headers: Vec<String>,
+    query: Vec<String>,
+    cookies: Vec<String>,
+}
+
+fn placeholder(name: &str) -> String {
+    format!("{{{{{}}}}}", name)
+}
+
+/// An OAuth 2.0 client for the first flow of the scheme with an absolute token url, asking for
+/// the scopes of the requirement.
+fn get_oauth2(scheme: &SecurityScheme, scopes: &[String]) -> Option<OAuth2> {
+    let flows = scheme.flows.as_ref()?;
+    let (grant, flow) = [
+        (OAuth2Grant::ClientCredentials, &flows.client_credentials),
+        (OAuth2Grant::AuthorizationCode, &flows.authorization_code),
+        (OAuth2Grant::Password, &flows.password),
+    ]
+    .into_iter()
+    .find_map(|(grant, flow)| {
+        let flow = flow.as_ref()?;
+        is_absolute_url(flow.token_url.as_deref()?).then_some((grant, flow))
+    })?;
+    let mut oauth2 = OAuth2 {
+        grant,
+        token_url: flow.token_url.clone().unwrap_or_default(),
+        client_id: placeholder("client_id"),
+        client_secret: placeholder("client_secret"),
+        scope: scopes.join(" "),
+        ..Default::default()
+    };
+    match grant {
+        OAuth2Grant::AuthorizationCode => {
+            oauth2.auth_url = flow.authorization_url.clone().unwrap_or_default()
+        }
+        OAuth2Grant::Password => {
+            oauth2.username = placeholder("username");
+            oauth2.password = placeholder("password");
+        }
+        _ => {}
+    }
+    Some(oauth2)
+}
+
+/// Maps a security scheme to auth. Basic and digest use `{{username}}` and `{{password}}`,
+/// bearer, OpenID Connect and oauth2 schemes without a token url `{{access_token}}`, and
+/// apiKey schemes a variable named after the scheme.
+fn get_auth(name: &str, scheme: &SecurityScheme, scopes: &[String]) -> Option<Auth> {
+    match (
+        scheme.scheme_type.as_str(),
+        scheme.scheme.as_deref().map(str::to_lowercase).as_deref(),
+    ) {
+        ("http", Some("basic")) => Some(Auth::Basic {
+            username: placeholder("username"),
+            password: placeholder("password"),
+        }),
+        ("http", Some("digest")) => Some(Auth::Digest {
+            username: placeholder("username"),
+            password: placeholder("password"),
+        }),
+        ("http", Some("bearer")) | ("openIdConnect", _) => Some(Auth::Bearer {
+            token: placeholder(ACCESS_TOKEN_VARIABLE),
+        }),
+        ("oauth2", _) => Some(match get_oauth2(scheme, scopes) {
+            Some(oauth2) => Auth::OAuth2(oauth2),
+            None => Auth::Bearer {
+                token: placeholder(ACCESS_TOKEN_VARIABLE),
+            },
+        }),
+        ("apiKey", _) => {
+            let location = match scheme.location.as_deref() {
+                Some("query") => ApiKeyLocation::Query,
+                Some("header") | None => ApiKeyLocation::Header,
+                Some(_) => return None,
+            };
+            Some(Auth::ApiKey {
+                name: scheme.name.clone().unwrap_or_else(|| name.to_string()),
+                value: placeholder(name),
+                location,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Sends a scheme that can't go in the auth of the request in its headers, query or cookies,
+/// with a variable named after the scheme, or `{{access_token}}` for bearer tokens.
+fn add_to_request(credentials: &mut Credentials, name: &str, scheme: &SecurityScheme) {
+    let access_token = placeholder(ACCESS_TOKEN_VARIABLE);
+    match (
+        scheme.scheme_type.as_str(),
+        scheme.scheme.as_deref().map(str::to_lowercase).as_deref(),
+    ) {
+        ("http", Some(other)) if other != "bearer" => credentials.headers.push(format!(
+            "Authorization: {} {}",
+            scheme.scheme.as_deref().unwrap_or(other),
+            placeholder(name)
+        )),
+        ("http", _) | ("oauth2", _) | ("openIdConnect", _) => credentials
+            .headers
+            .push(format!("Authorization: Bearer {}", access_token)),
+        ("apiKey", _) => {
+            let key = format!(
+                "{}={}",
+                scheme.name.as_deref().unwrap_or(name),
+                placeholder(name)
+            );
+            match scheme.location.as_deref() {
+                Some("query") => credentials.query.push(key),
+                Some("cookie") => credentials.cookies.push(key),
+                _ => credentials.headers.push(key.replacen('=', ": ", 1)),
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Credentials for the first of the alternative requirements. The first of its schemes that
+/// maps to auth goes there, the others are added to the request itself.
+fn get_credentials(requirements: &[SecurityRequirement], components: &Components) -> Credentials {
+    let mut credentials = Credentials::default();
+    let Some(requirement) = requirements.first() else {
+        return credentials;
+    };
+    for (name, scopes) in requirement {
+        let Some(scheme) = components.security_schemes.get(name) else {
+            continue;
+        };
+        match credentials
+            .auth
+            .is_none()
+            .then(|| get_auth(name, scheme, scopes))
+            .flatten()
+        {
+            Some(auth) => credentials.auth = Some(auth),
+            None => add_to_request(&mut credentials, name, scheme),
+        }
+    }
+    credentials
+}
+
+/// Whether the auth sends the header, in which case a header parameter of the same name is
+/// left out.
+fn sets_header(auth: Option<&Auth>, name: &str) -> bool {
+    match auth {
+        Some(Auth::ApiKey {
+            name: key,
+            location: ApiKeyLocation::Header,
+            ..
+        }) => key.eq_ignore_ascii_case(name),
+        Some(Auth::Basic { .. } | Auth::Digest { .. } | Auth::Bearer { .. } | Auth::OAuth2(_)) => {
+            name.eq_ignore_ascii_case("authorization")
+        }
+        _ => false,
+    }
+}
+
+/// Operation level parameters replace path level ones with the same name and location.
+fn merge_parameters(
+    path_parameters: &[Parameter],
+    operation_parameters: &[Parameter],
+) -> Vec<Parameter> {
+    let mut parameters: Vec<Parameter> = path_parameters
+        .iter()
+        .filter(|parameter| {
+            !operation_parameters.iter().any(|operation_parameter| {
+                operation_parameter.name == parameter.name
+                    && operation_parameter.location == parameter.location
+            })
+        })
+        .cloned()
+        .collect();
+    parameters.extend(operation_parameters.iter().cloned());
+    parameters
+}
+
+/// The example of the parameter, or of its schema, or the schema default.
+fn get_parameter_example(parameter: &Parameter) -> Option<&Value> {
+    parameter.example.as_ref().or_else(|| {
+        let schema = parameter.schema.as_ref()?;
+        schema.example.as_ref().or(schema.default.as_ref())
+    })
+}
+
+/// Maps every operation to a request named after its `operationId`, using
+/// `{{operation.parameter}}` placeholders, credential placeholders for its security
+/// requirements and an example body. The operation is the `operationId`, or else the method
+/// and path in camelCase, so that parameters of the same name in different operations don't
+/// share a variable. Optional query, header and cookie parameters are only added when they
+/// have an example or default. Also returns the examples and defaults of the parameters as
+/// variables.
+pub fn map_open_api_spec_to_http_requests(
+    base_url: &str,
+    open_api: OpenAPI,
+) -> (Vec<HttpRequest>, Vec<GlobalVariable>) {
+    let mut requests = Vec::new();
+    let mut examples: Vec<GlobalVariable> = Vec::new();
+    let components = open_api.components;
+    let security = open_api.security;
+    let resolve_parameters = |parameters: Option<Vec<Parameter>>| -> Vec<Parameter> {
+        parameters
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|parameter| resolve_parameter(parameter, &components))
+            .collect()
+    };
+    for (path, path_item) in open_api.paths {
+        let path_parameters = resolve_parameters(path_item.parameters);
+        let operations = [
+            ("GET", path_item.get),
+            ("POST", path_item.post),
+            ("PUT", path_item.put),
+            ("PATCH", path_item.patch),
+            ("DELETE", path_item.delete),
+            ("HEAD", path_item.head),
+            ("OPTIONS", path_item.options),
+            ("TRACE", path_item.trace),
+        ];
+        for (method, operation) in operations {
+            let Some(operation) = operation else {
+                continue;
+            };
+            let parameters =
+                merge_parameters(&path_parameters, &resolve_parameters(operation.parameters));
+            // Servers of the operation or path replace the base url when they are absolute.
+            let server_url = operation
+                .servers
+                .as_ref()
+                .or(path_item.servers.as_ref())
+                .and_then(|servers| servers.first())
+                .map(resolve_server_url)
+                .filter(|url| is_absolute_url(url));
+            let mut url = format!("{}{}", server_url.as_deref().unwrap_or(base_url), path);
+            let Credentials {
+                auth,
+                mut headers,
+                mut query,
+                mut cookies,
+            } = get_credentials(
+                operation.security.as_deref().unwrap_or(&security),
+                &components,
+            );
+            let variable_prefix = match &operation.operation_id {
+                Some(operation_id) => operation_id.clone(),
+                None => camel_case(&format!("{} {}", method, path)),
+            };
+            for parameter in &parameters {
+                let example = get_parameter_example(parameter);
+                let variable = format!("{}.{}", variable_prefix, parameter.name);
+                let placeholder = format!("{{{{{}}}}}", variable);
+                let included = parameter.required == Some(true) || example.is_some();
+                match parameter.location.as_str() {
+                    "path" => url = url.replace(&format!("{{{}}}", parameter.name), &placeholder),
+                    "query" if included => {
+                        query.push(format!("{}={}", parameter.name, placeholder))
+                    }
+                    "header"
+                        if included
+                            && !sets_header(auth.as_ref(), &parameter.name)
+                            && !headers.iter().any(|header| {
+                                header.split_once(':').is_some_and(|(name, _)| {
+                                    name.eq_ignore_ascii_case(&parameter.name)
+                                })
+                            }) =>
+                    {
+                        headers.push(format!("{}: {}", parameter.name, placeholder))
+                    }
+                    "cookie" if included => {
+                        cookies.push(format!("{}={}", parameter.name, placeholder))
+                    }
+                    _ => continue,
+                }
+                if let Some(example) = example {
+                    if !examples.iter().any(|example| example.key == variable) {
+                        examples.push(GlobalVariable {
+                            key: variable,
+                            value: json_value_to_variable(example),
+                        });
+                    }
+                }
+            }
+            if !query.is_empty() {
+                url = format!("{}?{}", url, query.join("&"));
+            }
+            if !cookies.is_empty() {
+                headers.push(format!("Cookie: {}", cookies.join("; ")));
+            }
+            let (body_type, body) = match operation
+                .request_body
+                .as_ref()
+                .and_then(|request_body| get_request_body(request_body, &components))
+            {
+                Some((body_type, body, content_type)) => {
+                    if let Some(content_type) = content_type {
+                        headers.push(format!("Content-Type: {}", content_type));
+                    }
+                    (Some(body_type), Some(body))
+                }
+                None => (None, None),
+            };
+            let operation_name = operation
+                .operation_id
+                .clone()
+                .unwrap_or_else(|| format!("{} {}", method, path));
+            let origin = RequestOrigin {
+                operation: operation_name,
+                imported: ImportedRequest {
+                    method: method.to_string(),
+                    url: url.clone(),
+                    headers: headers.clone(),
+                    body_type: body_type.clone(),
+                    body: body.clone(),
+                    auth: auth.clone(),
+                },
+                ..Default::default()
+            };
+            let request = HttpRequest {
+                name: operation.operation_id.clone(),
+                method: method.to_string(),
+                url,
+                headers,
+                body_type,
+                body,
+                auth,
+                origin: Some(origin),
+                extract_variables: None,
+                ..Default::default()
+            };
+            requests.push(request);
+        }
+    }
+    (requests, examples)
+}
+
+fn is_same_origin(request: &HttpRequest, origin: &RequestOrigin) -> bool {
+    request.origin.as_ref().is_some_and(|existing| {
+        existing.source == origin.source && existing.operation == origin.operation
+    })
+}
+
+/// Appends the imported requests, skipping operations that were already imported from the
+/// same spec and requests that are already saved.
+pub fn merge_requests(
+    saved_requests: &[HttpRequest],
+    imported_requests: &[HttpRequest],
+) -> Vec<HttpRequest> {
+    let mut merged_requests = saved_requests.to_vec();
+
+    for imported_request in imported_requests {
+        let already_imported = imported_request.origin.as_ref().is_some_and(|origin| {
+            merged_requests
+                .iter()
+                .any(|request| is_same_origin(request, origin))
+        });
+        if !already_imported && !merged_requests.contains(imported_request) {
+            merged_requests.push(HttpRequest {
+                method: imported_request.method.clone(),
+                url: imported_request.url.clone(),
+                headers: imported_request.headers.clone(),
+                body_type: imported_request.body_type.clone(),
+                body: imported_request.body.clone(),
+                auth: imported_request.auth.clone(),
+                extract_variables: None,
+                origin: imported_request.origin.clone(),
+                ..Default::default()
+            });
+        }
+    }
+
+    merged_requests
+}
+
+#[derive(Default)]
+pub struct SyncSummary {
+    pub added: Vec<String>,
+    /// Updated operations, with the locally edited fields that were kept.
+    pub updated: Vec<(String, Vec<&'static str>)>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl SyncSummary {
+    pub fn print(&self) {
+        print_line(&format!(
+            "{} added, {} updated, {} removed, {} unchanged",
+            self.added.len(),
+            self.updated.len(),
+            self.removed.len(),
+            self.unchanged
+        ));
+        for operation in &self.added {
+            println!("\x1b[32m+ {}\x1b[0m", operation);
+        }
+        for (operation, kept) in &self.updated {
+            if kept.is_empty() {
+                println!("\x1b[33m~ {}\x1b[0m", operation);
+            } else {
+                println!(
+                    "\x1b[33m~ {}\x1b[0m (kept local {})",
+                    operation,
+                    kept.join(", ")
+                );
+            }
+        }
+        for operation in &self.removed {
+            println!(
+                "\x1b[31m- {}\x1b[0m (no longer in the spec, flagged as removed)",
+                operation
+            );
+        }
+    }
+}
+
+/// Splits a url, without its query, into its origin and path. A leading `{{variable}}` is
+/// replaced by its value when it is set, else it is kept as the origin.
+fn split_origin(url: &str, variables: &[GlobalVariable]) -> (String, String) {
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let url = match url
+        .strip_prefix("{{")
+        .and_then(|rest| rest.split_once("}}"))
+    {
+        Some((name, rest)) => match variables.iter().find(|variable| variable.key == name) {
+            Some(variable) => format!("{}{}", variable.value.trim_end_matches('/'), rest),
+            None => return (format!("{{{{{}}}}}", name), rest.to_string()),
+        },
+        None => url.to_string(),
+    };
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            (
+                format!("{}://{}", scheme, host).to_lowercase(),
+                path.to_string(),
+            )
+        }
+        None => (String::new(), url),
+    }
+}
+
+/// Whether a request without an origin, such as one imported before origins were recorded,
+/// is the imported operation: the methods and the origins of the urls are the same, resolving
+/// a base url variable, and so is the path but for the `{{parameter}}` segments of the operation.
+fn matches_operation(
+    request: &HttpRequest,
+    imported: &HttpRequest,
+    variables: &[GlobalVariable],
+) -> bool {
+    if !request.method.eq_ignore_ascii_case(&imported.method) {
+        return false;
+    }
+    let (origin, path) = split_origin(&request.url, variables);
+    let (imported_origin, template) = split_origin(&imported.url, variables);
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let template: Vec<&str> = template.trim_matches('/').split('/').collect();
+    origin == imported_origin
+        && path.len() == template.len()
+        && path
+            .iter()
+            .zip(&template)
+            .all(|(segment, template)| segment == template || template.contains("{{"))
+}
+
+/// Applies a field of the spec unless it was edited locally since the last import.
+/// Returns whether a change of the spec was skipped because of a local edit.
+fn sync_field<T: PartialEq + Clone>(local: &mut T, last_imported: &T, imported: &T) -> bool {
+    if local == last_imported {
+        *local = imported.clone();
+        false
+    } else {
+        last_imported != imported
+    }
+}
+
+/// Updates the requests imported from the same spec. Fields that were edited locally are
+/// kept, operations missing from the spec are flagged as removed and new ones are appended.
+/// A request without an origin that matches a new operation is linked to it rather than
+/// duplicated, keeping all of its fields. `variables` resolve the base urls when matching.
+pub fn sync_requests(
+    saved_requests: &[HttpRequest],
+    imported_requests: &[HttpRequest],
+    source: &str,
+    variables: &[GlobalVariable],
+) -> (Vec<HttpRequest>, SyncSummary) {
+    let mut synced_requests = saved_requests.to_vec();
+    let mut summary = SyncSummary::default();
+
+    for request in synced_requests.iter_mut() {
+        let Some(origin) = request
+            .origin
+            .as_mut()
+            .filter(|origin| origin.source == source)
+        else {
+            continue;
+        };
+        let imported = imported_requests
+            .iter()
+            .filter_map(|imported| imported.origin.as_ref())
+            .find(|imported| imported.operation == origin.operation);
+        let Some(imported) = imported else {
+            if !origin.removed {
+                origin.removed = true;
+                summary.removed.push(origin.operation.clone());
+            }
+            continue;
+        };
+        let last = origin.imported.clone();
+        let new = imported.imported.clone();
+        if last == new && !origin.removed {
+            summary.unchanged += 1;
+            continue;
+        }
+        let mut kept = Vec::new();
+        if sync_field(&mut request.method, &last.method, &new.method) {
+            kept.push("method");
+        }
+        if sync_field(&mut request.url, &last.url, &new.url) {
+            kept.push("url");
+        }
+        if sync_field(&mut request.headers, &last.headers, &new.headers) {
+            kept.push("headers");
+        }
+        if sync_field(&mut request.body_type, &last.body_type, &new.body_type) {
+            kept.push("body type");
+        }
+        if sync_field(&mut request.body, &last.body, &new.body) {
+            kept.push("body");
+        }
+        if sync_field(&mut request.auth, &last.auth, &new.auth) {
+            kept.push("auth");
+        }
+        let origin = request.origin.as_mut().unwrap();
+        origin.removed = false;
+        origin.imported = new;
+        summary.updated.push((origin.operation.clone(), kept));
+    }
+
+    for imported in imported_requests {
+        let Some(origin) = &imported.origin else {
+            continue;
+        };
+        if synced_requests
+            .iter()
+            .any(|request| is_same_origin(request, origin))
+        {
+            continue;
+        }
+        let unlinked = synced_requests.iter_mut().find(|request| {
+            request.origin.is_none() && matches_operation(request, imported, variables)
+        });
+        let Some(request) = unlinked else {
+            summary.added.push(origin.operation.clone());
+            synced_requests.push(imported.clone());
+            continue;
+        };
+        let new = &origin.imported;
+        let kept = [
+            ("method", request.method != new.method),
+            ("url", request.url != new.url),
+            ("headers", request.headers != new.headers),
+            ("body type", request.body_type != new.body_type),
+            ("body", request.body != new.body),
+            ("auth", request.auth != new.auth),
+        ]
+        .into_iter()
+        .filter_map(|(field, differs)| differs.then_some(field))
+        .collect();
+        request.origin = Some(origin.clone());
+        summary.updated.push((origin.operation.clone(), kept));
+    }
+
+    (synced_requests, summary)
+}
+
+fn yaml_to_json(value: serde_yaml::Value) -> Value {
+    match value {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(value) => Value::Bool(value),
+        serde_yaml::Value::Number(number) => {
+            if let Some(number) = number.as_i64() {
+                json!(number)
+            } else if let Some(number) = number.as_u64() {
+                json!(number)
+            } else {
+                json!(number.as_f64())
+            }
+        }
+        serde_yaml::Value::String(text) => Value::String(text),
+        serde_yaml::Value::Sequence(items) => {
+            Value::Array(items.into_iter().map(yaml_to_json).collect())
+        }
+        // YAML allows keys such as `200:` that aren't strings.
+        serde_yaml::Value::Mapping(mapping) => Value::Object(
+            mapping
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match yaml_to_json(key) {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    (key, yaml_to_json(value))
+                })
+                .collect(),
+        ),
+        serde_yaml::Value::Tagged(tagged) => yaml_to_json(tagged.value),
+    }
+}
+
+/// Parses a JSON or YAML spec, converting Swagger 2.0 to the OpenAPI 3 layout.
+pub fn parse_spec(text: &str) -> Result<OpenAPI, String> {
+    let spec: Value = match serde_json::from_str(text) {
+        Ok(spec) => spec,
+        Err(_) => serde_yaml::from_str::<serde_yaml::Value>(text)
+            .map(yaml_to_json)
+            .map_err(|err| format!("The spec is neither valid JSON nor YAML: {}", err))?,
+    };
+    let spec = if swagger::is_swagger(&spec) {
+        swagger::convert_to_openapi(spec)
+    } else {
+        spec
+    };
+    let spec: OpenAPI =
+        serde_json::from_value(spec).map_err(|err| format!("Failed to read the spec: {}", err))?;
+    if !spec.openapi.starts_with("3.") {
+        return Err(format!("Unsupported OpenAPI version {}", spec.openapi));
+    }
+    Ok(spec)
+}
+
+const STDIN_ORIGIN: &str = "stdin";
+
+/// A spec read from stdin, a file or a url.
+struct SpecSource {
+    text: String,
+    /// The url the spec was fetched from.
+    url: Option<String>,
+    /// The absolute path or url of the spec, recorded as the origin of imported requests.
+    origin: String,
+}
+
+/// Reads the spec from stdin when the source is `-`, from a local file if one exists at
+/// that path or it starts like a path, or else from the url.
+async fn read_spec_source(source: &str) -> Result<SpecSource, String> {
+    if source == "-" {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|err| format!("Failed to read the spec from stdin: {}", err))?;
+        return Ok(SpecSource {
+            text,
+            url: None,
+            origin: STDIN_ORIGIN.to_string(),
+        });
+    }
+    let looks_like_path = !source.contains("://") && source.starts_with(['/', '.', '~']);
+    if looks_like_path || Path::new(source).is_file() {
+        let text = fs::read_to_string(source)
+            .map_err(|err| format!("Failed to read {}: {}", source, err))?;
+        let origin = fs::canonicalize(source)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| source.to_string());
+        return Ok(SpecSource {
+            text,
+            url: None,
+            origin,
+        });
+    }
+    let spec_url = get_url_with_https(source);
+    let response = reqwest::get(&spec_url)
+        .await
+        .map_err(|err| format!("Failed to fetch {}: {}", spec_url, err))?;
+    let text = response
+        .text()
+        .await
+        .map_err(|err| format!("Failed to fetch {}: {}", spec_url, err))?;
+    Ok(SpecSource {
+        text,
+        url: Some(spec_url.clone()),
+        origin: spec_url,
+    })
+}
+
+fn is_absolute_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// Replaces the `{variable}`s of the server url with their defaults.
+fn resolve_server_url(server: &Server) -> String {
+    let mut url = server.url.clone();
+    for (name, variable) in server.variables.iter().flatten() {
+        url = url.replace(&format!("{{{}}}", name), &variable.default);
+    }
+    url.trim_end_matches('/').to_string()
+}
+
+/// Resolves the chosen server (1-based, the first by default), relative urls being resolved
+/// against the host the spec was fetched from. Without servers, that host is used.
+fn get_base_url(
+    spec: &OpenAPI,
+    spec_url: Option<&str>,
+    server: Option<usize>,
+) -> Result<String, String> {
+    let origin = spec_url
+        .map(|url| url.split('/').take(3).collect::<Vec<&str>>().join("/"))
+        .unwrap_or_default();
+    let server_url = match server {
+        Some(number) => spec
+            .servers
+            .get(number.wrapping_sub(1))
+            .map(resolve_server_url)
+            .ok_or_else(|| format!("The spec has no server {}.", number))?,
+        None => spec
+            .servers
+            .first()
+            .map(resolve_server_url)
+            .unwrap_or_default(),
+    };
+    if is_absolute_url(&server_url) {
+        Ok(server_url)
+    } else {
+        Ok(format!("{}{}", origin, server_url))
+    }
+}
+
+fn print_servers(servers: &[Server]) {
+    print_line("Servers:");
+    for (index, server) in servers.iter().enumerate() {
+        let description = server
+            .description
+            .as_ref()
+            .map(|description| format!(" ({})", description))
+            .unwrap_or_default();
+        println!("{}: {}{}", index + 1, server.url, description);
+        for (name, variable) in server.variables.iter().flatten() {
+            let values = variable
+                .enum_values
+                .as_ref()
+                .map(|values| format!(", one of {}", values.join(", ")))
+                .unwrap_or_default();
+            println!("   {{{}}} = {}{}", name, variable.default, values);
+        }
+    }
+}
+
+/// The alphanumeric words of the text in camelCase: `Swagger Petstore` is `swaggerPetstore`.
+fn camel_case(text: &str) -> String {
+    text.split(|char: char| !char.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .enumerate()
+        .map(|(index, word)| {
+            let word = word.to_lowercase();
+            match index {
+                0 => word,
+                _ => word[..1].to_uppercase() + &word[1..],
+            }
+        })
+        .collect()
+}
+
+/// The global variable holding the server of the spec, named after its title so that specs
+/// of different APIs don't share one: `Swagger Petstore` uses `swaggerPetstoreBaseUrl`.
+fn get_base_url_variable(spec: &OpenAPI) -> String {
+    match camel_case(&spec.info.title).as_str() {
+        "" => BASE_URL_VARIABLE.to_string(),
+        title => format!("{}BaseUrl", title),
+    }
+}
+
+/// Imports the spec with every url starting with the base url variable of the spec, and
+/// stores the chosen server in it. An existing value is only replaced when a server is
+/// chosen with `--server`. With `sync`, requests imported earlier from
+/// the same spec are updated instead of only adding the new operations.
+pub async fn handle_open_api_sepc_import(
+    source: &str,
+    server: Option<usize>,
+    sync: bool,
+) -> Result<(), String> {
+    let mut spec_source = read_spec_source(source).await?;
+    let spec = parse_spec(&spec_source.text)?;
+    // Specs piped in are told apart by their title.
+    if spec_source.origin == STDIN_ORIGIN {
+        if spec.info.title.is_empty() {
+            return Err("A spec read from stdin needs an info.title to tell its requests apart from those of other specs.".to_string());
+        }
+        spec_source.origin = format!("{}:{}", STDIN_ORIGIN, spec.info.title);
+    }
+    if spec.servers.len() > 1 {
+        print_servers(&spec.servers);
+    }
+    let base_url = get_base_url(&spec, spec_source.url.as_deref(), server)?;
+    let base_url_variable = get_base_url_variable(&spec);
+    let global_variables = get_global_variables();
+    let existing_base_url = global_variables
+        .iter()
+        .find(|variable| variable.key == base_url_variable);
+    match existing_base_url {
+        Some(existing) if server.is_none() && existing.value != base_url => {
+            print_line(&format!(
+                "Keeping {{{{{}}}}} = {}, the spec uses {} (pass --server to replace it).",
+                base_url_variable, existing.value, base_url
+            ));
+        }
+        _ if base_url.is_empty() => {
+            print_line(&format!(
+                "The spec has no server url, set one with `xhtp ga {} <url>`.",
+                base_url_variable
+            ));
+        }
+        _ => {
+            save_to_global_variables(base_url_variable.clone(), base_url.clone());
+            print_line(&format!("{{{{{}}}}} = {}", base_url_variable, base_url));
+        }
+    }
+    let (mut imported_requests, examples) =
+        map_open_api_spec_to_http_requests(&format!("{{{{{}}}}}", base_url_variable), spec);
+    for origin in imported_requests
+        .iter_mut()
+        .filter_map(|request| request.origin.as_mut())
+    {
+        origin.source = spec_source.origin.clone();
+    }
+    for example in examples {
+        if !global_variables
+            .iter()
+            .any(|variable| variable.key == example.key)
+        {
+            save_to_global_variables(example.key, example.value);
+        }
+    }
+    let saved_requests = read_http_request_file();
+
+    if sync {
+        let (synced_requests, summary) = sync_requests(
+            &saved_requests,
+            &imported_requests,
+            &spec_source.origin,
+            &get_global_variables(),
+        );
+        write_http_request_file(&synced_requests);
+        summary.print();
+        return Ok(());
+    }
+
+    let merged_requests = merge_requests(&saved_requests, &imported_requests);
+    let added = merged_requests.len() - saved_requests.len();
+    let skipped = imported_requests.len() - added;
+
+    write_http_request_file(&merged_requests);
+    match skipped {
+        0 => print_line(&format!("Imported {} requests from {}", added, source)),
+        _ => print_line(&format!(
+            "Imported {} requests from {}, {} were already imported (use `xhtp sync {}` to update them).",
+            added, source, skipped, source
+        )),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, url: &str) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn imported(method: &str, url: &str, source: &str, operation: &str) -> HttpRequest {
+        HttpRequest {
+            origin: Some(RequestOrigin {
+                source: source.to_string(),
+                operation: operation.to_string(),
+                imported: ImportedRequest {
+                    method: method.to_string(),
+                    url: url.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            ..request(method, url)
+        }
+    }
+
+    fn base_url(value: &str) -> Vec<GlobalVariable> {
+        vec![GlobalVariable {
+            key: "petsBaseUrl".to_string(),
+            value: value.to_string(),
+        }]
+    }
+
+    #[test]
+    fn matches_operations_on_the_origin_and_the_whole_path() {
+        let variables = base_url("https://pets.example.com/v1/");
+        let operation = request("GET", "{{petsBaseUrl}}/pets/{{petId}}");
+        let matches = |method: &str, url: &str| {
+            matches_operation(&request(method, url), &operation, &variables)
+        };
+        assert!(matches("GET", "{{petsBaseUrl}}/pets/7?full=1"));
+        assert!(matches("get", "https://PETS.example.com/v1/pets/{{id}}"));
+        assert!(!matches("DELETE", "{{petsBaseUrl}}/pets/7"));
+        assert!(!matches("GET", "https://other.example.com/v1/pets/7"));
+        assert!(!matches("GET", "{{baseUrl}}/pets/7"));
+        assert!(!matches("GET", "https://pets.example.com/v2/pets/7"));
+        assert!(!matches("GET", "{{petsBaseUrl}}/pets/7/toys"));
+
+        let root = request("GET", "{{petsBaseUrl}}/");
+        assert!(!matches_operation(
+            &request("GET", "{{petsBaseUrl}}/pets"),
+            &root,
+            &variables
+        ));
+    }
+
+    #[test]
+    fn keeps_unset_base_url_variables_as_the_origin() {
+        let operation = request("GET", "{{petsBaseUrl}}/pets");
+        assert!(matches_operation(
+            &request("GET", "{{petsBaseUrl}}/pets"),
+            &operation,
+            &[]
+        ));
+        assert!(!matches_operation(
+            &request("GET", "{{baseUrl}}/pets"),
+            &operation,
+            &[]
+        ));
+    }
+
+    #[test]
+    fn links_requests_without_an_origin_of_the_same_api_only() {
+        let saved = vec![
+            request("GET", "{{petsBaseUrl}}/pets"),
+            request("GET", "https://shop.example.com/pets"),
+            imported(
+                "GET",
+                "{{petsBaseUrl}}/owners",
+                "stdin:Owners",
+                "listOwners",
+            ),
+        ];
+        let spec = vec![imported(
+            "GET",
+            "{{petsBaseUrl}}/pets",
+            "stdin:Pets",
+            "listPets",
+        )];
+        let (synced, summary) = sync_requests(
+            &saved,
+            &spec,
+            "stdin:Pets",
+            &base_url("https://pets.example.com"),
+        );
+        assert_eq!(synced.len(), 3);
+        assert_eq!(
+            synced[0]
+                .origin
+                .as_ref()
+                .map(|origin| origin.source.as_str()),
+            Some("stdin:Pets")
+        );
+        assert_eq!(synced[1].origin, None);
+        // Requests of another spec read from stdin are left alone.
+        assert!(!synced[2].origin.as_ref().unwrap().removed);
+        assert_eq!(summary.updated.len(), 1);
+        assert!(summary.added.is_empty() && summary.removed.is_empty());
+    }
+
+    #[test]
+    fn names_the_base_url_variable_after_the_spec_title() {
+        let variable = |title: &str| {
+            let spec = json!({ "openapi": "3.0.0", "info": { "title": title }, "paths": {} });
+            get_base_url_variable(&parse_spec(&spec.to_string()).unwrap())
+        };
+        assert_eq!(variable("Swagger Petstore"), "swaggerPetstoreBaseUrl");
+        assert_eq!(variable("GitHub v3 REST API"), "githubV3RestApiBaseUrl");
+        assert_eq!(variable(" -- "), "baseUrl");
+        assert_eq!(variable(""), "baseUrl");
+    }
+
+    #[test]
+    fn maps_parameters_to_variables_of_the_operation() {
+        let spec = json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Pets" },
+            "paths": {
+                "/pets/{id}": {
+                    "parameters": [{ "name": "id", "in": "path", "required": true, "example": 7 }],
+                    "get": {
+                        "operationId": "getPet",
+                        "parameters": [
+                            { "name": "fields", "in": "query", "schema": { "type": "string" } },
+                            { "name": "limit", "in": "query", "schema": { "default": 10 } },
+                            { "name": "X-Trace", "in": "header", "schema": { "type": "string" } },
+                            { "name": "X-Tenant", "in": "header", "required": true },
+                            { "name": "session", "in": "cookie", "example": "abc" }
+                        ]
+                    },
+                    "delete": {}
+                }
+            }
+        });
+        let (requests, examples) = map_open_api_spec_to_http_requests(
+            "{{petsBaseUrl}}",
+            parse_spec(&spec.to_string()).unwrap(),
+        );
+
+        let get = requests
+            .iter()
+            .find(|request| request.method == "GET")
+            .unwrap();
+        assert_eq!(get.name.as_deref(), Some("getPet"));
+        assert_eq!(
+            get.url,
+            "{{petsBaseUrl}}/pets/{{getPet.id}}?limit={{getPet.limit}}"
+        );
+        assert_eq!(
+            get.headers,
+            vec![
+                "X-Tenant: {{getPet.X-Tenant}}",
+                "Cookie: session={{getPet.session}}"
+            ]
+        );
+
+        let delete = requests
+            .iter()
+            .find(|request| request.method == "DELETE")
+            .unwrap();
+        assert_eq!(delete.name, None);
+        assert_eq!(delete.url, "{{petsBaseUrl}}/pets/{{deletePetsId.id}}");
+
+        let examples: Vec<(&str, &str)> = examples
+            .iter()
+            .map(|variable| (variable.key.as_str(), variable.value.as_str()))
+            .collect();
+        assert_eq!(
+            examples,
+            vec![
+                ("getPet.id", "7"),
+                ("getPet.limit", "10"),
+                ("getPet.session", "abc"),
+                ("deletePetsId.id", "7")
+            ]
+        );
+    }
+}
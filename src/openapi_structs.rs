@@ -1,62 +1,168 @@
 use serde::Deserialize;
+use serde_json::Value;
 use std::collections::BTreeMap;
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Operation {
-    pub summary: Option<String>,
-    pub description: Option<String>,
     pub operation_id: Option<String>,
     pub parameters: Option<Vec<Parameter>>,
     pub request_body: Option<RequestBody>,
-    pub responses: BTreeMap<String, Response>,
+    pub servers: Option<Vec<Server>>,
+    /// Replaces the spec level requirements, an empty list means no authentication.
+    pub security: Option<Vec<SecurityRequirement>>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Names of security schemes that must all be used, with their oauth2 scopes.
+pub type SecurityRequirement = BTreeMap<String, Vec<String>>;
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityScheme {
+    /// One of http, apiKey, oauth2, openIdConnect or mutualTLS.
+    #[serde(rename = "type")]
+    pub scheme_type: String,
+    /// The http authentication scheme, e.g. bearer or basic.
+    pub scheme: Option<String>,
+    /// The header, query or cookie name of an apiKey.
+    pub name: Option<String>,
+    #[serde(rename = "in")]
+    pub location: Option<String>,
+    /// The grants of an oauth2 scheme.
+    pub flows: Option<OAuthFlows>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthFlows {
+    pub client_credentials: Option<OAuthFlow>,
+    pub authorization_code: Option<OAuthFlow>,
+    pub password: Option<OAuthFlow>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthFlow {
+    pub authorization_url: Option<String>,
+    pub token_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct Parameter {
+    /// Set instead of the other fields when the parameter is defined in `components/parameters`.
+    #[serde(rename = "$ref")]
+    pub reference: Option<String>,
+    #[serde(default)]
     pub name: String,
-    pub description: Option<String>,
+    /// One of path, query, header or cookie.
+    #[serde(rename = "in", default)]
+    pub location: String,
     pub required: Option<bool>,
     pub schema: Option<Schema>,
+    pub example: Option<Value>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct RequestBody {
-    pub description: Option<String>,
+    #[serde(rename = "$ref")]
+    pub reference: Option<String>,
+    #[serde(default)]
     pub content: BTreeMap<String, MediaType>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct MediaType {
     pub schema: Option<Schema>,
+    pub example: Option<Value>,
+    pub examples: Option<BTreeMap<String, Example>>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Response {
-    pub description: String,
+#[derive(Debug, Deserialize, Clone)]
+pub struct Example {
+    #[serde(rename = "$ref")]
+    pub reference: Option<String>,
+    pub value: Option<Value>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Schema {}
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Schema {
+    #[serde(rename = "$ref")]
+    pub reference: Option<String>,
+    /// A single type, or a list of types in OpenAPI 3.1.
+    #[serde(rename = "type")]
+    pub schema_type: Option<Value>,
+    pub format: Option<String>,
+    pub default: Option<Value>,
+    pub example: Option<Value>,
+    /// A list of examples in OpenAPI 3.1.
+    pub examples: Option<Vec<Value>>,
+    #[serde(rename = "enum")]
+    pub enum_values: Option<Vec<Value>>,
+    pub properties: Option<BTreeMap<String, Schema>>,
+    pub required: Option<Vec<String>>,
+    pub items: Option<Box<Schema>>,
+    pub all_of: Option<Vec<Schema>>,
+    pub one_of: Option<Vec<Schema>>,
+    pub any_of: Option<Vec<Schema>>,
+}
 
 #[derive(Debug, Deserialize)]
 pub struct PathItem {
-    pub summary: Option<String>,
-    pub description: Option<String>,
     pub get: Option<Operation>,
     pub put: Option<Operation>,
     pub post: Option<Operation>,
     pub delete: Option<Operation>,
+    pub patch: Option<Operation>,
+    pub head: Option<Operation>,
+    pub options: Option<Operation>,
+    pub trace: Option<Operation>,
+    /// Parameters shared by every operation of the path, operations can override them.
+    pub parameters: Option<Vec<Parameter>>,
+    pub servers: Option<Vec<Server>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Components {
+    pub schemas: BTreeMap<String, Schema>,
+    pub parameters: BTreeMap<String, Parameter>,
+    pub request_bodies: BTreeMap<String, RequestBody>,
+    pub examples: BTreeMap<String, Example>,
+    pub security_schemes: BTreeMap<String, SecurityScheme>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
 pub struct Info {
+    #[serde(default)]
     pub title: String,
-    pub version: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Server {
+    pub url: String,
+    pub description: Option<String>,
+    pub variables: Option<BTreeMap<String, ServerVariable>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ServerVariable {
+    pub default: String,
+    #[serde(rename = "enum")]
+    pub enum_values: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct OpenAPI {
+    /// The OpenAPI version, Swagger 2.0 specs are converted to 3.0 first.
     pub openapi: String,
+    #[serde(default)]
     pub info: Info,
+    #[serde(default)]
+    pub servers: Vec<Server>,
     pub paths: BTreeMap<String, PathItem>,
+    #[serde(default)]
+    pub components: Components,
+    #[serde(default)]
+    pub security: Vec<SecurityRequirement>,
 }
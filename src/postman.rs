@@ -0,0 +1,865 @@
+//! Imports and exports Postman collections (v2.1) and environments.
+
+use crate::history;
+use crate::import::{self, Imported};
+use crate::structs::{
+    ApiKeyLocation, Auth, ClientAuthentication, GlobalVariable, HttpRequest, OAuth2, OAuth2Grant,
+};
+use crate::utils::get_global_variables;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::fs;
+
+const SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+fn get_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+fn get_array<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// Headers, form fields and variables can be switched off in Postman.
+fn is_enabled(item: &Value) -> bool {
+    item.get("disabled").and_then(Value::as_bool) != Some(true)
+        && item.get("enabled").and_then(Value::as_bool) != Some(false)
+}
+
+fn get_variables(items: &Value, key: &str) -> Vec<GlobalVariable> {
+    get_array(items, key)
+        .filter(|variable| is_enabled(variable))
+        .filter_map(|variable| {
+            Some(GlobalVariable {
+                key: get_str(variable, "key")?.to_string(),
+                value: variable
+                    .get("value")
+                    .map(crate::utils::json_value_to_variable)
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Auth parameters are a list of `{ key, value }` in v2.1 and an object in v2.0.
+fn get_auth_parameter(auth: &Value, auth_type: &str, key: &str) -> Option<String> {
+    let parameters = auth.get(auth_type)?;
+    let value = match parameters {
+        Value::Array(items) => items
+            .iter()
+            .find(|item| get_str(item, "key") == Some(key))?
+            .get("value")?,
+        parameters => parameters.get(key)?,
+    };
+    Some(crate::utils::json_value_to_variable(value))
+}
+
+fn convert_auth(auth: &Value, context: &str, imported: &mut Imported) -> Option<Auth> {
+    let auth_type = get_str(auth, "type").unwrap_or("noauth");
+    let parameter = |key: &str| get_auth_parameter(auth, auth_type, key).unwrap_or_default();
+    match auth_type {
+        "noauth" => None,
+        "bearer" => Some(Auth::Bearer {
+            token: parameter("token"),
+        }),
+        "basic" => Some(Auth::Basic {
+            username: parameter("username"),
+            password: parameter("password"),
+        }),
+        "digest" => Some(Auth::Digest {
+            username: parameter("username"),
+            password: parameter("password"),
+        }),
+        "apikey" => Some(Auth::ApiKey {
+            name: parameter("key"),
+            value: parameter("value"),
+            location: match parameter("in").as_str() {
+                "query" => ApiKeyLocation::Query,
+                _ => ApiKeyLocation::Header,
+            },
+        }),
+        "oauth2" => {
+            let grant = import::get_oauth2_grant(&parameter("grant_type"));
+            let token_url = parameter("accessTokenUrl");
+            if let (Some(grant), false) = (grant, token_url.is_empty()) {
+                return Some(Auth::OAuth2(OAuth2 {
+                    grant,
+                    token_url,
+                    client_id: parameter("clientId"),
+                    client_secret: parameter("clientSecret"),
+                    scope: parameter("scope"),
+                    username: parameter("username"),
+                    password: parameter("password"),
+                    refresh_token: parameter("refreshToken"),
+                    auth_url: parameter("authUrl"),
+                    redirect_uri: parameter("redirect_uri"),
+                    client_authentication: match parameter("client_authentication").as_str() {
+                        "body" => ClientAuthentication::Body,
+                        _ => ClientAuthentication::Basic,
+                    },
+                    ..Default::default()
+                }));
+            }
+            let token = get_auth_parameter(auth, auth_type, "accessToken")
+                .filter(|token| !token.is_empty())
+                .unwrap_or_else(|| "{{access_token}}".to_string());
+            Some(Auth::Bearer { token })
+        }
+        "awsv4" => Some(Auth::AwsSigv4 {
+            access_key_id: parameter("accessKey"),
+            secret_access_key: parameter("secretKey"),
+            session_token: parameter("sessionToken"),
+            region: parameter("region"),
+            service: parameter("service"),
+        }),
+        auth_type => {
+            imported.warn(context, &format!("{} auth is not supported", auth_type));
+            None
+        }
+    }
+}
+
+/// Path variables such as `:id` become `{{id}}`, and their values are saved as variables.
+fn convert_url(url: &Value, imported: &mut Imported) -> String {
+    let raw = match url {
+        Value::String(raw) => raw.clone(),
+        url => match get_str(url, "raw") {
+            Some(raw) => raw.to_string(),
+            None => {
+                let host = match url.get("host") {
+                    Some(Value::Array(parts)) => parts
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<&str>>()
+                        .join("."),
+                    Some(Value::String(host)) => host.clone(),
+                    _ => String::new(),
+                };
+                let path: Vec<&str> = get_array(url, "path").filter_map(Value::as_str).collect();
+                let query: Vec<String> = get_array(url, "query")
+                    .filter(|parameter| is_enabled(parameter))
+                    .map(|parameter| {
+                        format!(
+                            "{}={}",
+                            get_str(parameter, "key").unwrap_or_default(),
+                            get_str(parameter, "value").unwrap_or_default()
+                        )
+                    })
+                    .collect();
+                let mut raw = match get_str(url, "protocol") {
+                    Some(protocol) => format!("{}://{}", protocol, host),
+                    None => host,
+                };
+                if !path.is_empty() {
+                    raw = format!("{}/{}", raw, path.join("/"));
+                }
+                if !query.is_empty() {
+                    raw = format!("{}?{}", raw, query.join("&"));
+                }
+                raw
+            }
+        },
+    };
+    let mut converted = raw;
+    for variable in get_array(url, "variable") {
+        let Some(key) = get_str(variable, "key") else {
+            continue;
+        };
+        let path_variable = Regex::new(&format!(r"/:{}(/|\?|#|$)", regex::escape(key))).unwrap();
+        converted = path_variable
+            .replace_all(&converted, format!("/{{{{{}}}}}$1", key))
+            .to_string();
+        if let Some(value) = get_str(variable, "value").filter(|value| !value.is_empty()) {
+            imported.variables.push(GlobalVariable {
+                key: key.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+    converted
+}
+
+fn has_json_content_type(headers: &[String]) -> bool {
+    headers.iter().any(|header| {
+        header.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("content-type") && value.contains("json")
+        })
+    })
+}
+
+fn get_fields(fields: &Value, key: &str, context: &str, imported: &mut Imported) -> Value {
+    let mut converted = Map::new();
+    for field in get_array(fields, key).filter(|field| is_enabled(field)) {
+        let Some(name) = get_str(field, "key") else {
+            continue;
+        };
+        let value = if get_str(field, "type") == Some("file") {
+            match field.get("src") {
+                Some(Value::String(src)) => format!("@{}", src),
+                Some(Value::Array(sources)) if sources.len() == 1 => {
+                    format!("@{}", sources[0].as_str().unwrap_or_default())
+                }
+                _ => {
+                    imported.warn(context, &format!("the file of the form field {}", name));
+                    continue;
+                }
+            }
+        } else {
+            get_str(field, "value").unwrap_or_default().to_string()
+        };
+        converted.insert(name.to_string(), Value::String(value));
+    }
+    Value::Object(converted)
+}
+
+/// Maps the body modes to a body type: JSON raw bodies become `json`, `urlencoded` becomes
+/// `form`, `formdata` becomes `multipart`, GraphQL a `json` body and other raw bodies `text`.
+fn convert_body(
+    body: &Value,
+    headers: &mut Vec<String>,
+    context: &str,
+    imported: &mut Imported,
+) -> (Option<String>, Option<Value>) {
+    match get_str(body, "mode") {
+        Some("raw") => {
+            let raw = get_str(body, "raw").unwrap_or_default();
+            if raw.is_empty() {
+                return (None, None);
+            }
+            let language = body
+                .get("options")
+                .and_then(|options| options.get("raw"))
+                .and_then(|raw| get_str(raw, "language"));
+            let is_json = language == Some("json") || has_json_content_type(headers);
+            match serde_json::from_str::<Value>(raw) {
+                Ok(json) if is_json => (Some("json".to_string()), Some(json)),
+                _ => {
+                    if is_json && !has_json_content_type(headers) {
+                        headers.push("Content-Type: application/json".to_string());
+                    }
+                    (
+                        Some("text".to_string()),
+                        Some(Value::String(raw.to_string())),
+                    )
+                }
+            }
+        }
+        Some("urlencoded") => (
+            Some("form".to_string()),
+            Some(get_fields(body, "urlencoded", context, imported)),
+        ),
+        Some("formdata") => (
+            Some("multipart".to_string()),
+            Some(get_fields(body, "formdata", context, imported)),
+        ),
+        Some("graphql") => {
+            let graphql = body.get("graphql").cloned().unwrap_or(json!({}));
+            let variables = match graphql.get("variables") {
+                Some(Value::String(variables)) => {
+                    serde_json::from_str(variables).unwrap_or(Value::Null)
+                }
+                Some(variables) => variables.clone(),
+                None => Value::Null,
+            };
+            let mut query = json!({ "query": get_str(&graphql, "query").unwrap_or_default() });
+            if !variables.is_null() {
+                query["variables"] = variables;
+            }
+            (Some("json".to_string()), Some(query))
+        }
+        Some(mode) => {
+            imported.warn(context, &format!("{} bodies are not supported", mode));
+            (None, None)
+        }
+        None => (None, None),
+    }
+}
+
+fn has_scripts(item: &Value) -> bool {
+    get_array(item, "event").any(|event| {
+        event
+            .get("script")
+            .and_then(|script| script.get("exec"))
+            .is_some_and(|exec| match exec {
+                Value::Array(lines) => lines
+                    .iter()
+                    .any(|line| !line.as_str().unwrap_or_default().trim().is_empty()),
+                Value::String(exec) => !exec.trim().is_empty(),
+                _ => false,
+            })
+    })
+}
+
+fn convert_request(
+    item: &Value,
+    folder: &Option<String>,
+    auth: Option<&Value>,
+    imported: &mut Imported,
+) {
+    let name = get_str(item, "name").unwrap_or("request").to_string();
+    let context = match folder {
+        Some(folder) => format!("{}/{}", folder, name),
+        None => name.clone(),
+    };
+    let request = &item["request"];
+    let (method, url) = match request {
+        Value::String(url) => ("GET".to_string(), url.clone()),
+        request => (
+            get_str(request, "method").unwrap_or("GET").to_uppercase(),
+            request
+                .get("url")
+                .map(|url| convert_url(url, imported))
+                .unwrap_or_default(),
+        ),
+    };
+    let mut headers: Vec<String> = get_array(request, "header")
+        .filter(|header| is_enabled(header))
+        .filter_map(|header| {
+            Some(format!(
+                "{}: {}",
+                get_str(header, "key")?,
+                get_str(header, "value").unwrap_or_default()
+            ))
+        })
+        .collect();
+    let auth = request
+        .get("auth")
+        .or(auth)
+        .and_then(|auth| convert_auth(auth, &context, imported));
+    let (body_type, body) = match request.get("body") {
+        Some(body) => convert_body(body, &mut headers, &context, imported),
+        None => (None, None),
+    };
+    if has_scripts(item) {
+        imported.warn(&context, "pre-request and test scripts");
+    }
+    imported.requests.push(HttpRequest {
+        name: Some(name),
+        folder: folder.clone(),
+        method,
+        url,
+        headers,
+        body_type,
+        body,
+        auth,
+        ..Default::default()
+    });
+}
+
+/// Folders are nested into `parent/child` folder names and pass their auth to their requests.
+fn convert_items(
+    parent: &Value,
+    folder: Option<String>,
+    auth: Option<&Value>,
+    imported: &mut Imported,
+) {
+    for item in get_array(parent, "item") {
+        let auth = item.get("auth").or(auth);
+        if item.get("item").is_some() {
+            let name = get_str(item, "name").unwrap_or("folder");
+            let folder = match &folder {
+                Some(parent) => format!("{}/{}", parent, name),
+                None => name.to_string(),
+            };
+            if has_scripts(item) {
+                imported.warn(&folder, "folder scripts");
+            }
+            convert_items(item, Some(folder), auth, imported);
+        } else if item.get("request").is_some() {
+            convert_request(item, &folder, auth, imported);
+        }
+    }
+}
+
+pub fn is_collection(spec: &Value) -> bool {
+    spec.get("info").is_some() && spec.get("item").is_some()
+}
+
+/// Converts a collection, or an environment whose values become global variables.
+pub fn convert(spec: &Value) -> Result<Imported, String> {
+    let mut imported = Imported::default();
+    if is_collection(spec) {
+        if let Some(schema) = spec["info"].get("schema").and_then(Value::as_str) {
+            if !schema.contains("v2.") {
+                return Err(format!("Unsupported Postman collection schema {}", schema));
+            }
+        }
+        convert_items(spec, None, spec.get("auth"), &mut imported);
+        if has_scripts(spec) {
+            imported.warn("collection", "collection scripts");
+        }
+        imported.variables.extend(get_variables(spec, "variable"));
+    } else if spec.get("values").is_some() {
+        imported.variables.extend(get_variables(spec, "values"));
+    } else {
+        return Err("Not a Postman collection or environment".to_string());
+    }
+    Ok(imported)
+}
+
+pub fn read(path: &str) -> Result<Imported, String> {
+    let text =
+        fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+    let spec: Value =
+        serde_json::from_str(&text).map_err(|err| format!("Failed to parse {}: {}", path, err))?;
+    convert(&spec)
+}
+
+fn export_fields(body: &Value, is_multipart: bool) -> Vec<Value> {
+    let Value::Object(fields) = body else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .map(|(key, value)| {
+            let value = crate::utils::json_value_to_variable(value);
+            match value.strip_prefix('@') {
+                Some(path) if is_multipart => json!({ "key": key, "type": "file", "src": path }),
+                _ if is_multipart => json!({ "key": key, "type": "text", "value": value }),
+                _ => json!({ "key": key, "value": value }),
+            }
+        })
+        .collect()
+}
+
+fn export_body(request: &HttpRequest) -> Option<Value> {
+    let body = request.body.as_ref()?;
+    Some(match request.body_type.as_deref() {
+        Some("form") => json!({ "mode": "urlencoded", "urlencoded": export_fields(body, false) }),
+        Some("multipart") => json!({ "mode": "formdata", "formdata": export_fields(body, true) }),
+        Some("text") => json!({
+            "mode": "raw",
+            "raw": crate::utils::json_value_to_variable(body),
+        }),
+        _ => json!({
+            "mode": "raw",
+            "raw": serde_json::to_string_pretty(body).unwrap(),
+            "options": { "raw": { "language": "json" } },
+        }),
+    })
+}
+
+fn export_auth(auth: &Auth) -> Value {
+    let parameters = |auth_type: &str, parameters: &[(&str, &str)]| {
+        let parameters: Vec<Value> = parameters
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value, "type": "string" }))
+            .collect();
+        json!({ "type": auth_type, auth_type: parameters })
+    };
+    match auth {
+        Auth::None => json!({ "type": "noauth" }),
+        Auth::Basic { username, password } => parameters(
+            "basic",
+            &[
+                ("username", username.as_str()),
+                ("password", password.as_str()),
+            ],
+        ),
+        Auth::Digest { username, password } => parameters(
+            "digest",
+            &[
+                ("username", username.as_str()),
+                ("password", password.as_str()),
+            ],
+        ),
+        Auth::Bearer { token } => parameters("bearer", &[("token", token.as_str())]),
+        Auth::ApiKey {
+            name,
+            value,
+            location,
+        } => {
+            let location = match location {
+                ApiKeyLocation::Header => "header",
+                ApiKeyLocation::Query => "query",
+            };
+            parameters(
+                "apikey",
+                &[
+                    ("key", name.as_str()),
+                    ("value", value.as_str()),
+                    ("in", location),
+                ],
+            )
+        }
+        Auth::OAuth2(config) => {
+            let grant = match config.grant {
+                OAuth2Grant::ClientCredentials => "client_credentials",
+                OAuth2Grant::Password => "password_credentials",
+                OAuth2Grant::AuthorizationCode => "authorization_code_with_pkce",
+                // Postman has no refresh token or device code grants.
+                OAuth2Grant::RefreshToken | OAuth2Grant::DeviceCode => {
+                    return parameters("bearer", &[("token", "{{access_token}}")])
+                }
+            };
+            let client_authentication = match config.client_authentication {
+                ClientAuthentication::Body => "body",
+                ClientAuthentication::Basic => "header",
+            };
+            parameters(
+                "oauth2",
+                &[
+                    ("grant_type", grant),
+                    ("accessTokenUrl", config.token_url.as_str()),
+                    ("authUrl", config.auth_url.as_str()),
+                    ("clientId", config.client_id.as_str()),
+                    ("clientSecret", config.client_secret.as_str()),
+                    ("scope", config.scope.as_str()),
+                    ("username", config.username.as_str()),
+                    ("password", config.password.as_str()),
+                    ("redirect_uri", config.redirect_uri.as_str()),
+                    ("client_authentication", client_authentication),
+                    ("addTokenTo", "header"),
+                ],
+            )
+        }
+        Auth::AwsSigv4 {
+            access_key_id,
+            secret_access_key,
+            session_token,
+            region,
+            service,
+        } => parameters(
+            "awsv4",
+            &[
+                ("accessKey", access_key_id.as_str()),
+                ("secretKey", secret_access_key.as_str()),
+                ("sessionToken", session_token.as_str()),
+                ("region", region.as_str()),
+                ("service", service.as_str()),
+            ],
+        ),
+        // Postman can only sign requests with a script.
+        Auth::Hmac { .. } => json!({ "type": "noauth" }),
+    }
+}
+
+fn export_request(request: &HttpRequest) -> Value {
+    let headers: Vec<Value> = request
+        .headers
+        .iter()
+        .map(|header| {
+            let (key, value) = header.split_once(':').unwrap_or((header, ""));
+            json!({ "key": key.trim(), "value": value.trim() })
+        })
+        .collect();
+    let mut exported = json!({
+        "method": request.method,
+        "header": headers,
+        "url": { "raw": request.url },
+    });
+    if let Some(body) = export_body(request) {
+        exported["body"] = body;
+    }
+    if let Some(auth) = &request.auth {
+        exported["auth"] = export_auth(auth);
+    }
+    json!({
+        "name": crate::utils::get_request_label(request),
+        "request": exported,
+    })
+}
+
+/// Finds or creates the nested folder for a `parent/child` folder name.
+fn get_folder_items<'a>(items: &'a mut Vec<Value>, folder: &str) -> &'a mut Vec<Value> {
+    let mut items = items;
+    for name in folder.split('/') {
+        let position = items
+            .iter()
+            .position(|item| get_str(item, "name") == Some(name) && item.get("item").is_some());
+        let position = match position {
+            Some(position) => position,
+            None => {
+                items.push(json!({ "name": name, "item": [] }));
+                items.len() - 1
+            }
+        };
+        items = items[position]["item"].as_array_mut().unwrap();
+    }
+    items
+}
+
+/// Exports the requests as a collection, with the global variables they use as collection
+/// variables. Secrets are exported without their value.
+pub fn export_collection(requests: &[HttpRequest]) -> Value {
+    let mut items = Vec::new();
+    for request in requests {
+        let folder_items = match &request.folder {
+            Some(folder) => get_folder_items(&mut items, folder),
+            None => &mut items,
+        };
+        folder_items.push(export_request(request));
+    }
+
+    let placeholder = Regex::new(r"\{\{([^{}]+)\}\}").unwrap();
+    let used = serde_json::to_string(requests).unwrap();
+    let global_variables = get_global_variables();
+    let mut variables: Vec<Value> = Vec::new();
+    for captures in placeholder.captures_iter(&used) {
+        let key = &captures[1];
+        if variables
+            .iter()
+            .any(|variable| get_str(variable, "key") == Some(key))
+        {
+            continue;
+        }
+        let value = global_variables
+            .iter()
+            .find(|variable| variable.key == key)
+            .filter(|_| !history::is_sensitive_name(key))
+            .map(|variable| variable.value.clone())
+            .unwrap_or_default();
+        variables.push(json!({ "key": key, "value": value }));
+    }
+
+    json!({
+        "info": { "name": "xhtp", "schema": SCHEMA },
+        "item": items,
+        "variable": variables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection() -> Value {
+        json!({
+            "info": { "name": "Shop", "schema": SCHEMA },
+            "auth": { "type": "bearer", "bearer": [{ "key": "token", "value": "{{token}}" }] },
+            "variable": [
+                { "key": "baseUrl", "value": "https://shop.test" },
+                { "key": "off", "value": "1", "disabled": true }
+            ],
+            "item": [
+                {
+                    "name": "Users",
+                    "item": [{
+                        "name": "Admin",
+                        "auth": {
+                            "type": "basic",
+                            "basic": { "username": "root", "password": "{{password}}" }
+                        },
+                        "item": [{
+                            "name": "Get user",
+                            "event": [{ "listen": "test", "script": { "exec": ["pm.test()"] } }],
+                            "request": {
+                                "method": "get",
+                                "header": [
+                                    { "key": "Accept", "value": "application/json" },
+                                    { "key": "X-Debug", "value": "1", "disabled": true }
+                                ],
+                                "url": {
+                                    "raw": "{{baseUrl}}/users/:id?expand=1",
+                                    "variable": [{ "key": "id", "value": "7" }]
+                                }
+                            }
+                        }]
+                    }]
+                },
+                {
+                    "name": "Create order",
+                    "request": {
+                        "method": "POST",
+                        "url": {
+                            "protocol": "https",
+                            "host": ["shop", "test"],
+                            "path": ["orders"],
+                            "query": [
+                                { "key": "dry", "value": "true" },
+                                { "key": "skip", "value": "1", "disabled": true }
+                            ]
+                        },
+                        "body": {
+                            "mode": "raw",
+                            "raw": "{\"item\": 1}",
+                            "options": { "raw": { "language": "json" } }
+                        }
+                    }
+                },
+                {
+                    "name": "Upload",
+                    "request": {
+                        "method": "POST",
+                        "auth": { "type": "ntlm" },
+                        "url": "https://shop.test/upload",
+                        "body": {
+                            "mode": "formdata",
+                            "formdata": [
+                                { "key": "title", "value": "cat", "type": "text" },
+                                { "key": "file", "src": "/tmp/cat.png", "type": "file" },
+                                { "key": "many", "src": ["/a", "/b"], "type": "file" }
+                            ]
+                        }
+                    }
+                },
+                {
+                    "name": "Search",
+                    "request": {
+                        "method": "POST",
+                        "url": "https://shop.test/graphql",
+                        "body": {
+                            "mode": "graphql",
+                            "graphql": { "query": "{ items }", "variables": "{\"first\": 2}" }
+                        }
+                    }
+                },
+                {
+                    "name": "Binary",
+                    "request": {
+                        "method": "PUT",
+                        "url": "https://shop.test/blob",
+                        "body": { "mode": "file", "file": { "src": "/tmp/blob" } }
+                    }
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn converts_collections() {
+        let imported = convert(&collection()).unwrap();
+        let requests = &imported.requests;
+        assert_eq!(requests.len(), 5);
+
+        let get_user = &requests[0];
+        assert_eq!(get_user.name.as_deref(), Some("Get user"));
+        assert_eq!(get_user.folder.as_deref(), Some("Users/Admin"));
+        assert_eq!(get_user.method, "GET");
+        assert_eq!(get_user.url, "{{baseUrl}}/users/{{id}}?expand=1");
+        assert_eq!(get_user.headers, vec!["Accept: application/json"]);
+        assert_eq!(
+            get_user.auth,
+            Some(Auth::Basic {
+                username: "root".to_string(),
+                password: "{{password}}".to_string()
+            })
+        );
+
+        let create_order = &requests[1];
+        assert_eq!(create_order.url, "https://shop.test/orders?dry=true");
+        assert_eq!(create_order.body_type.as_deref(), Some("json"));
+        assert_eq!(create_order.body, Some(json!({ "item": 1 })));
+        assert_eq!(
+            create_order.auth,
+            Some(Auth::Bearer {
+                token: "{{token}}".to_string()
+            })
+        );
+
+        let upload = &requests[2];
+        assert_eq!(upload.auth, None);
+        assert_eq!(upload.body_type.as_deref(), Some("multipart"));
+        assert_eq!(
+            upload.body,
+            Some(json!({ "title": "cat", "file": "@/tmp/cat.png" }))
+        );
+
+        assert_eq!(
+            requests[3].body,
+            Some(json!({ "query": "{ items }", "variables": { "first": 2 } }))
+        );
+        assert_eq!(requests[4].body, None);
+
+        let variables: Vec<(&str, &str)> = imported
+            .variables
+            .iter()
+            .map(|variable| (variable.key.as_str(), variable.value.as_str()))
+            .collect();
+        assert_eq!(
+            variables,
+            vec![("id", "7"), ("baseUrl", "https://shop.test")]
+        );
+        assert_eq!(
+            imported.warnings,
+            vec![
+                "Users/Admin/Get user: pre-request and test scripts",
+                "Upload: ntlm auth is not supported",
+                "Upload: the file of the form field many",
+                "Binary: file bodies are not supported",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_raw_bodies_that_are_not_json_as_text() {
+        let mut headers = vec!["Content-Type: text/csv".to_string()];
+        let mut imported = Imported::default();
+        let body = json!({ "mode": "raw", "raw": "a,b" });
+        assert_eq!(
+            convert_body(&body, &mut headers, "csv", &mut imported),
+            (Some("text".to_string()), Some(json!("a,b")))
+        );
+
+        // Invalid JSON keeps its content type so it is still sent as JSON.
+        let mut headers = Vec::new();
+        let body = json!({
+            "mode": "raw",
+            "raw": "{ \"id\": {{id}} }",
+            "options": { "raw": { "language": "json" } }
+        });
+        assert_eq!(
+            convert_body(&body, &mut headers, "json", &mut imported),
+            (Some("text".to_string()), Some(json!("{ \"id\": {{id}} }")))
+        );
+        assert_eq!(headers, vec!["Content-Type: application/json"]);
+    }
+
+    #[test]
+    fn converts_environments_and_rejects_other_files() {
+        let environment = json!({
+            "name": "staging",
+            "values": [
+                { "key": "baseUrl", "value": "https://staging.test", "enabled": true },
+                { "key": "old", "value": "x", "enabled": false }
+            ]
+        });
+        let imported = convert(&environment).unwrap();
+        assert_eq!(imported.requests.len(), 0);
+        assert_eq!(imported.variables.len(), 1);
+        assert_eq!(imported.variables[0].value, "https://staging.test");
+
+        let v1 = json!({
+            "info": { "schema": "https://schema.getpostman.com/json/collection/v1.0.0/" },
+            "item": []
+        });
+        assert!(convert(&v1).is_err());
+        assert!(convert(&json!({ "openapi": "3.0.0" })).is_err());
+    }
+
+    #[test]
+    fn exports_requests_that_import_back() {
+        let request = HttpRequest {
+            name: Some("Login".to_string()),
+            folder: Some("Auth".to_string()),
+            method: "POST".to_string(),
+            url: "{{baseUrl}}/login".to_string(),
+            headers: vec!["Accept: application/json".to_string()],
+            body_type: Some("form".to_string()),
+            body: Some(json!({ "user": "ada", "remember": true })),
+            auth: Some(Auth::ApiKey {
+                name: "X-Key".to_string(),
+                value: "{{key}}".to_string(),
+                location: ApiKeyLocation::Query,
+            }),
+            ..Default::default()
+        };
+        let mut items = Vec::new();
+        get_folder_items(&mut items, "Auth").push(export_request(&request));
+        let collection = json!({ "info": { "name": "xhtp", "schema": SCHEMA }, "item": items });
+
+        let imported = convert(&collection).unwrap();
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        assert_eq!(
+            imported.requests,
+            vec![HttpRequest {
+                body: Some(json!({ "user": "ada", "remember": "true" })),
+                ..request
+            }]
+        );
+    }
+}
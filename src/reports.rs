@@ -0,0 +1,524 @@
+use crate::runner::{IterationResult, StepResult};
+use crate::structs::{HistoryEntry, HttpRequest};
+use crate::{exit_codes, har, utils};
+use serde_json::{json, Map, Value};
+use std::fs;
+
+pub enum ReportFormat {
+    Junit,
+    Json,
+    Tap,
+    Har,
+}
+
+impl ReportFormat {
+    /// Uses the explicit format when given, otherwise guesses from the file extension.
+    pub fn from_args(path: &str, format: Option<&str>) -> Result<ReportFormat, String> {
+        let format = match format {
+            Some(format) => format.to_lowercase(),
+            None => path.rsplit('.').next().unwrap_or("").to_lowercase(),
+        };
+        match format.as_str() {
+            "junit" | "xml" => Ok(ReportFormat::Junit),
+            "json" => Ok(ReportFormat::Json),
+            "tap" => Ok(ReportFormat::Tap),
+            "har" => Ok(ReportFormat::Har),
+            _ => Err(format!(
+                "Unknown report format '{}', use one of junit, json, tap or har.",
+                format
+            )),
+        }
+    }
+}
+
+/// A step of the run, including the ones skipped after a failure.
+enum Step<'a> {
+    Ran(&'a StepResult),
+    Skipped(usize, &'a HttpRequest),
+}
+
+impl Step<'_> {
+    fn request(&self) -> &HttpRequest {
+        match self {
+            Step::Ran(result) => &result.request,
+            Step::Skipped(_, request) => request,
+        }
+    }
+}
+
+/// The steps of one iteration of the run, one per data row when `--data` is used.
+struct Suite<'a> {
+    iteration: &'a IterationResult,
+    steps: Vec<Step<'a>>,
+}
+
+impl Suite<'_> {
+    fn name(&self) -> String {
+        match self.iteration.row {
+            Some(_) => format!(
+                "{} ({})",
+                self.iteration.label(),
+                self.iteration.variables_summary()
+            ),
+            None => "xhtp".to_string(),
+        }
+    }
+
+    fn step_name(&self, step: &Step) -> String {
+        let label = utils::get_request_label(step.request());
+        match self.iteration.row {
+            Some(_) => format!("{}: {}", self.iteration.label(), label),
+            None => label,
+        }
+    }
+}
+
+fn get_suites<'a>(
+    requests: &'a [HttpRequest],
+    indexes: &[usize],
+    iterations: &'a [IterationResult],
+) -> Vec<Suite<'a>> {
+    iterations
+        .iter()
+        .map(|iteration| Suite {
+            iteration,
+            steps: indexes
+                .iter()
+                .enumerate()
+                .map(|(step, index)| match iteration.results.get(step) {
+                    Some(result) => Step::Ran(result),
+                    None => Step::Skipped(
+                        *index,
+                        utils::get_request_from_saved_requests(requests, *index),
+                    ),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn seconds(duration_ms: u128) -> String {
+    format!("{:.3}", duration_ms as f64 / 1000.0)
+}
+
+fn render_junit(suites: &[Suite]) -> String {
+    let mut totals = [0, 0, 0, 0];
+    let mut total_ms = 0;
+    let mut rendered_suites = Vec::new();
+
+    for suite in suites {
+        let mut failures = 0;
+        let mut errors = 0;
+        let mut skipped = 0;
+        let mut suite_ms = 0;
+        let mut cases = Vec::new();
+
+        for step in &suite.steps {
+            let classname = escape_xml(step.request().folder.as_deref().unwrap_or("xhtp"));
+            let name = escape_xml(&suite.step_name(step));
+            match step {
+                Step::Ran(result) => {
+                    suite_ms += result.duration_ms;
+                    let mut case = format!(
+                        "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+                        name,
+                        classname,
+                        seconds(result.duration_ms)
+                    );
+                    if result.passed() {
+                        case.push_str(" />");
+                    } else {
+                        let messages = result.failure_messages();
+                        let (tag, kind) = if result.error.is_some() {
+                            errors += 1;
+                            ("error", "error")
+                        } else if result.exit_code == exit_codes::ASSERTION_FAILED {
+                            failures += 1;
+                            ("failure", "assertion")
+                        } else {
+                            failures += 1;
+                            ("failure", "status")
+                        };
+                        case.push_str(&format!(
+                            ">\n      <{} message=\"{}\" type=\"{}\">{}</{}>\n    </testcase>",
+                            tag,
+                            escape_xml(messages.first().map(String::as_str).unwrap_or("")),
+                            kind,
+                            escape_xml(&messages.join("\n")),
+                            tag
+                        ));
+                    }
+                    cases.push(case);
+                }
+                Step::Skipped(..) => {
+                    skipped += 1;
+                    cases.push(format!(
+                        "    <testcase name=\"{}\" classname=\"{}\" time=\"0.000\">\n      <skipped message=\"not run after an earlier failure\" />\n    </testcase>",
+                        name, classname
+                    ));
+                }
+            }
+        }
+
+        rendered_suites.push(format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">\n{}\n  </testsuite>",
+            escape_xml(&suite.name()),
+            suite.steps.len(),
+            failures,
+            errors,
+            skipped,
+            seconds(suite_ms),
+            cases.join("\n")
+        ));
+        totals[0] += suite.steps.len();
+        totals[1] += failures;
+        totals[2] += errors;
+        totals[3] += skipped;
+        total_ms += suite_ms;
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"xhtp\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">\n{}\n</testsuites>\n",
+        totals[0],
+        totals[1],
+        totals[2],
+        totals[3],
+        seconds(total_ms),
+        rendered_suites.join("\n")
+    )
+}
+
+fn render_json(suites: &[Suite]) -> String {
+    let mut passed = 0;
+    let mut failed = 0;
+    let mut skipped = 0;
+    let mut total_ms = 0;
+    let mut entries: Vec<Value> = Vec::new();
+
+    for suite in suites {
+        let row = suite.iteration.row;
+        let variables: Map<String, Value> = suite
+            .iteration
+            .variables
+            .iter()
+            .map(|variable| (variable.key.clone(), Value::String(variable.value.clone())))
+            .collect();
+        for step in &suite.steps {
+            let request = step.request();
+            let mut entry = json!({
+                "name": utils::get_request_label(request),
+                "folder": request.folder,
+                "method": request.method,
+                "url": request.url,
+            });
+            if row.is_some() {
+                entry["row"] = json!(row);
+                entry["variables"] = Value::Object(variables.clone());
+            }
+            match step {
+                Step::Ran(result) => {
+                    total_ms += result.duration_ms;
+                    if result.passed() {
+                        passed += 1;
+                    } else {
+                        failed += 1;
+                    }
+                    let outcome = result.outcome.as_ref();
+                    entry["index"] = json!(result.index);
+                    entry["result"] = json!(if result.passed() { "passed" } else { "failed" });
+                    entry["status_code"] = json!(outcome.map(|outcome| outcome.status_code));
+                    entry["duration_ms"] = json!(result.duration_ms);
+                    entry["error"] = json!(result.error);
+                    entry["assertions"] = json!(outcome.map(|outcome| &outcome.assertion_results));
+                    entry["failure_messages"] = json!(result.failure_messages());
+                }
+                Step::Skipped(index, _) => {
+                    skipped += 1;
+                    entry["index"] = json!(index);
+                    entry["result"] = json!("skipped");
+                }
+            }
+            entries.push(entry);
+        }
+    }
+
+    let report = json!({
+        "summary": {
+            "total": passed + failed + skipped,
+            "passed": passed,
+            "failed": failed,
+            "skipped": skipped,
+            "duration_ms": total_ms,
+        },
+        "results": entries,
+    });
+    serde_json::to_string_pretty(&report).unwrap()
+}
+
+fn render_tap(suites: &[Suite]) -> String {
+    let total: usize = suites.iter().map(|suite| suite.steps.len()).sum();
+    let mut lines = vec!["TAP version 13".to_string(), format!("1..{}", total)];
+    let mut number = 0;
+    for suite in suites {
+        for step in &suite.steps {
+            number += 1;
+            let name = suite.step_name(step);
+            let Step::Ran(result) = step else {
+                lines.push(format!(
+                    "ok {} - {} # SKIP not run after an earlier failure",
+                    number, name
+                ));
+                continue;
+            };
+            let verdict = if result.passed() { "ok" } else { "not ok" };
+            lines.push(format!("{} {} - {}", verdict, number, name));
+            lines.push("  ---".to_string());
+            lines.push(format!("  duration_ms: {}", result.duration_ms));
+            if let Some(outcome) = &result.outcome {
+                lines.push(format!("  status_code: {}", outcome.status_code));
+                if !outcome.assertion_results.is_empty() {
+                    lines.push("  assertions:".to_string());
+                    for assertion in &outcome.assertion_results {
+                        lines.push(format!(
+                            "    - {}: {}",
+                            if assertion.passed { "ok" } else { "not ok" },
+                            serde_json::to_string(&assertion.assertion).unwrap()
+                        ));
+                    }
+                }
+            }
+            let messages = result.failure_messages();
+            if !messages.is_empty() {
+                lines.push("  messages:".to_string());
+                for message in messages {
+                    lines.push(format!(
+                        "    - {}",
+                        serde_json::to_string(&message).unwrap()
+                    ));
+                }
+            }
+            lines.push("  ...".to_string());
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+pub fn write_report(
+    path: &str,
+    format: &ReportFormat,
+    requests: &[HttpRequest],
+    indexes: &[usize],
+    iterations: &[IterationResult],
+) -> Result<(), std::io::Error> {
+    let suites = get_suites(requests, indexes, iterations);
+    let report = match format {
+        ReportFormat::Junit => render_junit(&suites),
+        ReportFormat::Json => render_json(&suites),
+        ReportFormat::Tap => render_tap(&suites),
+        ReportFormat::Har => {
+            let entries: Vec<HistoryEntry> = iterations
+                .iter()
+                .flat_map(|iteration| &iteration.results)
+                .map(|result| result.entry.clone())
+                .collect();
+            serde_json::to_string_pretty(&har::to_har(&entries)).unwrap()
+        }
+    };
+    fs::write(path, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::{AssertionResult, GlobalVariable, RequestOutcome};
+
+    fn request(name: &str) -> HttpRequest {
+        HttpRequest {
+            name: Some(name.to_string()),
+            folder: Some("users".to_string()),
+            method: "GET".to_string(),
+            url: format!("{{{{baseUrl}}}}/{}", name),
+            ..Default::default()
+        }
+    }
+
+    fn step(index: usize, request: &HttpRequest, status_code: u16, passed: bool) -> StepResult {
+        StepResult {
+            index,
+            request: request.clone(),
+            outcome: Some(RequestOutcome {
+                status_code,
+                duration_ms: 250,
+                assertion_results: vec![AssertionResult {
+                    assertion: "$.name equals \"<Ada>\"".to_string(),
+                    passed,
+                    message: (!passed).then(|| "expected \"<Ada>\" but got null".to_string()),
+                }],
+                extracted_variables: Vec::new(),
+            }),
+            error: None,
+            duration_ms: 250,
+            exit_code: match passed {
+                true => exit_codes::SUCCESS,
+                false => exit_codes::ASSERTION_FAILED,
+            },
+            entry: HistoryEntry {
+                id: index as u64,
+                timestamp: "2024-01-01T00:00:00Z".to_string(),
+                saved_request: None,
+                request: request.clone(),
+                status_code: Some(status_code),
+                response_headers: Vec::new(),
+                duration_ms: 250,
+                response_size: 0,
+                response_body: None,
+                error: None,
+            },
+        }
+    }
+
+    /// Three requests, where the second fails its assertion and the third is skipped.
+    fn run(requests: &[HttpRequest], row: Option<usize>) -> Vec<IterationResult> {
+        vec![IterationResult {
+            row,
+            variables: vec![GlobalVariable {
+                key: "id".to_string(),
+                value: "7".to_string(),
+            }],
+            results: vec![
+                step(1, &requests[0], 200, true),
+                step(2, &requests[1], 200, false),
+            ],
+        }]
+    }
+
+    fn render(format: ReportFormat, row: Option<usize>) -> String {
+        let requests = vec![request("login"), request("fetch"), request("logout")];
+        let iterations = run(&requests, row);
+        let suites = get_suites(&requests, &[1, 2, 3], &iterations);
+        match format {
+            ReportFormat::Junit => render_junit(&suites),
+            ReportFormat::Json => render_json(&suites),
+            ReportFormat::Tap => render_tap(&suites),
+            ReportFormat::Har => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn picks_the_format_from_the_argument_or_extension() {
+        assert!(matches!(
+            ReportFormat::from_args("out.xml", None),
+            Ok(ReportFormat::Junit)
+        ));
+        assert!(matches!(
+            ReportFormat::from_args("out.txt", Some("TAP")),
+            Ok(ReportFormat::Tap)
+        ));
+        assert!(matches!(
+            ReportFormat::from_args("run.har", None),
+            Ok(ReportFormat::Har)
+        ));
+        assert!(ReportFormat::from_args("out.txt", None).is_err());
+    }
+
+    #[test]
+    fn renders_junit() {
+        assert_eq!(
+            render(ReportFormat::Junit, None),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="xhtp" tests="3" failures="1" errors="0" skipped="1" time="0.500">
+  <testsuite name="xhtp" tests="3" failures="1" errors="0" skipped="1" time="0.500">
+    <testcase name="login" classname="users" time="0.250" />
+    <testcase name="fetch" classname="users" time="0.250">
+      <failure message="$.name equals &quot;&lt;Ada&gt;&quot; (expected &quot;&lt;Ada&gt;&quot; but got null)" type="assertion">$.name equals &quot;&lt;Ada&gt;&quot; (expected &quot;&lt;Ada&gt;&quot; but got null)</failure>
+    </testcase>
+    <testcase name="logout" classname="users" time="0.000">
+      <skipped message="not run after an earlier failure" />
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn renders_tap() {
+        assert_eq!(
+            render(ReportFormat::Tap, Some(2)),
+            r#"TAP version 13
+1..3
+ok 1 - row 2: login
+  ---
+  duration_ms: 250
+  status_code: 200
+  assertions:
+    - ok: "$.name equals \"<Ada>\""
+  ...
+not ok 2 - row 2: fetch
+  ---
+  duration_ms: 250
+  status_code: 200
+  assertions:
+    - not ok: "$.name equals \"<Ada>\""
+  messages:
+    - "$.name equals \"<Ada>\" (expected \"<Ada>\" but got null)"
+  ...
+ok 3 - row 2: logout # SKIP not run after an earlier failure
+"#
+        );
+    }
+
+    #[test]
+    fn renders_json() {
+        let report: Value = serde_json::from_str(&render(ReportFormat::Json, Some(2))).unwrap();
+        assert_eq!(
+            report["summary"],
+            json!({ "total": 3, "passed": 1, "failed": 1, "skipped": 1, "duration_ms": 500 })
+        );
+        let results = report["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[1],
+            json!({
+                "name": "fetch",
+                "folder": "users",
+                "method": "GET",
+                "url": "{{baseUrl}}/fetch",
+                "row": 2,
+                "variables": { "id": "7" },
+                "index": 2,
+                "result": "failed",
+                "status_code": 200,
+                "duration_ms": 250,
+                "error": null,
+                "assertions": [{
+                    "assertion": "$.name equals \"<Ada>\"",
+                    "passed": false,
+                    "message": "expected \"<Ada>\" but got null"
+                }],
+                "failure_messages": ["$.name equals \"<Ada>\" (expected \"<Ada>\" but got null)"]
+            })
+        );
+        assert_eq!(
+            results[2],
+            json!({
+                "name": "logout",
+                "folder": "users",
+                "method": "GET",
+                "url": "{{baseUrl}}/logout",
+                "row": 2,
+                "variables": { "id": "7" },
+                "index": 3,
+                "result": "skipped"
+            })
+        );
+    }
+}
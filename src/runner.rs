@@ -0,0 +1,586 @@
+use crate::snapshots::SnapshotKey;
+use crate::structs::{Assertion, GlobalVariable, HistoryEntry, HttpRequest, RequestOutcome};
+use crate::{client, exit_codes, utils};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+#[derive(Clone)]
+pub struct RunOptions {
+    pub continue_on_failure: bool,
+    pub delay: Option<u64>,
+    pub concurrency: usize,
+    pub check_status: bool,
+}
+
+pub struct StepResult {
+    pub index: usize,
+    pub request: HttpRequest,
+    pub outcome: Option<RequestOutcome>,
+    pub error: Option<String>,
+    pub duration_ms: u128,
+    pub exit_code: i32,
+    /// The request and response as recorded in the history, with secrets masked.
+    pub entry: HistoryEntry,
+}
+
+impl StepResult {
+    pub fn passed(&self) -> bool {
+        self.exit_code == exit_codes::SUCCESS
+    }
+
+    pub fn failure_messages(&self) -> Vec<String> {
+        let mut messages = Vec::new();
+        if self.passed() {
+            return messages;
+        }
+        if let Some(error) = &self.error {
+            messages.push(error.clone());
+        }
+        if let Some(outcome) = &self.outcome {
+            if self.exit_code != exit_codes::ASSERTION_FAILED {
+                messages.push(format!("responded with status {}", outcome.status_code));
+            }
+            for assertion in &outcome.assertion_results {
+                if let Some(message) = &assertion.message {
+                    messages.push(format!("{} ({})", assertion.assertion, message));
+                }
+            }
+        }
+        messages
+    }
+}
+
+/// The results of running the selected requests once, with the variables of a data row if any.
+pub struct IterationResult {
+    pub row: Option<usize>,
+    pub variables: Vec<GlobalVariable>,
+    pub results: Vec<StepResult>,
+}
+
+impl IterationResult {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed())
+    }
+
+    pub fn label(&self) -> String {
+        match self.row {
+            Some(row) => format!("row {}", row),
+            None => "run".to_string(),
+        }
+    }
+
+    pub fn variables_summary(&self) -> String {
+        let summary = self
+            .variables
+            .iter()
+            .map(|variable| format!("{}={}", variable.key, variable.value))
+            .collect::<Vec<String>>()
+            .join(", ");
+        if summary.chars().count() > 40 {
+            format!("{}...", summary.chars().take(37).collect::<String>())
+        } else {
+            summary
+        }
+    }
+}
+
+fn parse_index(requests: &[HttpRequest], part: &str) -> Result<usize, String> {
+    let index = part
+        .parse::<usize>()
+        .map_err(|_| format!("'{}' is not a request number.", part))?;
+    if index == 0 || index > requests.len() {
+        return Err(format!(
+            "There is no request number {}, the collection has {} requests.",
+            index,
+            requests.len()
+        ));
+    }
+    Ok(index)
+}
+
+fn is_number(part: &str) -> bool {
+    !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Resolves a comma separated selector of request numbers (`2`), ranges (`1-4`),
+/// request names, folder names or `all` into 1-based request numbers.
+pub fn select_requests(
+    requests: &[HttpRequest],
+    selector: Option<&str>,
+) -> Result<Vec<usize>, String> {
+    let mut indexes = Vec::new();
+    for part in selector.unwrap_or("all").split(',').map(str::trim) {
+        if part.is_empty() {
+            continue;
+        }
+        if part == "all" {
+            indexes.extend(1..=requests.len());
+            continue;
+        }
+        if is_number(part) {
+            indexes.push(parse_index(requests, part)?);
+            continue;
+        }
+        if let Some((start, end)) = part.split_once('-') {
+            if is_number(start) && is_number(end) {
+                let start = parse_index(requests, start)?;
+                let end = parse_index(requests, end)?;
+                if start > end {
+                    return Err(format!(
+                        "The range {} goes backwards, did you mean {}-{}?",
+                        part, end, start
+                    ));
+                }
+                indexes.extend(start..=end);
+                continue;
+            }
+        }
+        let by_name: Vec<usize> = requests
+            .iter()
+            .enumerate()
+            .filter(|(_, request)| request.name.as_deref() == Some(part))
+            .map(|(index, _)| index + 1)
+            .collect();
+        if !by_name.is_empty() {
+            indexes.extend(by_name);
+            continue;
+        }
+        let by_folder: Vec<usize> = requests
+            .iter()
+            .enumerate()
+            .filter(|(_, request)| request.folder.as_deref() == Some(part))
+            .map(|(index, _)| index + 1)
+            .collect();
+        if by_folder.is_empty() {
+            return Err(format!("No request, folder or number matches '{}'.", part));
+        }
+        indexes.extend(by_folder);
+    }
+    Ok(indexes)
+}
+
+/// A step fails on failed assertions and, with `--check-status`, with the code of its response
+/// status, the same as a single request. Requests asserting on the status itself only fail on
+/// their assertions.
+fn get_step_exit_code(request: &HttpRequest, outcome: &RequestOutcome, check_status: bool) -> i32 {
+    let asserts_status = request
+        .assertions
+        .iter()
+        .flatten()
+        .any(|assertion| matches!(assertion, Assertion::Status { .. }));
+    exit_codes::from_outcome(outcome, check_status && !asserts_status)
+}
+
+pub async fn run_requests(
+    client: &reqwest::Client,
+    requests: &[HttpRequest],
+    indexes: &[usize],
+    mut variables: Vec<GlobalVariable>,
+    options: &RunOptions,
+    row: Option<usize>,
+) -> Vec<StepResult> {
+    let label_prefix = match row {
+        Some(row) => format!("[row {}] ", row),
+        None => String::new(),
+    };
+    let mut results = Vec::new();
+    for (step, index) in indexes.iter().enumerate() {
+        if step > 0 {
+            if let Some(delay) = options.delay {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+            }
+        }
+        let request = utils::get_request_from_saved_requests(requests, *index);
+        utils::print_line(&format!(
+            "{}[{}/{}] {}",
+            label_prefix,
+            step + 1,
+            indexes.len(),
+            utils::get_request_label(request)
+        ));
+
+        let started = Instant::now();
+        let snapshot_key = SnapshotKey { row };
+        let (result, entry) = client::send_request(client, request, &variables).await;
+        let result =
+            result.map(|received| client::handle_response(request, snapshot_key, received));
+        let duration_ms = started.elapsed().as_millis();
+        let step_result = match result {
+            Ok(outcome) => {
+                // Variables extracted by this step win over the row's own values.
+                for extracted in outcome.extracted_variables.iter().rev() {
+                    variables.retain(|variable| variable.key != extracted.key);
+                    variables.insert(0, extracted.clone());
+                }
+                StepResult {
+                    index: *index,
+                    request: request.clone(),
+                    exit_code: get_step_exit_code(request, &outcome, options.check_status),
+                    duration_ms: outcome.duration_ms,
+                    outcome: Some(outcome),
+                    error: None,
+                    entry,
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                StepResult {
+                    index: *index,
+                    request: request.clone(),
+                    exit_code: match options.check_status {
+                        true => exit_codes::from_error(&err),
+                        false => exit_codes::ERROR,
+                    },
+                    duration_ms,
+                    outcome: None,
+                    error: Some(err.to_string()),
+                    entry,
+                }
+            }
+        };
+
+        let passed = step_result.passed();
+        results.push(step_result);
+        if !passed && !options.continue_on_failure {
+            break;
+        }
+    }
+    results
+}
+
+/// Runs the selected requests once per data row, with up to `options.concurrency`
+/// rows in flight. Rows that were never started because of a failure have no results.
+/// The columns of a row take precedence over the `environment` variables.
+pub async fn run_data_rows(
+    client: &reqwest::Client,
+    requests: &[HttpRequest],
+    indexes: &[usize],
+    rows: Vec<Vec<GlobalVariable>>,
+    environment: &[GlobalVariable],
+    options: &RunOptions,
+) -> Vec<IterationResult> {
+    let requests = Arc::new(requests.to_vec());
+    let indexes = Arc::new(indexes.to_vec());
+    let rows = Arc::new(rows);
+    let environment = Arc::new(environment.to_vec());
+    let next_row = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let mut workers = JoinSet::new();
+    for _ in 0..options.concurrency.max(1) {
+        let client = client.clone();
+        let requests = requests.clone();
+        let indexes = indexes.clone();
+        let rows = rows.clone();
+        let environment = environment.clone();
+        let next_row = next_row.clone();
+        let stop = stop.clone();
+        let options = options.clone();
+        workers.spawn(async move {
+            let mut iterations = Vec::new();
+            loop {
+                let row = next_row.fetch_add(1, Ordering::SeqCst);
+                if row >= rows.len() || stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let results = run_requests(
+                    &client,
+                    &requests,
+                    &indexes,
+                    [rows[row].as_slice(), environment.as_slice()].concat(),
+                    &options,
+                    Some(row + 1),
+                )
+                .await;
+                if !options.continue_on_failure && results.iter().any(|result| !result.passed()) {
+                    stop.store(true, Ordering::SeqCst);
+                }
+                iterations.push(IterationResult {
+                    row: Some(row + 1),
+                    variables: rows[row].clone(),
+                    results,
+                });
+            }
+            iterations
+        });
+    }
+
+    let mut iterations = Vec::new();
+    while let Some(worker) = workers.join_next().await {
+        iterations.extend(worker.expect("A data row worker panicked"));
+    }
+    for (row, variables) in rows.iter().enumerate() {
+        if !iterations
+            .iter()
+            .any(|iteration| iteration.row == Some(row + 1))
+        {
+            iterations.push(IterationResult {
+                row: Some(row + 1),
+                variables: variables.clone(),
+                results: Vec::new(),
+            });
+        }
+    }
+    iterations.sort_by_key(|iteration| iteration.row);
+    iterations
+}
+
+/// The exit code of the first failed step, so scripts see why the run stopped.
+pub fn get_exit_code(iterations: &[IterationResult]) -> i32 {
+    iterations
+        .iter()
+        .flat_map(|iteration| &iteration.results)
+        .map(|result| result.exit_code)
+        .find(|code| *code != exit_codes::SUCCESS)
+        .unwrap_or(exit_codes::SUCCESS)
+}
+
+const PASS: &str = "\x1b[32mPASS\x1b[0m";
+const FAIL: &str = "\x1b[31mFAIL\x1b[0m";
+const SKIP: &str = "\x1b[33mSKIP\x1b[0m";
+
+fn print_table<const N: usize>(rows: &[[String; N]], verdicts: &[&str]) {
+    let widths: Vec<usize> = (0..N)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect();
+    println!();
+    for (row_index, row) in rows.iter().enumerate() {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ");
+        if row_index == 0 {
+            utils::print_line(&format!("{}  Result", line));
+        } else {
+            println!("{}  {}", line, verdicts[row_index - 1]);
+        }
+    }
+}
+
+fn print_step_summary(requests: &[HttpRequest], indexes: &[usize], results: &[StepResult]) {
+    let mut rows = vec![[
+        "#".to_string(),
+        "Request".to_string(),
+        "Status".to_string(),
+        "Time".to_string(),
+        "Assertions".to_string(),
+    ]];
+    let mut verdicts = Vec::new();
+    for (step, index) in indexes.iter().enumerate() {
+        match results.get(step) {
+            Some(result) => {
+                let (status, assertions) = match &result.outcome {
+                    Some(outcome) => (
+                        outcome.status_code.to_string(),
+                        format!(
+                            "{}/{}",
+                            outcome
+                                .assertion_results
+                                .iter()
+                                .filter(|assertion| assertion.passed)
+                                .count(),
+                            outcome.assertion_results.len()
+                        ),
+                    ),
+                    None => ("error".to_string(), "-".to_string()),
+                };
+                rows.push([
+                    result.index.to_string(),
+                    utils::get_request_label(&result.request),
+                    status,
+                    format!("{} ms", result.duration_ms),
+                    assertions,
+                ]);
+                verdicts.push(if result.passed() { PASS } else { FAIL });
+            }
+            None => {
+                let request = utils::get_request_from_saved_requests(requests, *index);
+                rows.push([
+                    index.to_string(),
+                    utils::get_request_label(request),
+                    "-".to_string(),
+                    "-".to_string(),
+                    "-".to_string(),
+                ]);
+                verdicts.push(SKIP);
+            }
+        }
+    }
+    print_table(&rows, &verdicts);
+
+    for result in results.iter().filter(|result| !result.passed()) {
+        let label = utils::get_request_label(&result.request);
+        for message in result.failure_messages() {
+            println!("{}: {}", label, message);
+        }
+    }
+
+    let passed = results.iter().filter(|result| result.passed()).count();
+    println!(
+        "{} passed, {} failed, {} skipped",
+        passed,
+        results.len() - passed,
+        indexes.len() - results.len()
+    );
+}
+
+fn print_row_summary(indexes: &[usize], iterations: &[IterationResult]) {
+    let mut rows = vec![[
+        "Row".to_string(),
+        "Variables".to_string(),
+        "Steps".to_string(),
+        "Time".to_string(),
+    ]];
+    let mut verdicts = Vec::new();
+    for iteration in iterations {
+        let passed_steps = iteration
+            .results
+            .iter()
+            .filter(|result| result.passed())
+            .count();
+        let duration_ms: u128 = iteration
+            .results
+            .iter()
+            .map(|result| result.duration_ms)
+            .sum();
+        rows.push([
+            iteration.label(),
+            iteration.variables_summary(),
+            format!("{}/{}", passed_steps, indexes.len()),
+            format!("{} ms", duration_ms),
+        ]);
+        verdicts.push(if iteration.results.is_empty() {
+            SKIP
+        } else if iteration.passed() {
+            PASS
+        } else {
+            FAIL
+        });
+    }
+    print_table(&rows, &verdicts);
+
+    for iteration in iterations {
+        for result in iteration.results.iter().filter(|result| !result.passed()) {
+            let label = utils::get_request_label(&result.request);
+            for message in result.failure_messages() {
+                println!("{} {}: {}", iteration.label(), label, message);
+            }
+        }
+    }
+
+    let passed = iterations
+        .iter()
+        .filter(|iteration| !iteration.results.is_empty() && iteration.passed())
+        .count();
+    let skipped = iterations
+        .iter()
+        .filter(|iteration| iteration.results.is_empty())
+        .count();
+    println!(
+        "{} rows passed, {} failed, {} skipped",
+        passed,
+        iterations.len() - passed - skipped,
+        skipped
+    );
+}
+
+pub fn print_summary(requests: &[HttpRequest], indexes: &[usize], iterations: &[IterationResult]) {
+    match iterations {
+        [iteration] if iteration.row.is_none() => {
+            print_step_summary(requests, indexes, &iteration.results)
+        }
+        _ => print_row_summary(indexes, iterations),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::AssertionResult;
+
+    fn requests(count: usize) -> Vec<HttpRequest> {
+        (0..count)
+            .map(|index| HttpRequest {
+                name: Some(format!("request-{}", index + 1)),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn selects_numbers_ranges_and_names() {
+        let requests = requests(5);
+        assert_eq!(
+            select_requests(&requests, Some("1, 3-4,request-5")),
+            Ok(vec![1, 3, 4, 5])
+        );
+        assert_eq!(select_requests(&requests, None), Ok(vec![1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn rejects_invalid_numbers_and_ranges() {
+        let requests = requests(5);
+        for selector in ["0", "6", "-1", "2--1", "4-2", "1-99999999999999999999999"] {
+            assert!(
+                select_requests(&requests, Some(selector)).is_err(),
+                "{}",
+                selector
+            );
+        }
+    }
+
+    fn outcome(status_code: u16, passed: bool) -> RequestOutcome {
+        RequestOutcome {
+            status_code,
+            duration_ms: 0,
+            assertion_results: vec![AssertionResult {
+                assertion: "status".to_string(),
+                passed,
+                message: None,
+            }],
+            extracted_variables: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn maps_step_statuses_like_single_requests() {
+        let request = HttpRequest::default();
+        for (status_code, expected) in [
+            (200, exit_codes::SUCCESS),
+            (302, exit_codes::REDIRECTION),
+            (404, exit_codes::CLIENT_ERROR),
+            (503, exit_codes::SERVER_ERROR),
+        ] {
+            let outcome = outcome(status_code, true);
+            assert_eq!(get_step_exit_code(&request, &outcome, true), expected);
+            assert_eq!(
+                get_step_exit_code(&request, &outcome, false),
+                exit_codes::SUCCESS
+            );
+        }
+        assert_eq!(
+            get_step_exit_code(&request, &outcome(500, false), false),
+            exit_codes::ASSERTION_FAILED
+        );
+    }
+
+    #[test]
+    fn status_assertions_replace_the_status_check() {
+        let request = HttpRequest {
+            assertions: Some(vec![Assertion::Status { equals: 404 }]),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_step_exit_code(&request, &outcome(404, true), true),
+            exit_codes::SUCCESS
+        );
+        assert_eq!(
+            get_step_exit_code(&request, &outcome(200, false), true),
+            exit_codes::ASSERTION_FAILED
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Snapshots of normalized responses, stored next to the requests config and checked on every send.
+
+use crate::client::ReceivedResponse;
+use crate::content::Body;
+use crate::structs::{AssertionResult, HttpRequest, Snapshot, SnapshotConfig};
+use crate::utils;
+use crate::{diff, history};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Number of differences listed in the failure message.
+const MAX_LISTED_CHANGES: usize = 5;
+
+static UPDATE: AtomicBool = AtomicBool::new(false);
+
+pub fn configure(update: bool) {
+    UPDATE.store(update, Ordering::SeqCst);
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Tells apart the snapshots of the data rows of a run.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SnapshotKey {
+    /// The data row the request was sent with.
+    pub row: Option<usize>,
+}
+
+/// Snapshots are named after the request, or its method and url template when it has no
+/// name, so that they survive reordering the requests, with the data row if any, inside a
+/// directory per folder.
+pub fn get_snapshot_path(request: &HttpRequest, key: SnapshotKey) -> String {
+    let mut stem = utils::get_request_label(request);
+    if let Some(row) = key.row {
+        stem.push_str(&format!(".row-{}", row));
+    }
+    let file_name = format!("{}.json", sanitize(&stem));
+    match &request.folder {
+        Some(folder) => format!(
+            "{}/{}/{}",
+            utils::get_snapshots_dir_path(),
+            sanitize(folder),
+            file_name
+        ),
+        None => format!("{}/{}", utils::get_snapshots_dir_path(), file_name),
+    }
+}
+
+/// Keeps the status, the configured headers and the body, with the redacted paths replaced.
+/// Credentials are masked as in the history.
+fn normalize(config: &SnapshotConfig, received: &ReceivedResponse) -> Snapshot {
+    let headers = config
+        .headers
+        .iter()
+        .filter_map(|name| {
+            let value = received.headers.get(name.as_str())?.to_str().unwrap_or("");
+            let redacted = config
+                .redact
+                .iter()
+                .any(|pattern| pattern.eq_ignore_ascii_case(name));
+            let value = if redacted {
+                diff::REDACTED.to_string()
+            } else if history::is_sensitive_name(name) {
+                history::mask_value(value)
+            } else {
+                value.to_string()
+            };
+            Some((name.to_lowercase(), value))
+        })
+        .collect();
+    let mut body = match &received.body {
+        Body::Binary(binary) => Some(Value::String(binary.summary())),
+        _ => history::mask_response_body(received),
+    };
+    if let Some(body) = &mut body {
+        diff::redact(body, &config.redact);
+    }
+    Snapshot {
+        status_code: received.status_code,
+        headers,
+        body,
+    }
+}
+
+fn write_snapshot(path: &str, snapshot: &Snapshot) -> Result<(), std::io::Error> {
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(snapshot).unwrap())
+}
+
+/// Compares the response to the snapshot of the request, writing the snapshot when there is
+/// none yet or `--update-snapshots` was passed. Requests without a `snapshot` config are skipped.
+pub fn check(
+    request: &HttpRequest,
+    key: SnapshotKey,
+    received: &ReceivedResponse,
+) -> Option<AssertionResult> {
+    let config = request.snapshot.as_ref()?;
+    let path = get_snapshot_path(request, key);
+    let snapshot = normalize(config, received);
+    let assertion = format!("snapshot {}", path);
+    let failed = |message: String| AssertionResult {
+        assertion: assertion.clone(),
+        passed: false,
+        message: Some(message),
+    };
+
+    let stored = match fs::read_to_string(&path) {
+        Ok(content) if !UPDATE.load(Ordering::SeqCst) => content,
+        _ => {
+            return Some(match write_snapshot(&path, &snapshot) {
+                Ok(()) => {
+                    eprintln!("\x1b[94mSnapshot written to {}\x1b[0m", path);
+                    AssertionResult {
+                        assertion,
+                        passed: true,
+                        message: None,
+                    }
+                }
+                Err(err) => failed(format!("failed to write the snapshot: {}", err)),
+            });
+        }
+    };
+    let stored: Snapshot = match serde_json::from_str(&stored) {
+        Ok(stored) => stored,
+        Err(err) => return Some(failed(format!("failed to parse the snapshot: {}", err))),
+    };
+
+    let changes = diff::diff_json(
+        &serde_json::to_value(&stored).unwrap(),
+        &serde_json::to_value(&snapshot).unwrap(),
+        &[],
+    );
+    if changes.is_empty() {
+        return Some(AssertionResult {
+            assertion,
+            passed: true,
+            message: None,
+        });
+    }
+    let mut listed: Vec<String> = changes
+        .iter()
+        .take(MAX_LISTED_CHANGES)
+        .map(|change| change.describe())
+        .collect();
+    if changes.len() > MAX_LISTED_CHANGES {
+        listed.push(format!("{} more", changes.len() - MAX_LISTED_CHANGES));
+    }
+    Some(failed(format!(
+        "{}; run with --update-snapshots to accept",
+        listed.join("; ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_snapshots_after_the_request() {
+        let request = HttpRequest {
+            method: "GET".to_string(),
+            url: "{{baseUrl}}/users/{{id}}".to_string(),
+            ..Default::default()
+        };
+        let path = get_snapshot_path(&request, SnapshotKey::default());
+        assert!(
+            path.ends_with("/GET___baseUrl___users___id__.json"),
+            "{}",
+            path
+        );
+
+        let request = HttpRequest {
+            name: Some("fetch user".to_string()),
+            folder: Some("users".to_string()),
+            ..request
+        };
+        let path = get_snapshot_path(&request, SnapshotKey { row: Some(2) });
+        assert!(path.ends_with("/users/fetch_user.row-2.json"), "{}", path);
+    }
+}
@@ -1,12 +1,83 @@
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
 
 #[derive(Parser)]
 pub struct Cli {
     pub first_arg: Option<String>,
     pub second_arg: Option<String>,
     pub third_arg: Option<String>,
+    /// Exit with a code derived from the response status (3xx, 4xx, 5xx) or request error
+    #[arg(long)]
+    pub check_status: bool,
+    /// Request timeout in seconds
+    #[arg(long)]
+    pub timeout: Option<u64>,
+    /// Keep running the remaining steps of `run` after a step fails
+    #[arg(long)]
+    pub continue_on_failure: bool,
+    /// Milliseconds to wait between the steps of `run`
+    #[arg(long)]
+    pub delay: Option<u64>,
+    /// Write a report of the `run` to this path
+    #[arg(long)]
+    pub report: Option<String>,
+    /// Format of the report: junit, json or tap (defaults to the report file extension)
+    #[arg(long)]
+    pub report_format: Option<String>,
+    /// CSV or JSON file whose rows are used as variables, running the selection once per row
+    #[arg(long)]
+    pub data: Option<String>,
+    /// Number of data rows (`run`) or requests (`bench`) in flight at the same time
+    #[arg(long, default_value_t = 1)]
+    pub concurrency: usize,
+    /// Number of requests sent by `bench`
+    #[arg(short = 'n', long)]
+    pub requests: Option<u64>,
+    /// Seconds that `bench` keeps sending requests for
+    #[arg(long)]
+    pub duration: Option<u64>,
+    /// Maximum requests per second sent by `bench`, from 1 to 1000000
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..=1_000_000))]
+    pub rate: Option<u64>,
+    /// Output format of `bench`: table or json
+    #[arg(long)]
+    pub output: Option<String>,
+    /// Don't record this request in the history
+    #[arg(long)]
+    pub no_history: bool,
+    /// Record this request in the history without the response body
+    #[arg(long)]
+    pub no_history_body: bool,
+    /// Use the variables of ~/.xhtp/environments/<name>.json, can be repeated
+    #[arg(long)]
+    pub env: Vec<String>,
+    /// JSON path or header name left out of `diff`, can be repeated
+    #[arg(long)]
+    pub ignore: Vec<String>,
+    /// Number of the spec server used as the base url of the spec by `i`
+    #[arg(long)]
+    pub server: Option<usize>,
+    /// Overwrite the snapshots of the requests that are sent instead of comparing them
+    #[arg(long)]
+    pub update_snapshots: bool,
+    /// Format of `export`: curl, httpie, python-requests, js-fetch, rust-reqwest, postman or http,
+    /// or har for `hl` and `hf`
+    #[arg(long = "as")]
+    pub export_format: Option<String>,
+    /// Resolve the variables in the snippet of `export` instead of keeping the placeholders
+    #[arg(long)]
+    pub resolve: bool,
+    /// Only import the HAR entries sent to this host, can be repeated
+    #[arg(long)]
+    pub host: Vec<String>,
+    /// Only import the HAR entries with this method, can be repeated
+    #[arg(long)]
+    pub method: Vec<String>,
+    /// Only import the HAR entries whose url matches this regular expression
+    #[arg(long)]
+    pub url_pattern: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
@@ -16,16 +87,227 @@ pub struct ExtractVariable {
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assertion {
+    Status {
+        equals: u16,
+    },
+    Header {
+        name: String,
+        equals: Option<String>,
+        contains: Option<String>,
+        matches: Option<String>,
+    },
+    JsonPath {
+        path: String,
+        equals: Option<Value>,
+        contains: Option<Value>,
+        matches: Option<String>,
+        exists: Option<bool>,
+    },
+    ResponseTime {
+        less_than_ms: u64,
+    },
+    JsonSchema {
+        schema: Value,
+    },
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone)]
 pub struct HttpRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
     pub method: String,
     pub url: String,
     pub headers: Vec<String>,
     pub body_type: Option<String>,
     pub body: Option<Value>,
     pub extract_variables: Option<Vec<ExtractVariable>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assertions: Option<Vec<Assertion>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<RequestOrigin>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<Auth>,
+    /// Skips verifying the server certificate, like `curl -k`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insecure: Option<bool>,
+}
+
+/// Credentials of a request, kept out of `headers` so they can be masked and rotated.
+/// Every value can use `{{variables}}`.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Auth {
+    /// Sends the request without the default auth.
+    None,
+    Basic {
+        username: String,
+        #[serde(default)]
+        password: String,
+    },
+    /// Answers the `WWW-Authenticate: Digest` challenge of a first, unauthenticated request.
+    Digest {
+        username: String,
+        #[serde(default)]
+        password: String,
+    },
+    Bearer {
+        token: String,
+    },
+    ApiKey {
+        name: String,
+        value: String,
+        #[serde(rename = "in", default)]
+        location: ApiKeyLocation,
+    },
+    /// Sends the access token of an OAuth 2.0 grant as a bearer token.
+    #[serde(rename = "oauth2")]
+    OAuth2(OAuth2),
+    /// Signs the request with AWS Signature Version 4. Empty credentials and region are read
+    /// from the `AWS_*` environment variables.
+    AwsSigv4 {
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        access_key_id: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        secret_access_key: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        session_token: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        region: String,
+        /// Such as `execute-api` for API Gateway.
+        service: String,
+    },
+    /// Sends an HMAC-SHA256 signature of the request, built from a template such as
+    /// `{method}\n{path}\n{timestamp}\n{body}`, which is the default.
+    Hmac {
+        secret: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        template: String,
+        /// `X-Signature` by default.
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        header: String,
+        /// A template for the value of the header, `{signature}` by default.
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        header_value: String,
+        /// The header the timestamp is sent in, if any.
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        timestamp_header: String,
+        #[serde(default)]
+        timestamp_format: TimestampFormat,
+        #[serde(default)]
+        encoding: SignatureEncoding,
+    },
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// Seconds since the epoch.
+    #[default]
+    Unix,
+    UnixMs,
+    Rfc3339,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyLocation {
+    #[default]
+    Header,
+    Query,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// An OAuth 2.0 client. The tokens it gets are cached in `~/.xhtp/oauth2_tokens.json` and
+/// refreshed before they expire.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone)]
+pub struct OAuth2 {
+    pub grant: OAuth2Grant,
+    pub token_url: String,
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub client_secret: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub scope: String,
+    /// The resource owner, for the password grant.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub username: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub password: String,
+    /// For the refresh token grant.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub refresh_token: String,
+    /// The authorization endpoint, for the authorization code grant.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub auth_url: String,
+    /// The localhost url the browser is sent back to, `http://127.0.0.1:8400/callback` by default.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub redirect_uri: String,
+    /// The device authorization endpoint, for the device code grant.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub device_url: String,
+    /// Whether the client id and secret are sent in the form or as basic auth.
+    #[serde(default)]
+    pub client_authentication: ClientAuthentication,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuth2Grant {
+    #[default]
+    ClientCredentials,
+    Password,
+    RefreshToken,
+    AuthorizationCode,
+    DeviceCode,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAuthentication {
+    #[default]
+    Body,
+    Basic,
+}
+
+/// Where an imported request came from, used to update it when the spec changes.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone)]
+pub struct RequestOrigin {
+    /// The path or url of the spec, or `stdin:<title>` for a spec read from stdin.
+    pub source: String,
+    /// The operationId, or the method and path of the operation.
+    pub operation: String,
+    /// Set when the operation is no longer in the spec.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub removed: bool,
+    /// The request as last imported, to tell local edits apart from changes to the spec.
+    pub imported: ImportedRequest,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ImportedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<String>,
+    pub body_type: Option<String>,
+    pub body: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<Auth>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct GlobalVariable {
     pub key: String,
     pub value: String,
@@ -38,4 +320,50 @@ pub struct HttpResponse {
     pub status_code: u16,
     pub json_data: Option<Value>,
     pub text_data: Option<String>,
+    pub binary_data: Option<String>,
+}
+
+/// Which parts of the response are kept in the snapshot of a request.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone)]
+pub struct SnapshotConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Snapshot {
+    pub status_code: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<Value>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct AssertionResult {
+    pub assertion: String,
+    pub passed: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct RequestOutcome {
+    pub status_code: u16,
+    pub duration_ms: u128,
+    pub assertion_results: Vec<AssertionResult>,
+    pub extracted_variables: Vec<GlobalVariable>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub timestamp: String,
+    pub saved_request: Option<HttpRequest>,
+    pub request: HttpRequest,
+    pub status_code: Option<u16>,
+    pub response_headers: Vec<String>,
+    pub duration_ms: u128,
+    pub response_size: usize,
+    pub response_body: Option<Value>,
+    pub error: Option<String>,
 }
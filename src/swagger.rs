@@ -0,0 +1,334 @@
+//! Converts Swagger 2.0 specs to the OpenAPI 3 layout the importer reads.
+
+use serde_json::{json, Map, Value};
+
+const METHODS: [&str; 7] = ["get", "put", "post", "delete", "options", "head", "patch"];
+
+/// Parameter keys that move into the `schema` of an OpenAPI 3 parameter.
+const SCHEMA_KEYS: [&str; 10] = [
+    "type",
+    "format",
+    "items",
+    "enum",
+    "default",
+    "minimum",
+    "maximum",
+    "pattern",
+    "minLength",
+    "maxLength",
+];
+
+pub fn is_swagger(spec: &Value) -> bool {
+    spec.get("swagger")
+        .and_then(Value::as_str)
+        .is_some_and(|version| version.starts_with('2'))
+}
+
+/// Points `#/definitions/...` and `#/parameters/...` references at `#/components/...`.
+fn rewrite_refs(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                match item {
+                    Value::String(reference) if key == "$ref" => {
+                        *reference = reference
+                            .replacen("#/definitions/", "#/components/schemas/", 1)
+                            .replacen("#/parameters/", "#/components/parameters/", 1)
+                            .replacen("#/responses/", "#/components/responses/", 1);
+                    }
+                    item => rewrite_refs(item),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(rewrite_refs),
+        _ => {}
+    }
+}
+
+fn get_strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(|item| item.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// File parameters become binary strings.
+fn get_parameter_schema(parameter: &Map<String, Value>) -> Value {
+    let mut schema = Map::new();
+    for key in SCHEMA_KEYS {
+        if let Some(value) = parameter.get(key) {
+            schema.insert(key.to_string(), value.clone());
+        }
+    }
+    if parameter.get("type").and_then(Value::as_str) == Some("file") {
+        schema.insert("type".to_string(), json!("string"));
+        schema.insert("format".to_string(), json!("binary"));
+    }
+    Value::Object(schema)
+}
+
+fn convert_parameter(parameter: &Value) -> Value {
+    let Some(map) = parameter.as_object() else {
+        return parameter.clone();
+    };
+    if map.contains_key("$ref") {
+        return parameter.clone();
+    }
+    let mut converted = Map::new();
+    for key in ["name", "in", "description", "required"] {
+        if let Some(value) = map.get(key) {
+            converted.insert(key.to_string(), value.clone());
+        }
+    }
+    if let Some(example) = map.get("x-example") {
+        converted.insert("example".to_string(), example.clone());
+    }
+    converted.insert("schema".to_string(), get_parameter_schema(map));
+    Value::Object(converted)
+}
+
+fn get_location<'a>(parameter: &'a Value, definitions: &'a Map<String, Value>) -> Option<&'a str> {
+    let parameter = match parameter.get("$ref").and_then(Value::as_str) {
+        Some(reference) => definitions.get(reference.strip_prefix("#/components/parameters/")?)?,
+        None => parameter,
+    };
+    parameter.get("in").and_then(Value::as_str)
+}
+
+fn resolve<'a>(parameter: &'a Value, definitions: &'a Map<String, Value>) -> &'a Value {
+    parameter
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/components/parameters/"))
+        .and_then(|name| definitions.get(name))
+        .unwrap_or(parameter)
+}
+
+/// Turns the `body` or `formData` parameters of an operation into a `requestBody`.
+fn get_request_body(
+    parameters: &[&Value],
+    consumes: &[String],
+    definitions: &Map<String, Value>,
+) -> Option<Value> {
+    let body = parameters
+        .iter()
+        .map(|parameter| resolve(parameter, definitions))
+        .find(|parameter| parameter.get("in").and_then(Value::as_str) == Some("body"));
+    if let Some(body) = body {
+        let content_type = consumes
+            .iter()
+            .find(|content_type| content_type.contains("json"))
+            .or(consumes.first())
+            .map(String::as_str)
+            .unwrap_or("application/json");
+        let schema = body.get("schema").cloned().unwrap_or(json!({}));
+        return Some(json!({ "content": { content_type: { "schema": schema } } }));
+    }
+
+    let fields: Vec<&Value> = parameters
+        .iter()
+        .map(|parameter| resolve(parameter, definitions))
+        .filter(|parameter| parameter.get("in").and_then(Value::as_str) == Some("formData"))
+        .collect();
+    if fields.is_empty() {
+        return None;
+    }
+    let has_file = fields
+        .iter()
+        .any(|field| field.get("type").and_then(Value::as_str) == Some("file"));
+    let content_type = if has_file
+        || consumes
+            .iter()
+            .any(|content_type| content_type == "multipart/form-data")
+    {
+        "multipart/form-data"
+    } else {
+        "application/x-www-form-urlencoded"
+    };
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for field in fields {
+        let Some(map) = field.as_object() else {
+            continue;
+        };
+        let name = map.get("name").and_then(Value::as_str).unwrap_or_default();
+        let mut schema = get_parameter_schema(map);
+        if let Some(example) = map.get("x-example") {
+            schema["example"] = example.clone();
+        }
+        properties.insert(name.to_string(), schema);
+        if map.get("required").and_then(Value::as_bool) == Some(true) {
+            required.push(json!(name));
+        }
+    }
+    Some(json!({
+        "content": {
+            content_type: {
+                "schema": { "type": "object", "properties": properties, "required": required }
+            }
+        }
+    }))
+}
+
+/// The server built from `schemes`, `host` and `basePath`, or just the base path without a host.
+fn get_server_url(spec: &Value) -> String {
+    let base_path = spec.get("basePath").and_then(Value::as_str).unwrap_or("");
+    match spec.get("host").and_then(Value::as_str) {
+        Some(host) => {
+            let schemes = get_strings(spec.get("schemes"));
+            let scheme = if schemes.is_empty() || schemes.iter().any(|scheme| scheme == "https") {
+                "https"
+            } else {
+                &schemes[0]
+            };
+            format!("{}://{}{}", scheme, host, base_path)
+        }
+        None => base_path.to_string(),
+    }
+}
+
+/// Swagger 2.0 `basic` schemes are `http` schemes in OpenAPI 3, and the single `flow` of an
+/// `oauth2` scheme becomes its `flows`.
+fn convert_security_definitions(spec: &Value) -> Map<String, Value> {
+    spec.get("securityDefinitions")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(name, definition)| {
+            let mut scheme = definition.clone();
+            if definition.get("type").and_then(Value::as_str) == Some("basic") {
+                scheme["type"] = json!("http");
+                scheme["scheme"] = json!("basic");
+            }
+            let flow = match definition.get("flow").and_then(Value::as_str) {
+                Some("application") => Some("clientCredentials"),
+                Some("accessCode") => Some("authorizationCode"),
+                Some("password") => Some("password"),
+                Some("implicit") => Some("implicit"),
+                _ => None,
+            };
+            if let Some(flow) = flow {
+                let mut flow_object = Map::new();
+                for key in ["authorizationUrl", "tokenUrl", "scopes"] {
+                    if let Some(value) = definition.get(key) {
+                        flow_object.insert(key.to_string(), value.clone());
+                    }
+                }
+                scheme["flows"] = json!({ flow: flow_object });
+            }
+            (name.clone(), scheme)
+        })
+        .collect()
+}
+
+pub fn convert_to_openapi(mut spec: Value) -> Value {
+    rewrite_refs(&mut spec);
+    let consumes = get_strings(spec.get("consumes"));
+    let parameter_definitions = spec
+        .get("parameters")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let is_request_parameter = |parameter: &Value| {
+        matches!(
+            get_location(parameter, &parameter_definitions),
+            Some("body") | Some("formData")
+        )
+    };
+
+    let mut paths = Map::new();
+    for (path, item) in spec
+        .get("paths")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        let Some(item) = item.as_object() else {
+            continue;
+        };
+        let path_parameters: Vec<&Value> = item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .collect();
+        let mut converted_item = Map::new();
+        for (key, value) in item {
+            if key == "parameters" {
+                let parameters: Vec<Value> = path_parameters
+                    .iter()
+                    .filter(|parameter| !is_request_parameter(parameter))
+                    .map(|parameter| convert_parameter(parameter))
+                    .collect();
+                converted_item.insert(key.clone(), Value::Array(parameters));
+                continue;
+            }
+            let Some(operation) = value
+                .as_object()
+                .filter(|_| METHODS.contains(&key.as_str()))
+            else {
+                converted_item.insert(key.clone(), value.clone());
+                continue;
+            };
+            let operation_parameters: Vec<&Value> = operation
+                .get("parameters")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .collect();
+            let all_parameters: Vec<&Value> = path_parameters
+                .iter()
+                .chain(operation_parameters.iter())
+                .copied()
+                .collect();
+            let operation_consumes = match operation.get("consumes") {
+                Some(value) => get_strings(Some(value)),
+                None => consumes.clone(),
+            };
+
+            let mut converted = operation.clone();
+            converted.remove("consumes");
+            converted.remove("produces");
+            converted.insert(
+                "parameters".to_string(),
+                Value::Array(
+                    operation_parameters
+                        .iter()
+                        .filter(|parameter| !is_request_parameter(parameter))
+                        .map(|parameter| convert_parameter(parameter))
+                        .collect(),
+                ),
+            );
+            if let Some(request_body) =
+                get_request_body(&all_parameters, &operation_consumes, &parameter_definitions)
+            {
+                converted.insert("requestBody".to_string(), request_body);
+            }
+            converted_item.insert(key.clone(), Value::Object(converted));
+        }
+        paths.insert(path.clone(), Value::Object(converted_item));
+    }
+
+    let parameters: Map<String, Value> = parameter_definitions
+        .iter()
+        .filter(|(_, parameter)| !is_request_parameter(parameter))
+        .map(|(name, parameter)| (name.clone(), convert_parameter(parameter)))
+        .collect();
+    json!({
+        "openapi": "3.0.0",
+        "info": spec.get("info").cloned().unwrap_or(json!({ "title": "", "version": "" })),
+        "servers": [{ "url": get_server_url(&spec) }],
+        "security": spec.get("security").cloned().unwrap_or(json!([])),
+        "paths": paths,
+        "components": {
+            "schemas": spec.get("definitions").cloned().unwrap_or(json!({})),
+            "parameters": parameters,
+            "securitySchemes": convert_security_definitions(&spec),
+        },
+    })
+}
@@ -1,15 +1,16 @@
-use crate::openapi_structs::OpenAPI;
 use crate::structs::{ExtractVariable, GlobalVariable, HttpRequest};
 use dirs::home_dir;
 use serde_json::Value;
 use std::fs::{self, File};
 use std::io::{BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
 
 pub fn get_home_path() -> String {
     if let Some(path) = home_dir() {
         return path.to_str().unwrap().to_string();
     }
-    return "".to_string();
+    "".to_string()
 }
 
 pub fn get_http_requests_file_path() -> String {
@@ -20,6 +21,26 @@ pub fn get_global_variables_file_path() -> String {
     format!("{}/.xhtp/global_variables.json", get_home_path())
 }
 
+pub fn get_history_dir_path() -> String {
+    format!("{}/.xhtp/history", get_home_path())
+}
+
+pub fn get_snapshots_dir_path() -> String {
+    format!("{}/.xhtp/snapshots", get_home_path())
+}
+
+pub fn get_auth_file_path() -> String {
+    format!("{}/.xhtp/auth.json", get_home_path())
+}
+
+pub fn get_oauth2_tokens_file_path() -> String {
+    format!("{}/.xhtp/oauth2_tokens.json", get_home_path())
+}
+
+pub fn get_environment_file_path(name: &str) -> String {
+    format!("{}/.xhtp/environments/{}.json", get_home_path(), name)
+}
+
 pub fn print_line(text: &str) {
     println!("\x1b[94m{}\x1b[0m", text);
 }
@@ -30,17 +51,18 @@ pub fn print(text: &str) {
 
 pub fn get_url_with_https(url: &str) -> String {
     if url.starts_with("http") {
-        return url.to_string();
+        url.to_string()
     } else {
-        return format!("https://{}", url);
+        format!("https://{}", url)
     }
 }
 
-pub fn get_content_type_from_header(headers: &reqwest::header::HeaderMap) -> String {
-    let content_type = headers.get("content-type").unwrap();
-    let content_type = content_type.to_str().unwrap();
-    let content_type = content_type.split(";").collect::<Vec<&str>>();
-    content_type[0].to_string()
+pub fn build_client(timeout: Option<u64>, insecure: bool) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder().danger_accept_invalid_certs(insecure);
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(std::time::Duration::from_secs(timeout));
+    }
+    builder.build().expect("Failed to build HTTP client")
 }
 
 pub fn arg_is_number(option: &str) -> bool {
@@ -52,11 +74,21 @@ pub fn convert_option_to_number(option: &str) -> usize {
 }
 
 pub fn get_request_from_saved_requests(
-    saved_requests: &Vec<HttpRequest>,
+    saved_requests: &[HttpRequest],
     index: usize,
 ) -> &HttpRequest {
-    let request = &saved_requests[index - 1];
-    request
+    &saved_requests[index - 1]
+}
+
+/// Finds a saved request by its number or name and returns its 1-based number.
+pub fn find_request_index(saved_requests: &[HttpRequest], number_or_name: &str) -> Option<usize> {
+    if let Ok(index) = number_or_name.parse::<usize>() {
+        return (1..=saved_requests.len()).contains(&index).then_some(index);
+    }
+    saved_requests
+        .iter()
+        .position(|request| request.name.as_deref() == Some(number_or_name))
+        .map(|position| position + 1)
 }
 
 pub fn get_ansi_colored_request_method(method: &str) -> String {
@@ -64,6 +96,7 @@ pub fn get_ansi_colored_request_method(method: &str) -> String {
         "GET" => format!("\x1b[32m{}\x1b[0m", method),
         "POST" => format!("\x1b[33m{}\x1b[0m", method),
         "PUT" => format!("\x1b[34m{}\x1b[0m", method),
+        "PATCH" => format!("\x1b[35m{}\x1b[0m", method),
         "DELETE" => format!("\x1b[31m{}\x1b[0m", method),
         _ => method.to_string(),
     }
@@ -90,42 +123,94 @@ fn get_extract_variables_list(extract_variables: &Option<Vec<ExtractVariable>>)
     extract_variables_list
 }
 
-pub fn print_saved_requests(saved_requests: &Vec<HttpRequest>) {
+fn get_name_and_folder(request: &HttpRequest) -> String {
+    match (&request.name, &request.folder) {
+        (Some(name), Some(folder)) => format!(" ({}/{})", folder, name),
+        (Some(name), None) => format!(" ({})", name),
+        (None, Some(folder)) => format!(" ({}/)", folder),
+        (None, None) => String::new(),
+    }
+}
+
+fn get_removed_marker(request: &HttpRequest) -> &'static str {
+    match &request.origin {
+        Some(origin) if origin.removed => " \x1b[31m[removed from spec]\x1b[0m",
+        _ => "",
+    }
+}
+
+pub fn print_saved_requests(saved_requests: &[HttpRequest]) {
     for (index, request) in saved_requests.iter().enumerate() {
         println!(
-            "{}: {} {}",
+            "{}: {} {}{}{}",
             index + 1,
             get_ansi_colored_request_method(request.method.as_str()),
             request.url,
+            get_name_and_folder(request),
+            get_removed_marker(request),
         );
     }
 }
 
-pub fn print_full_saved_request_from_index(saved_requests: &Vec<HttpRequest>, index: usize) {
+pub fn print_full_saved_request_from_index(saved_requests: &[HttpRequest], index: usize) {
     let request = get_request_from_saved_requests(saved_requests, index);
     println!(
-        "{}: {} {}",
+        "{}: {} {}{}{}",
         index,
         get_ansi_colored_request_method(request.method.as_str()),
         request.url,
+        get_name_and_folder(request),
+        get_removed_marker(request),
     );
+    match (&request.auth, crate::auth::read_default()) {
+        (Some(auth), _) => {
+            print_line("Auth:");
+            println!("- {}", crate::auth::describe(auth));
+        }
+        (None, Some(default)) => {
+            print_line("Auth:");
+            println!("- {} (default)", crate::auth::describe(&default));
+        }
+        (None, None) => {}
+    }
+    if let Some(origin) = &request.origin {
+        print_line("Imported from:");
+        println!("- {} ({})", origin.source, origin.operation);
+    }
     if request.extract_variables.is_some() {
         print_line("Extract variables:");
         println!("{}", get_extract_variables_list(&request.extract_variables));
     }
+    if let Some(assertions) = &request.assertions {
+        print_line("Assertions:");
+        for assertion in assertions {
+            println!("- {}", crate::assertions::describe(assertion));
+        }
+    }
+    if let Some(snapshot) = &request.snapshot {
+        print_line("Snapshot:");
+        let key = crate::snapshots::SnapshotKey::default();
+        println!("- {}", crate::snapshots::get_snapshot_path(request, key));
+        if !snapshot.headers.is_empty() {
+            println!("- headers: {}", snapshot.headers.join(", "));
+        }
+        if !snapshot.redact.is_empty() {
+            println!("- redact: {}", snapshot.redact.join(", "));
+        }
+    }
     print_line("Headers:");
     for header in &request.headers {
         println!("- {}", header);
     }
-    if request.body.is_some() {
+    if let Some(body) = &request.body {
         print_line("Body:");
-        let json = serde_json::to_string_pretty(request.body.as_ref().unwrap()).unwrap();
+        let json = serde_json::to_string_pretty(body).unwrap();
         println!("{:#}", json);
     }
 }
 
 pub fn handle_delete(requests: &mut Vec<HttpRequest>) -> Result<(), Box<dyn std::error::Error>> {
-    print_saved_requests(&requests);
+    print_saved_requests(requests);
     print("Select the number of the request you want to delete: ");
     std::io::stdout().flush().unwrap();
     let mut number = String::new();
@@ -143,22 +228,98 @@ pub fn handle_delete(requests: &mut Vec<HttpRequest>) -> Result<(), Box<dyn std:
     let mut file = File::create(get_http_requests_file_path())?;
     file.write_all(json.as_bytes())?;
 
-    return Ok(());
+    Ok(())
 }
 
-pub fn too_big(saved_requests: &Vec<HttpRequest>) -> Result<(), reqwest::Error> {
+pub fn too_big(saved_requests: &[HttpRequest]) -> Result<(), reqwest::Error> {
     print_line("The number you passed is too big!");
     print_line("Here are your available options:");
-    print_saved_requests(&saved_requests);
-    return Ok(());
+    print_saved_requests(saved_requests);
+    Ok(())
 }
 
+/// Looks up a top level key, or a path such as `data.items[0].id` (`$.` prefix optional).
 pub fn get_json_value<'a>(json: &'a Value, key: &str) -> Option<&'a Value> {
-    json.get(key)
+    if let Some(value) = json.get(key) {
+        return Some(value);
+    }
+    let path = key
+        .trim_start_matches('$')
+        .replace('[', ".")
+        .replace(']', "");
+    let mut current = json;
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        current = match current {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => current.get(segment)?,
+        };
+    }
+    Some(current)
+}
+
+pub fn replace_variables(text: &str, variables: &[GlobalVariable]) -> String {
+    let mut text = text.to_string();
+    for variable in variables {
+        text = text.replace(&format!("{{{{{}}}}}", variable.key), &variable.value);
+    }
+    text
+}
+
+fn replace_variables_in_json(value: &Value, variables: &[GlobalVariable]) -> Value {
+    match value {
+        Value::String(text) => Value::String(replace_variables(text, variables)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| replace_variables_in_json(item, variables))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, item)| (key.clone(), replace_variables_in_json(item, variables)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Returns a copy of the request with `{{variable}}` placeholders filled in.
+pub fn resolve_request(request: &HttpRequest, variables: &[GlobalVariable]) -> HttpRequest {
+    let mut resolved = request.clone();
+    resolved.url = replace_variables(&request.url, variables);
+    resolved.headers = request
+        .headers
+        .iter()
+        .map(|header| replace_variables(header, variables))
+        .collect();
+    resolved.body = request
+        .body
+        .as_ref()
+        .map(|body| replace_variables_in_json(body, variables));
+    resolved.auth = request
+        .auth
+        .as_ref()
+        .map(|auth| crate::auth::resolve(auth, variables));
+    resolved
+}
+
+/// Strings are stored without their JSON quotes so they can be used anywhere in a request.
+pub fn json_value_to_variable(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub fn get_request_label(request: &HttpRequest) -> String {
+    match &request.name {
+        Some(name) => name.clone(),
+        None => format!("{} {}", request.method, request.url),
+    }
 }
 
 pub fn get_global_variables() -> Vec<GlobalVariable> {
-    if !File::open(get_global_variables_file_path()).is_ok() {
+    if File::open(get_global_variables_file_path()).is_err() {
         // File does not exist, create it
         let mut file =
             File::create(get_global_variables_file_path()).expect("Failed to create file");
@@ -172,7 +333,51 @@ pub fn get_global_variables() -> Vec<GlobalVariable> {
     global_variables
 }
 
+/// Reads the variables of an environment, stored like the global variables.
+pub fn read_environment(name: &str) -> Result<Vec<GlobalVariable>, String> {
+    let path = get_environment_file_path(name);
+    let content = fs::read_to_string(&path)
+        .map_err(|err| format!("Failed to read the environment {}: {}", path, err))?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("Failed to parse the environment {}: {}", path, err))
+}
+
+/// Adds the variables to an environment, replacing those with the same key.
+pub fn save_to_environment(name: &str, variables: &[GlobalVariable]) {
+    let mut environment = read_environment(name).unwrap_or_default();
+    for variable in variables {
+        match environment
+            .iter_mut()
+            .find(|saved| saved.key == variable.key)
+        {
+            Some(saved) => saved.value = variable.value.clone(),
+            None => environment.push(variable.clone()),
+        }
+    }
+    let path = get_environment_file_path(name);
+    if let Some(dir) = Path::new(&path).parent() {
+        fs::create_dir_all(dir).unwrap();
+    }
+    let json = serde_json::to_string(&environment).unwrap();
+    fs::write(path, json).unwrap();
+}
+
+/// Merges the variables of the environments, earlier environments take precedence.
+pub fn read_environments(names: &[String]) -> Result<Vec<GlobalVariable>, String> {
+    let mut variables = Vec::new();
+    for name in names {
+        variables.extend(read_environment(name)?);
+    }
+    Ok(variables)
+}
+
+/// Serializes updates of the global variables file between concurrent data rows.
+static GLOBAL_VARIABLES_LOCK: Mutex<()> = Mutex::new(());
+
 pub fn save_to_global_variables(key: String, value: String) {
+    let _lock = GLOBAL_VARIABLES_LOCK
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
     let mut global_variables = get_global_variables();
     let global_variable = GlobalVariable { key, value };
 
@@ -248,126 +453,8 @@ pub fn list_global_variables() {
 //    }
 //}
 
-pub fn map_open_api_spec_to_http_requests(base_url: &str, open_api: OpenAPI) -> Vec<HttpRequest> {
-    let mut requests = Vec::new();
-    for (path, path_item) in open_api.paths {
-        if path_item.get.is_some() {
-            let mut url = format!("{}{}", base_url, path);
-            let mut parameters = None;
-
-            let operation = path_item.get.unwrap();
-            if operation.parameters.is_some() {
-                parameters = operation.parameters;
-            }
-            if parameters.is_some() {
-                for parameter in parameters.unwrap() {
-                    if parameter.required.is_some() && parameter.required.unwrap() {
-                        url = url.replace(&format!("{{{}}}", parameter.name), "1");
-                    } else {
-                        url = url.replace(&format!("{{{}}}", parameter.name), "0");
-                    }
-                }
-            }
-            let request = HttpRequest {
-                method: "GET".to_string(),
-                url,
-                headers: Vec::new(),
-                body_type: None,
-                body: None,
-                extract_variables: None,
-            };
-            requests.push(request);
-        }
-
-        if path_item.post.is_some() {
-            let mut url = format!("{}{}", base_url, path);
-            let mut parameters = None;
-
-            let operation = path_item.post.unwrap();
-            if operation.parameters.is_some() {
-                parameters = operation.parameters;
-            }
-            if parameters.is_some() {
-                for parameter in parameters.unwrap() {
-                    if parameter.required.is_some() && parameter.required.unwrap() {
-                        url = url.replace(&format!("{{{}}}", parameter.name), "1");
-                    } else {
-                        url = url.replace(&format!("{{{}}}", parameter.name), "0");
-                    }
-                }
-            }
-            let request = HttpRequest {
-                method: "POST".to_string(),
-                url,
-                headers: Vec::new(),
-                body_type: None,
-                body: None,
-                extract_variables: None,
-            };
-            requests.push(request);
-        }
-
-        if path_item.put.is_some() {
-            let mut url = format!("{}{}", base_url, path);
-            let mut parameters = None;
-
-            let operation = path_item.put.unwrap();
-            if operation.parameters.is_some() {
-                parameters = operation.parameters;
-            }
-            if parameters.is_some() {
-                for parameter in parameters.unwrap() {
-                    if parameter.required.is_some() && parameter.required.unwrap() {
-                        url = url.replace(&format!("{{{}}}", parameter.name), "1");
-                    } else {
-                        url = url.replace(&format!("{{{}}}", parameter.name), "0");
-                    }
-                }
-            }
-            let request = HttpRequest {
-                method: "PUT".to_string(),
-                url,
-                headers: Vec::new(),
-                body_type: None,
-                body: None,
-                extract_variables: None,
-            };
-            requests.push(request);
-        }
-
-        if path_item.delete.is_some() {
-            let mut url = format!("{}{}", base_url, path);
-            let mut parameters = None;
-
-            let operation = path_item.delete.unwrap();
-            if operation.parameters.is_some() {
-                parameters = operation.parameters;
-            }
-            if parameters.is_some() {
-                for parameter in parameters.unwrap() {
-                    if parameter.required.is_some() && parameter.required.unwrap() {
-                        url = url.replace(&format!("{{{}}}", parameter.name), "1");
-                    } else {
-                        url = url.replace(&format!("{{{}}}", parameter.name), "0");
-                    }
-                }
-            }
-            let request = HttpRequest {
-                method: "DELETE".to_string(),
-                url,
-                headers: Vec::new(),
-                body_type: None,
-                body: None,
-                extract_variables: None,
-            };
-            requests.push(request);
-        }
-    }
-    return requests;
-}
-
 pub fn read_http_request_file() -> Vec<HttpRequest> {
-    if !File::open(get_http_requests_file_path()).is_ok() {
+    if File::open(get_http_requests_file_path()).is_err() {
         if let Err(err) = fs::create_dir(format!("{}/.xhtp", get_home_path())) {
             eprintln!("Error creating directory: {}", err);
         }
@@ -382,52 +469,8 @@ pub fn read_http_request_file() -> Vec<HttpRequest> {
     requests
 }
 
-pub fn merge_requests(
-    saved_requests: &Vec<HttpRequest>,
-    imported_requests: &Vec<HttpRequest>,
-) -> Vec<HttpRequest> {
-    let mut merged_requests = saved_requests.clone();
-
-    for imported_request in imported_requests {
-        if !merged_requests.contains(imported_request) {
-            merged_requests.push(HttpRequest {
-                method: imported_request.method.clone(),
-                url: imported_request.url.clone(),
-                headers: imported_request.headers.clone(),
-                body_type: imported_request.body_type.clone(),
-                body: imported_request.body.clone(),
-                extract_variables: None,
-            });
-        }
-    }
-
-    return merged_requests;
-}
-
-pub async fn handle_open_api_sepc_import(spec_url: &str) -> Result<(), reqwest::Error> {
-    let base_url = spec_url.split("/").collect::<Vec<&str>>()[0..3].join("/");
-    println!("{}", base_url);
-    let spec_url = get_url_with_https(spec_url);
-    let spec = reqwest::get(spec_url).await?.text().await?;
-    let spec: OpenAPI = serde_json::from_str(&spec).unwrap();
-    let imported_requests = map_open_api_spec_to_http_requests(&base_url, spec);
-    let saved_requests = read_http_request_file();
-
-    let merged_requests = merge_requests(&saved_requests, &imported_requests);
-
-    print_line("Saving requests...");
-
-    let json = serde_json::to_string_pretty(&merged_requests).unwrap();
-
-    print_line("Created json");
-
+pub fn write_http_request_file(requests: &[HttpRequest]) {
+    let json = serde_json::to_string_pretty(requests).unwrap();
     let mut file = File::create(get_http_requests_file_path()).unwrap();
-
-    print_line("Created file");
-
     file.write_all(json.as_bytes()).unwrap();
-
-    print_line("Wrote to file");
-
-    return Ok(());
 }